@@ -10,6 +10,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::configure()
         .build_server(true)
         .build_client(true)
+        // Messages are reused by the REST API as JSON payloads
+        .type_attribute(
+            ".auction",
+            "#[derive(serde::Serialize, serde::Deserialize)] #[serde(default)]",
+        )
         .file_descriptor_set_path(out_dir.join("auction_descriptor.bin"))
         .out_dir(out_dir)
         .compile(protos, proto_dir)?;
@@ -0,0 +1,233 @@
+use cdrs_tokio::query_values;
+use chrono::Utc;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use crate::core::error::Error;
+use crate::core::orm::batch::Batch;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::core::pagination::PaginationParams;
+use crate::core::realm::parse_realm;
+use crate::core::validation::Validate;
+use crate::models::trade::{Trade, TradeStatus, TRADE_ALL_COLUMNS, TRADE_TABLE};
+use crate::models::trade_flag::{TradeFlag, TRADE_FLAG_ALL_COLUMNS, TRADE_FLAG_TABLE};
+use crate::proto::{
+    auction_admin_server::AuctionAdmin, CancelScheduledTradeRequest, CancelScheduledTradeResponse,
+    GetRealmVisibilityRequest, GetRealmVisibilityResponse, ListScheduledTradesRequest,
+    ListScheduledTradesResponse, ListTradeFlagsRequest, ListTradeFlagsResponse,
+    SetRealmVisibilityRequest, SetRealmVisibilityResponse, Trade as TradeDetail,
+    TradeFlag as TradeFlagDetail,
+};
+use crate::services::mailbox::{get_cancellation_mail, record_mail};
+use crate::services::outbox::record_event;
+use crate::services::realm_visibility::RealmVisibility;
+use crate::services::trade_events::{TradeEvent, TradeEventBus, TradeEventKind};
+
+pub struct AdminServiceImpl {
+    db: CassandraSession,
+    events: TradeEventBus,
+    realm_visibility: RealmVisibility,
+}
+
+impl AdminServiceImpl {
+    pub fn new(
+        db: CassandraSession,
+        events: TradeEventBus,
+        realm_visibility: RealmVisibility,
+    ) -> Self {
+        Self {
+            db,
+            events,
+            realm_visibility,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl AuctionAdmin for AdminServiceImpl {
+    async fn list_trade_flags(
+        &self,
+        request: Request<ListTradeFlagsRequest>,
+    ) -> Result<Response<ListTradeFlagsResponse>, Status> {
+        request.validate()?;
+        let params = request.into_inner();
+
+        let mut query_builder = QueryBuilder::new(&TRADE_FLAG_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_FLAG_ALL_COLUMNS)
+            .allow_filtering(true);
+        if let Some(kind) = params.kind {
+            query_builder =
+                query_builder.filter_by(Filter::new("kind", Operator::Eq, Some(kind.into())));
+        }
+        let query = query_builder.build();
+
+        let pagination_params = PaginationParams::new(params.page, params.page_size);
+        let flags = query
+            .get_paginated_entries::<TradeFlag>(&self.db, &pagination_params)
+            .await?;
+
+        Ok(Response::new(ListTradeFlagsResponse {
+            page: pagination_params.page,
+            page_size: pagination_params.page_size,
+            flags: flags.iter().map(TradeFlagDetail::from).collect(),
+        }))
+    }
+
+    async fn get_realm_visibility(
+        &self,
+        request: Request<GetRealmVisibilityRequest>,
+    ) -> Result<Response<GetRealmVisibilityResponse>, Status> {
+        request.validate()?;
+        let realm_id = parse_realm(&request.get_ref().realm_id)?;
+
+        let visible_realms = self.realm_visibility.get_visible_realms(&realm_id).await?;
+
+        Ok(Response::new(GetRealmVisibilityResponse {
+            realm_id,
+            visible_realms,
+        }))
+    }
+
+    async fn set_realm_visibility(
+        &self,
+        request: Request<SetRealmVisibilityRequest>,
+    ) -> Result<Response<SetRealmVisibilityResponse>, Status> {
+        request.validate()?;
+        let data = request.get_ref();
+        let realm_id = parse_realm(&data.realm_id)?;
+        let visible_realms = data
+            .visible_realms
+            .iter()
+            .map(|realm| parse_realm(realm))
+            .collect::<Result<Vec<String>, _>>()?;
+
+        let visible_realms = self
+            .realm_visibility
+            .set_visible_realms(&realm_id, &visible_realms)
+            .await?;
+
+        Ok(Response::new(SetRealmVisibilityResponse {
+            realm_id,
+            visible_realms,
+        }))
+    }
+
+    async fn list_scheduled_trades(
+        &self,
+        request: Request<ListScheduledTradesRequest>,
+    ) -> Result<Response<ListScheduledTradesResponse>, Status> {
+        request.validate()?;
+        let params = request.into_inner();
+
+        let mut query_builder = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "status",
+                Operator::Eq,
+                Some(TradeStatus::Scheduled.as_str().into()),
+            ))
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
+            .allow_filtering(true);
+        if let Some(realm_id) = &params.realm_id {
+            let realm_id = parse_realm(realm_id)?;
+            query_builder = query_builder.filter_by(Filter::new(
+                "realm_id",
+                Operator::Eq,
+                Some(realm_id.into()),
+            ));
+        }
+        let query = query_builder.build();
+
+        let pagination_params = PaginationParams::new(params.page, params.page_size);
+        let trades = query
+            .get_paginated_entries::<Trade>(&self.db, &pagination_params)
+            .await?;
+
+        Ok(Response::new(ListScheduledTradesResponse {
+            page: pagination_params.page,
+            page_size: pagination_params.page_size,
+            // Trades that already started, but weren't picked up by the
+            // scheduler yet, are on sale
+            trades: trades
+                .iter()
+                .filter(|trade| trade.is_pending())
+                .map(TradeDetail::from)
+                .collect(),
+        }))
+    }
+
+    async fn cancel_scheduled_trade(
+        &self,
+        request: Request<CancelScheduledTradeRequest>,
+    ) -> Result<Response<CancelScheduledTradeResponse>, Status> {
+        request.validate()?;
+        let data = request.get_ref();
+        let realm_id = parse_realm(&data.realm_id)?;
+        let trade_id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
+
+        let read_query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .limit(1)
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::Eq,
+                Some(realm_id.as_str().into()),
+            ))
+            .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
+            .allow_filtering(true)
+            .build();
+        let trade = read_query.get_instance::<Trade>(&self.db).await?;
+
+        // Trades on sale may have bids already, so they are cancelled
+        // by their sellers under the cancellation rules
+        if !trade.is_pending() {
+            return Err(Status::from(Error::ValidationError {
+                field: "id".to_string(),
+                message: "Only trades that didn't go on sale yet can be cancelled.".to_string(),
+            }));
+        }
+
+        let delete_query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Update)
+            .columns(&["is_deleted", "expired_at", "status"])
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::Eq,
+                Some(realm_id.as_str().into()),
+            ))
+            .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
+            .filter_by(Filter::new(
+                "item_id",
+                Operator::Eq,
+                Some(trade.item_id().into()),
+            ))
+            .filter_by(Filter::new(
+                "created_by",
+                Operator::Eq,
+                Some(trade.created_by().into()),
+            ))
+            .build();
+        let delete_query_values = query_values!(
+            "is_deleted" => true,
+            "expired_at" => Utc::now(),
+            "status" => TradeStatus::Cancelled.as_str()
+        );
+        let event = TradeEvent::new(TradeEventKind::Cancelled, &trade);
+        let batch = record_event(
+            Batch::new().add(&delete_query, &delete_query_values)?,
+            &event,
+        )?;
+        record_mail(batch, get_cancellation_mail(&trade, None)?)?
+            .execute(&self.db)
+            .await?;
+        self.events.publish(event);
+
+        Ok(Response::new(CancelScheduledTradeResponse {}))
+    }
+}
@@ -0,0 +1,99 @@
+use tonic::Request;
+use uuid::Uuid;
+
+use crate::core::error::Error;
+use crate::core::realm::parse_realm;
+use crate::core::validation::Validate;
+use crate::models::trade_flag::FlagKind;
+use crate::proto::{
+    CancelScheduledTradeRequest, GetRealmVisibilityRequest, ListScheduledTradesRequest,
+    ListTradeFlagsRequest, SetRealmVisibilityRequest,
+};
+
+// The maximum amount of other realms visible in a single realm. Every realm
+// adds a partition to read for each listing query.
+const MAX_VISIBLE_REALMS: usize = 16;
+
+impl Validate for Request<ListTradeFlagsRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if let Some(kind) = &data.kind {
+            if !FlagKind::ALL.iter().any(|value| value.as_str() == kind) {
+                return Err(Error::ValidationError {
+                    field: "kind".to_string(),
+                    message: format!("{0} is not a valid flag kind.", kind),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<GetRealmVisibilityRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        validate_realm("realm_id", &self.get_ref().realm_id)
+    }
+}
+
+impl Validate for Request<SetRealmVisibilityRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+        validate_realm("realm_id", &data.realm_id)?;
+
+        if data.visible_realms.len() > MAX_VISIBLE_REALMS {
+            return Err(Error::ValidationError {
+                field: "visible_realms".to_string(),
+                message: format!(
+                    "The realm can't see more than {0} other realms.",
+                    MAX_VISIBLE_REALMS
+                ),
+            });
+        }
+
+        for realm in data.visible_realms.iter() {
+            validate_realm("visible_realms", realm)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<ListScheduledTradesRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        match &self.get_ref().realm_id {
+            Some(realm_id) => validate_realm("realm_id", realm_id),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Validate for Request<CancelScheduledTradeRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+        validate_realm("realm_id", &data.realm_id)?;
+
+        if Uuid::try_parse(&data.id).is_err() {
+            return Err(Error::ValidationError {
+                field: "id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+// Reports invalid realms for the field of the request, instead of the
+// realm metadata.
+fn validate_realm(field: &str, value: &str) -> Result<(), Error> {
+    match parse_realm(value) {
+        Ok(_) => Ok(()),
+        Err(Error::ValidationError { message, .. }) => Err(Error::ValidationError {
+            field: field.to_string(),
+            message,
+        }),
+        Err(err) => Err(err),
+    }
+}
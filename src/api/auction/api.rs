@@ -1,53 +1,202 @@
+use std::pin::Pin;
+use std::sync::Arc;
+
 use cdrs_tokio::query_values;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use log::error;
+use tokio_stream::{Stream, StreamExt};
 use tonic::{Request, Response, Status};
 use uuid::Uuid;
 
 use crate::api::auction::filters::{
-    ItemBidPriceRangeFilter, ItemBuyoutPriceRangeFilter, ItemNameFilter,
+    CurrencyFilter, ItemBidPriceRangeFilter, ItemBuyoutPriceRangeFilter, ItemNameFilter,
 };
+use crate::api::auction::policies::cancellation::CancellationOutcome;
+use crate::api::auction::policies::AuctionPolicies;
 use crate::core::error::Error;
+use crate::core::money::Money;
+use crate::core::orm::batch::Batch;
 use crate::core::orm::filter::{CustomFilter, Filter, IntoCustomFilter, Operator};
 use crate::core::orm::query_builder::{QueryBuilder, QueryType};
 use crate::core::orm::session::CassandraSession;
 use crate::core::pagination::PaginationParams;
+use crate::core::realm::get_realm;
 use crate::core::validation::Validate;
-use crate::models::trade::{Trade, EMPTY_UUID, TRADE_ALL_COLUMNS, TRADE_TABLE};
+use crate::models::barter_offer::BarterOffer;
+use crate::models::inventory_change::InventoryChange;
+use crate::models::ledger::LedgerReason;
+use crate::models::saved_search::SavedSearch;
+use crate::models::trade::{
+    format_price, LineItem, Trade, TradeStatus, TRADE_ALL_COLUMNS, TRADE_TABLE,
+};
+use crate::models::trade_change::TradeChange;
 use crate::proto::{
-    auction_server::Auction, BidRequest, BidResponse, BuyoutRequest, BuyoutResponse,
-    CancelTradeRequest, CancelTradeResponse, CreateTradeRequest, CreateTradeResponse,
-    ListTradesRequest, ListTradesResponse, Trade as TradeDetail,
+    auction_server::Auction, AcceptBarterOfferRequest, AcceptBarterOfferResponse,
+    BarterOffer as BarterOfferDetail, BidRequest, BidResponse, BuyoutRequest, BuyoutResponse,
+    CancelTradeRequest, CancelTradeResponse, Candle as CandleDetail, ClaimAllMailRequest,
+    ClaimAllMailResponse, ClaimMailRequest, ClaimMailResponse, CreateSavedSearchRequest,
+    CreateSavedSearchResponse, CreateTradeRequest, CreateTradeResponse, DeleteSavedSearchRequest,
+    DeleteSavedSearchResponse, GetMarketPricesRequest, GetMarketPricesResponse,
+    GetPriceHistoryRequest, GetPriceHistoryResponse, ListBarterOffersRequest,
+    ListBarterOffersResponse, ListMailRequest, ListMailResponse, ListNotificationsRequest,
+    ListNotificationsResponse, ListPrivateOffersRequest, ListPrivateOffersResponse,
+    ListSavedSearchesRequest, ListSavedSearchesResponse, ListTradesRequest, ListTradesResponse,
+    ListWatchedTradesRequest, ListWatchedTradesResponse, Mail as MailDetail,
+    MarketPrice as MarketPriceDetail, Notification as NotificationDetail, OfferBarterRequest,
+    OfferBarterResponse, RejectBarterOfferRequest, RejectBarterOfferResponse,
+    SavedSearch as SavedSearchDetail, StreamTradeEventsRequest, SuggestPriceRequest,
+    SuggestPriceResponse, Trade as TradeDetail, TradeEvent as TradeEventDetail, UnwatchItemRequest,
+    UnwatchItemResponse, UnwatchTradeRequest, UnwatchTradeResponse, UpdateSavedSearchRequest,
+    UpdateSavedSearchResponse, WatchItemRequest, WatchItemResponse, WatchTradeRequest,
+    WatchTradeResponse, WatchedItem as WatchedItemDetail, WatchedTrade as WatchedTradeDetail,
+};
+use crate::services::barter::{covers_wanted_items, BarterOffers};
+use crate::services::inventory::InventoryHook;
+use crate::services::mailbox::{
+    get_barter_mail, get_buyout_mail, get_cancellation_mail, get_outbid_mail, Mailbox,
+};
+use crate::services::market_summary::MarketSummaries;
+use crate::services::notifications::Notifications;
+use crate::services::outbox::{get_outbox_entry, record_event};
+use crate::services::price_history::{build_candles, PriceHistory};
+use crate::services::price_suggestion::PriceAdvisor;
+use crate::services::realm_visibility::RealmVisibility;
+use crate::services::saved_searches::SavedSearches;
+use crate::services::settlement::{get_charge_entry, record_charge};
+use crate::services::trade_changes::TradeChanges;
+use crate::services::trade_events::{
+    get_closing_event, Subscription, TradeEvent, TradeEventBus, TradeEventKind,
 };
+use crate::services::trade_updates::{get_bid_update, get_open_trade_update, get_trade_update};
+use crate::services::watchlist::Watchlist;
 
 pub struct AuctionServiceImpl {
     db: CassandraSession,
+    price_history: PriceHistory,
+    price_advisor: PriceAdvisor,
+    market_summaries: MarketSummaries,
+    watchlist: Watchlist,
+    saved_searches: SavedSearches,
+    notifications: Notifications,
+    mailbox: Mailbox,
+    barter_offers: BarterOffers,
+    trade_changes: TradeChanges,
+    events: TradeEventBus,
+    policies: AuctionPolicies,
+    realm_visibility: RealmVisibility,
 }
 
 impl AuctionServiceImpl {
-    pub fn new(db: CassandraSession) -> Self {
-        Self { db }
+    pub fn new(
+        db: CassandraSession,
+        events: TradeEventBus,
+        policies: AuctionPolicies,
+        realm_visibility: RealmVisibility,
+        inventory: Arc<dyn InventoryHook>,
+    ) -> Self {
+        Self {
+            price_history: PriceHistory::new(db.clone()),
+            price_advisor: PriceAdvisor::new(db.clone()),
+            market_summaries: MarketSummaries::new(db.clone()),
+            watchlist: Watchlist::new(db.clone()),
+            saved_searches: SavedSearches::new(db.clone()),
+            notifications: Notifications::new(db.clone()),
+            mailbox: Mailbox::new(db.clone()),
+            barter_offers: BarterOffers::new(db.clone(), inventory),
+            trade_changes: TradeChanges::new(db.clone()),
+            db,
+            events,
+            policies,
+            realm_visibility,
+        }
+    }
+
+    async fn count_active_listings(&self, realm_id: &str, player_id: Uuid) -> Result<usize, Error> {
+        let query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+            .filter_by(Filter::new(
+                "created_by",
+                Operator::Eq,
+                Some(player_id.into()),
+            ))
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
+            .allow_filtering(true)
+            .build();
+        let trades = query.get_entries::<Trade>(&self.db).await?;
+
+        Ok(trades.iter().filter(|trade| !trade.is_expired()).count())
+    }
+
+    // Returns the open barter trade, that the seller answers offers for.
+    async fn get_own_barter_trade(
+        &self,
+        realm_id: &str,
+        trade_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Trade, Error> {
+        let read_query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .limit(1)
+            .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+            .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
+            .allow_filtering(true)
+            .build();
+        let trade = read_query.get_instance::<Trade>(&self.db).await?;
+
+        if user_id != trade.created_by() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: "Only the owner can answer the barter offers.".to_string(),
+            });
+        }
+
+        if !trade.is_barter() {
+            return Err(Error::ValidationError {
+                field: "id".to_string(),
+                message: "The trade doesn't accept barter offers.".to_string(),
+            });
+        }
+
+        Ok(trade)
     }
 }
 
 #[tonic::async_trait]
 impl Auction for AuctionServiceImpl {
+    type StreamTradeEventsStream =
+        Pin<Box<dyn Stream<Item = Result<TradeEventDetail, Status>> + Send>>;
+
     async fn list_trades(
         &self,
         request: Request<ListTradesRequest>,
     ) -> Result<Response<ListTradesResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let visible_realms = self.realm_visibility.get_visible_realms(&realm_id).await?;
         let params = request.into_inner();
         let filter_params = params.filter_params.unwrap_or_default();
+        let user_id = params
+            .user_id
+            .as_deref()
+            .map(|user_id| Uuid::parse_str(user_id).expect("parse valid uuid from request"));
 
         let item_name_filter = ItemNameFilter::new(&filter_params).into_custom_filter();
         let item_bid_price_filter =
             ItemBidPriceRangeFilter::new(&filter_params).into_custom_filter();
         let item_buyout_price_filter =
             ItemBuyoutPriceRangeFilter::new(&filter_params).into_custom_filter();
+        let currency = CurrencyFilter::new(&filter_params);
+        let currency_filter = currency.into_custom_filter();
 
         let backend_filters: Vec<&CustomFilter> = vec![
             &item_name_filter,
             &item_bid_price_filter,
             &item_buyout_price_filter,
+            &currency_filter,
         ]
         .iter()
         .filter(|f| f.is_some())
@@ -58,22 +207,26 @@ impl Auction for AuctionServiceImpl {
             .query_type(QueryType::Select)
             .columns(&TRADE_ALL_COLUMNS)
             .allow_filtering(true)
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::In,
+                Some(visible_realms.into()),
+            ))
             .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
             .custom_filters(&backend_filters)
             .build();
 
         let pagination_params = PaginationParams::new(params.page, params.page_size);
         let trades = query
-            .get_paginated_entries::<Trade>(&self.db, &pagination_params)
+            .get_paginated_entries_where::<Trade, _>(&self.db, &pagination_params, |trade| {
+                trade.is_listed_for(user_id) && currency.accepts(trade)
+            })
             .await?;
 
         Ok(Response::new(ListTradesResponse {
             page: pagination_params.page,
             page_size: pagination_params.page_size,
-            trades: trades
-                .iter()
-                .map(|trade| TradeDetail::from(trade))
-                .collect(),
+            trades: trades.iter().map(TradeDetail::from).collect(),
         }))
     }
 
@@ -82,78 +235,156 @@ impl Auction for AuctionServiceImpl {
         request: Request<CreateTradeRequest>,
     ) -> Result<Response<CreateTradeResponse>, Status> {
         request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let created_by = Uuid::parse_str(&data.created_by).expect("parse valid uuid from request");
+        let faction = data.faction.to_owned();
+        let trade = Trade::from(request.into_inner()).with_realm(&realm_id);
+        self.policies.currencies.check_listing(&trade)?;
+        let house = self.policies.houses.check_listing(&trade, &faction)?;
+        let deposit = house.get_deposit(&trade)?;
+        let trade = trade.with_fees(deposit, house.commission_percent());
+
+        // The slot is kept until the trade is stored
+        let listing_slot = self.policies.limits.reserve_active_listing(created_by);
+        if self.policies.limits.is_active_listings_limited() {
+            let active_listings = self.count_active_listings(&realm_id, created_by).await?;
+            self.policies
+                .limits
+                .check_active_listings(&listing_slot, active_listings)?;
+        }
+        let listing = self.policies.limits.reserve_listing(created_by)?;
 
-        let trade = Trade::from(request.into_inner());
+        let item_id = trade.item_id();
+        // Scheduled trades are announced by the scheduler, once they go on sale
+        let event = match trade.is_pending() {
+            true => TradeEvent::new(TradeEventKind::Scheduled, &trade),
+            false => TradeEvent::new(TradeEventKind::Listed, &trade),
+        };
         let query = QueryBuilder::new(&TRADE_TABLE)
             .query_type(QueryType::Insert)
             .columns(&TRADE_ALL_COLUMNS)
             .build();
+        let mut batch = Batch::new();
+        if trade.deposit().is_positive() {
+            batch = record_charge(
+                batch,
+                created_by,
+                &trade,
+                trade.deposit(),
+                LedgerReason::ListingDeposit,
+            )?;
+        }
         let query_values = trade.into_query_values();
-        query.insert(&self.db, &query_values).await;
+        record_event(batch.add(&query, &query_values)?, &event)?
+            .execute(&self.db)
+            .await?;
+        listing.confirm();
+        drop(listing_slot);
+        self.market_summaries.refresh(&realm_id, item_id).await;
+        self.events.publish(event);
 
         Ok(Response::new(CreateTradeResponse {}))
     }
 
     async fn bid(&self, request: Request<BidRequest>) -> Result<Response<BidResponse>, Status> {
         request.validate()?;
+        let realm_id = get_realm(&request)?;
         let data = request.get_ref();
         let trade_id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
         let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+        let bid = self.policies.limits.reserve_bid(user_id)?;
+        // Players can bid only on trades of the realms visible to them
+        let visible_realms = self.realm_visibility.get_visible_realms(&realm_id).await?;
 
         let read_query = QueryBuilder::new(&TRADE_TABLE)
             .query_type(QueryType::Select)
             .columns(&TRADE_ALL_COLUMNS)
             .limit(1)
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::In,
+                Some(visible_realms.into()),
+            ))
             .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
             .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
             .allow_filtering(true)
             .build();
-        let trade = read_query.get_instance::<Trade>(&self.db).await?;
+        let mut trade = read_query.get_instance::<Trade>(&self.db).await?;
+        if trade.is_barter() {
+            return Err(Status::from(Error::ValidationError {
+                field: "id".to_string(),
+                message: "The barter trade accepts only barter offers.".to_string(),
+            }));
+        }
+        self.policies.bidding.check(&trade, user_id)?;
+        self.policies
+            .currencies
+            .check_payment(&trade, &data.currency)?;
+        self.policies.houses.check_bidder(&trade, &data.faction)?;
+        self.trade_changes.finish_pending(&mut trade).await?;
 
-        if data.amount <= trade.bid_price() {
+        let amount = Money::new(data.amount);
+        if amount <= trade.bid_price() {
             return Err(Status::from(Error::ValidationError {
                 field: "amount".to_string(),
-                message: "The bid can't be less that the current price.".to_string(),
+                message: format!(
+                    "The bid can't be less that the current price of {0}.",
+                    format_price(trade.bid_price(), trade.currency())
+                ),
             }));
         }
 
-        if data.amount >= trade.buyout_price() {
+        if amount >= trade.buyout_price() {
             return Err(Status::from(Error::ValidationError {
                 field: "amount".to_string(),
-                message: "The bid can't be greater that the buyout price.".to_string(),
+                message: format!(
+                    "The bid can't be greater that the buyout price of {0}.",
+                    format_price(trade.buyout_price(), trade.currency())
+                ),
             }));
         }
 
-        let update_query = QueryBuilder::new(&TRADE_TABLE)
-            .query_type(QueryType::Update)
-            .columns(&["bid_price", "bought_by", "bought_by_username"])
-            .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
-            .filter_by(Filter::new(
-                "item_id",
-                Operator::Eq,
-                Some(trade.item_id().into()),
-            ))
-            .filter_by(Filter::new(
-                "created_by",
-                Operator::Eq,
-                Some(trade.created_by().into()),
-            ))
-            .build();
+        // Only the bid that replaced the previous one refunds it
+        let event = TradeEvent::new(TradeEventKind::Bid, &trade).with_bid(user_id, amount);
+        let outbid_event = event
+            .previous_bidder()
+            .map(|_| event.clone().with_kind(TradeEventKind::Outbid));
+        let mut change = TradeChange::new(&trade)
+            .with_event(get_outbox_entry(&event))
+            .with_mail(get_outbid_mail(&trade));
+        if let Some(outbid_event) = &outbid_event {
+            change = change.with_event(get_outbox_entry(outbid_event));
+        }
+        let pending_change = self.trade_changes.prepare(&change).await?;
+
+        let update_query = get_bid_update(&trade).build();
         let update_query_values = query_values!(
-            "bid_price" => data.amount,
+            "bid_price" => amount,
             "bought_by" => user_id,
-            "bought_by_username" => data.username.to_owned()
+            "bought_by_username" => data.username.to_owned(),
+            "pending_change" => pending_change.to_owned()
         );
-        update_query
-            .update(&self.db, &update_query_values)
-            .await
-            .map_err(|_| {
-                Error::CassandraError(String::from(
-                    "The item expired or was bought by other player.",
-                ))
-            })?;
+        let applied = update_query
+            .update_if(&self.db, &update_query_values)
+            .await?;
+        if !applied {
+            self.trade_changes.discard(&change).await;
+            return Err(Status::from(Error::Conflict(String::from(
+                "The item expired or was bought by other player.",
+            ))));
+        }
+
+        bid.confirm();
+        self.trade_changes.finish(&change, &pending_change).await;
 
-        // TODO: Return currency to the latest bidder
+        self.market_summaries
+            .refresh(trade.realm_id(), trade.item_id())
+            .await;
+        if let Some(outbid_event) = outbid_event {
+            self.events.publish(outbid_event);
+        }
+        self.events.publish(event);
 
         Ok(Response::new(BidResponse {}))
     }
@@ -163,65 +394,98 @@ impl Auction for AuctionServiceImpl {
         request: Request<BuyoutRequest>,
     ) -> Result<Response<BuyoutResponse>, Status> {
         request.validate()?;
+        let realm_id = get_realm(&request)?;
         let data = request.get_ref();
         let trade_id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
         let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+        let visible_realms = self.realm_visibility.get_visible_realms(&realm_id).await?;
 
         let read_query = QueryBuilder::new(&TRADE_TABLE)
             .query_type(QueryType::Select)
             .columns(&TRADE_ALL_COLUMNS)
             .limit(1)
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::In,
+                Some(visible_realms.into()),
+            ))
             .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
             .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
             .allow_filtering(true)
             .build();
-        let trade = read_query.get_instance::<Trade>(&self.db).await?;
+        let mut trade = read_query.get_instance::<Trade>(&self.db).await?;
+        if trade.is_barter() {
+            return Err(Status::from(Error::ValidationError {
+                field: "id".to_string(),
+                message: "The barter trade accepts only barter offers.".to_string(),
+            }));
+        }
+        self.policies.bidding.check(&trade, user_id)?;
+        self.policies
+            .currencies
+            .check_payment(&trade, &data.currency)?;
+        self.policies.houses.check_bidder(&trade, &data.faction)?;
+        self.trade_changes.finish_pending(&mut trade).await?;
 
-        if data.amount != trade.buyout_price() {
+        let amount = Money::new(data.amount);
+        if amount != trade.buyout_price() {
             return Err(Status::from(Error::ValidationError {
                 field: "amount".to_string(),
-                message: "The amount of currency must correspond to the buyout price.".to_string(),
+                message: format!(
+                    "The amount of currency must correspond to the buyout price of {0}.",
+                    format_price(trade.buyout_price(), trade.currency())
+                ),
             }));
         }
 
-        let update_query = QueryBuilder::new(&TRADE_TABLE)
-            .query_type(QueryType::Update)
-            .columns(&[
+        let event = TradeEvent::new(TradeEventKind::BoughtOut, &trade).with_bid(user_id, amount);
+        let change = TradeChange::new(&trade)
+            .with_event(get_outbox_entry(&event))
+            .with_mail(get_buyout_mail(&trade, user_id, amount)?);
+        let pending_change = self.trade_changes.prepare(&change).await?;
+
+        let update_query = get_open_trade_update(
+            &trade,
+            &[
+                "bid_price",
                 "bought_by",
                 "bought_by_username",
                 "is_deleted",
                 "expired_at",
-            ])
-            .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
-            .filter_by(Filter::new(
-                "item_id",
-                Operator::Eq,
-                Some(trade.item_id().into()),
-            ))
-            .filter_by(Filter::new(
-                "created_by",
-                Operator::Eq,
-                Some(trade.created_by().into()),
-            ))
-            .build();
+                "status",
+                "pending_change",
+            ],
+        )
+        .build();
+        let sold_at = Utc::now();
         let update_query_values = query_values!(
-            "bid_price" => data.amount,
+            "bid_price" => amount,
             "bought_by" => user_id,
             "bought_by_username" => data.username.to_owned(),
             "is_deleted" => true,
-            "expired_at" => Utc::now()
+            "expired_at" => sold_at,
+            "status" => TradeStatus::Sold.as_str(),
+            "pending_change" => pending_change.to_owned()
         );
-        update_query
-            .update(&self.db, &update_query_values)
-            .await
-            .map_err(|_| {
-                Error::CassandraError(String::from(
-                    "The item expired or was bought by other player.",
-                ))
-            })?;
+        let applied = update_query
+            .update_if(&self.db, &update_query_values)
+            .await?;
+        if !applied {
+            self.trade_changes.discard(&change).await;
+            return Err(Status::from(Error::Conflict(String::from(
+                "The item expired or was bought by other player.",
+            ))));
+        }
+
+        self.trade_changes.finish(&change, &pending_change).await;
 
-        // TODO: Return currency to the latest bidder
-        // TODO: Add the item to buyer's inventory
+        self.price_history
+            .record_sale(&trade, amount, sold_at)
+            .await;
+        self.market_summaries
+            .refresh(trade.realm_id(), trade.item_id())
+            .await;
+        self.events.publish(event);
 
         Ok(Response::new(BuyoutResponse {}))
     }
@@ -230,6 +494,8 @@ impl Auction for AuctionServiceImpl {
         &self,
         request: Request<CancelTradeRequest>,
     ) -> Result<Response<CancelTradeResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
         let data = request.get_ref();
         let trade_id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
         let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
@@ -238,18 +504,16 @@ impl Auction for AuctionServiceImpl {
             .query_type(QueryType::Select)
             .columns(&TRADE_ALL_COLUMNS)
             .limit(1)
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::Eq,
+                Some(realm_id.as_str().into()),
+            ))
             .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
             .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
             .allow_filtering(true)
             .build();
-        let trade = read_query.get_instance::<Trade>(&self.db).await?;
-
-        if trade.bought_by() != *EMPTY_UUID {
-            return Err(Status::from(Error::ValidationError {
-                field: "bought_by".to_string(),
-                message: "The trade can't be deleted when someone did a bid.".to_string(),
-            }));
-        }
+        let mut trade = read_query.get_instance::<Trade>(&self.db).await?;
 
         if user_id != trade.created_by() {
             return Err(Status::from(Error::ValidationError {
@@ -257,37 +521,680 @@ impl Auction for AuctionServiceImpl {
                 message: "Only the owner can delete the trade.".to_string(),
             }));
         }
+        self.trade_changes.finish_pending(&mut trade).await?;
 
-        let delete_query = QueryBuilder::new(&TRADE_TABLE)
-            .query_type(QueryType::Update)
-            .columns(&["is_deleted"])
-            .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
+        // The mail is built before the trade is closed, so that the trade
+        // with corrupt items stays open
+        let (mail, penalty) = match self.policies.cancellation.evaluate(&trade)? {
+            CancellationOutcome::Free => (get_cancellation_mail(&trade, None)?, Money::ZERO),
+            CancellationOutcome::Penalized {
+                bidder,
+                refund,
+                penalty,
+            } => (
+                get_cancellation_mail(&trade, Some((bidder, refund)))?,
+                penalty,
+            ),
+        };
+
+        let event = TradeEvent::new(TradeEventKind::Cancelled, &trade);
+        let mut change = TradeChange::new(&trade)
+            .with_event(get_outbox_entry(&event))
+            .with_mail(mail);
+        if penalty != Money::ZERO {
+            change = change.with_charge(get_charge_entry(
+                trade.created_by(),
+                &trade,
+                penalty,
+                LedgerReason::CancellationPenalty,
+            )?);
+        }
+        let pending_change = self.trade_changes.prepare(&change).await?;
+
+        let delete_query = get_trade_update(
+            &trade,
+            &["is_deleted", "expired_at", "status", "pending_change"],
+        )
+        .build();
+        let delete_query_values = query_values!(
+            "is_deleted" => true,
+            "expired_at" => Utc::now(),
+            "status" => TradeStatus::Cancelled.as_str(),
+            "pending_change" => pending_change.to_owned()
+        );
+        let applied = delete_query
+            .update_if(&self.db, &delete_query_values)
+            .await?;
+        if !applied {
+            self.trade_changes.discard(&change).await;
+            return Err(Status::from(Error::Conflict(String::from(
+                "The item expired or was bought by other player.",
+            ))));
+        }
+
+        self.trade_changes.finish(&change, &pending_change).await;
+
+        self.market_summaries
+            .refresh(trade.realm_id(), trade.item_id())
+            .await;
+        if trade.is_barter() {
+            self.barter_offers.reject_pending(&trade).await;
+        }
+        self.events.publish(event);
+
+        Ok(Response::new(CancelTradeResponse {
+            penalty: penalty.into(),
+        }))
+    }
+
+    async fn get_price_history(
+        &self,
+        request: Request<GetPriceHistoryRequest>,
+    ) -> Result<Response<GetPriceHistoryResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let item_id = Uuid::parse_str(&data.item_id).expect("parse valid uuid from request");
+        let from = DateTime::from_timestamp(data.from, 0).expect("valid period start");
+        let to = DateTime::from_timestamp(data.to, 0).expect("valid period end");
+
+        let sales = self
+            .price_history
+            .get_sales(&realm_id, item_id, from, to)
+            .await?;
+        let candles = build_candles(&sales, data.from, data.interval);
+
+        Ok(Response::new(GetPriceHistoryResponse {
+            candles: candles.iter().map(CandleDetail::from).collect(),
+        }))
+    }
+
+    async fn suggest_price(
+        &self,
+        request: Request<SuggestPriceRequest>,
+    ) -> Result<Response<SuggestPriceResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let item_id = Uuid::parse_str(&data.item_id).expect("parse valid uuid from request");
+
+        let suggestion = self
+            .price_advisor
+            .suggest(&realm_id, item_id, i64::from(data.quantity))
+            .await?;
+
+        Ok(Response::new(SuggestPriceResponse::from(&suggestion)))
+    }
+
+    async fn get_market_prices(
+        &self,
+        request: Request<GetMarketPricesRequest>,
+    ) -> Result<Response<GetMarketPricesResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let item_ids = request
+            .get_ref()
+            .item_ids
+            .iter()
+            .map(|item_id| Uuid::parse_str(item_id).expect("parse valid uuid from request"))
+            .collect::<Vec<Uuid>>();
+
+        let summaries = self.market_summaries.get_many(&realm_id, &item_ids).await?;
+        let prices = item_ids
+            .iter()
+            .map(|item_id| {
+                summaries
+                    .iter()
+                    .find(|summary| summary.item_id() == *item_id)
+                    .map(MarketPriceDetail::from)
+                    .unwrap_or_else(|| MarketPriceDetail {
+                        item_id: item_id.to_string(),
+                        ..Default::default()
+                    })
+            })
+            .collect();
+
+        Ok(Response::new(GetMarketPricesResponse { prices }))
+    }
+
+    async fn watch_trade(
+        &self,
+        request: Request<WatchTradeRequest>,
+    ) -> Result<Response<WatchTradeResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let trade_id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        let read_query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .limit(1)
             .filter_by(Filter::new(
-                "item_id",
+                "realm_id",
                 Operator::Eq,
-                Some(trade.item_id().into()),
+                Some(realm_id.as_str().into()),
+            ))
+            .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
+            .allow_filtering(true)
+            .build();
+        let trade = read_query.get_instance::<Trade>(&self.db).await?;
+
+        self.watchlist.watch(user_id, &trade).await?;
+
+        Ok(Response::new(WatchTradeResponse {}))
+    }
+
+    async fn unwatch_trade(
+        &self,
+        request: Request<UnwatchTradeRequest>,
+    ) -> Result<Response<UnwatchTradeResponse>, Status> {
+        request.validate()?;
+        let data = request.get_ref();
+        let trade_id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        self.watchlist.unwatch(user_id, trade_id).await?;
+
+        Ok(Response::new(UnwatchTradeResponse {}))
+    }
+
+    async fn list_watched_trades(
+        &self,
+        request: Request<ListWatchedTradesRequest>,
+    ) -> Result<Response<ListWatchedTradesResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        let watched_trades = self
+            .watchlist
+            .get_watched_trades(&realm_id, user_id)
+            .await?;
+        let watched_items = self.watchlist.get_watched_items(&realm_id, user_id).await?;
+
+        Ok(Response::new(ListWatchedTradesResponse {
+            trades: watched_trades
+                .iter()
+                .map(|(entry, trade)| WatchedTradeDetail::from((entry, trade)))
+                .collect(),
+            items: watched_items
+                .iter()
+                .map(|(item, trades)| WatchedItemDetail::from((item, trades)))
+                .collect(),
+        }))
+    }
+
+    async fn watch_item(
+        &self,
+        request: Request<WatchItemRequest>,
+    ) -> Result<Response<WatchItemResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let item_id = Uuid::parse_str(&data.item_id).expect("parse valid uuid from request");
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        self.watchlist
+            .watch_item(&realm_id, user_id, item_id)
+            .await?;
+
+        Ok(Response::new(WatchItemResponse {}))
+    }
+
+    async fn unwatch_item(
+        &self,
+        request: Request<UnwatchItemRequest>,
+    ) -> Result<Response<UnwatchItemResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let item_id = Uuid::parse_str(&data.item_id).expect("parse valid uuid from request");
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        self.watchlist
+            .unwatch_item(&realm_id, user_id, item_id)
+            .await?;
+
+        Ok(Response::new(UnwatchItemResponse {}))
+    }
+
+    async fn create_saved_search(
+        &self,
+        request: Request<CreateSavedSearchRequest>,
+    ) -> Result<Response<CreateSavedSearchResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+        let filter_params = data.filter_params.to_owned().unwrap_or_default();
+
+        let saved_search = SavedSearch::new(&realm_id, user_id, &data.name, &filter_params);
+        let saved_search_detail = SavedSearchDetail::from(&saved_search);
+        self.saved_searches.create(saved_search).await?;
+
+        Ok(Response::new(CreateSavedSearchResponse {
+            saved_search: Some(saved_search_detail),
+        }))
+    }
+
+    async fn update_saved_search(
+        &self,
+        request: Request<UpdateSavedSearchRequest>,
+    ) -> Result<Response<UpdateSavedSearchResponse>, Status> {
+        request.validate()?;
+        let data = request.get_ref();
+        let id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+        let filter_params = data.filter_params.to_owned().unwrap_or_default();
+
+        let saved_search = self
+            .saved_searches
+            .get(user_id, id)
+            .await?
+            .with_params(&data.name, &filter_params);
+        let saved_search_detail = SavedSearchDetail::from(&saved_search);
+        self.saved_searches.save(saved_search).await?;
+
+        Ok(Response::new(UpdateSavedSearchResponse {
+            saved_search: Some(saved_search_detail),
+        }))
+    }
+
+    async fn delete_saved_search(
+        &self,
+        request: Request<DeleteSavedSearchRequest>,
+    ) -> Result<Response<DeleteSavedSearchResponse>, Status> {
+        request.validate()?;
+        let data = request.get_ref();
+        let id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        self.saved_searches.delete(user_id, id).await?;
+
+        Ok(Response::new(DeleteSavedSearchResponse {}))
+    }
+
+    async fn list_saved_searches(
+        &self,
+        request: Request<ListSavedSearchesRequest>,
+    ) -> Result<Response<ListSavedSearchesResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        let saved_searches = self.saved_searches.get_player_searches(user_id).await?;
+
+        Ok(Response::new(ListSavedSearchesResponse {
+            saved_searches: saved_searches
+                .iter()
+                .filter(|saved_search| saved_search.realm_id() == realm_id)
+                .map(SavedSearchDetail::from)
+                .collect(),
+        }))
+    }
+
+    async fn list_notifications(
+        &self,
+        request: Request<ListNotificationsRequest>,
+    ) -> Result<Response<ListNotificationsResponse>, Status> {
+        request.validate()?;
+        let params = request.into_inner();
+        let user_id = Uuid::parse_str(&params.user_id).expect("parse valid uuid from request");
+
+        let pagination_params = PaginationParams::new(params.page, params.page_size);
+        let notifications = self
+            .notifications
+            .get_notifications(user_id, &pagination_params)
+            .await?;
+
+        Ok(Response::new(ListNotificationsResponse {
+            page: pagination_params.page,
+            page_size: pagination_params.page_size,
+            notifications: notifications.iter().map(NotificationDetail::from).collect(),
+        }))
+    }
+
+    async fn list_private_offers(
+        &self,
+        request: Request<ListPrivateOffersRequest>,
+    ) -> Result<Response<ListPrivateOffersResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let visible_realms = self.realm_visibility.get_visible_realms(&realm_id).await?;
+        let params = request.into_inner();
+        let user_id = Uuid::parse_str(&params.user_id).expect("parse valid uuid from request");
+
+        let query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .allow_filtering(true)
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::In,
+                Some(visible_realms.into()),
             ))
             .filter_by(Filter::new(
-                "created_by",
+                "reserved_for",
                 Operator::Eq,
-                Some(trade.created_by().into()),
+                Some(user_id.into()),
             ))
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
             .build();
-        let delete_query_values = query_values!(
+
+        let pagination_params = PaginationParams::new(params.page, params.page_size);
+        // Trades that aren't on sale yet or anymore are hidden
+        let trades = query
+            .get_paginated_entries_where::<Trade, _>(&self.db, &pagination_params, |trade| {
+                !trade.is_pending() && !trade.is_expired()
+            })
+            .await?;
+
+        Ok(Response::new(ListPrivateOffersResponse {
+            page: pagination_params.page,
+            page_size: pagination_params.page_size,
+            trades: trades.iter().map(TradeDetail::from).collect(),
+        }))
+    }
+
+    async fn list_mail(
+        &self,
+        request: Request<ListMailRequest>,
+    ) -> Result<Response<ListMailResponse>, Status> {
+        request.validate()?;
+        let data = request.get_ref();
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        let mail = self.mailbox.get_mail(user_id).await?;
+
+        Ok(Response::new(ListMailResponse {
+            mail: mail.iter().map(MailDetail::from).collect(),
+        }))
+    }
+
+    async fn claim_mail(
+        &self,
+        request: Request<ClaimMailRequest>,
+    ) -> Result<Response<ClaimMailResponse>, Status> {
+        request.validate()?;
+        let data = request.get_ref();
+        let id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        let (mail, delivered) = self.mailbox.claim(user_id, id).await?;
+
+        Ok(Response::new(ClaimMailResponse {
+            mail: Some(MailDetail::from(&mail)),
+            delivered,
+        }))
+    }
+
+    async fn claim_all_mail(
+        &self,
+        request: Request<ClaimAllMailRequest>,
+    ) -> Result<Response<ClaimAllMailResponse>, Status> {
+        request.validate()?;
+        let data = request.get_ref();
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        let mail = self.mailbox.claim_all(user_id).await?;
+
+        Ok(Response::new(ClaimAllMailResponse {
+            mail: mail.iter().map(MailDetail::from).collect(),
+        }))
+    }
+
+    async fn stream_trade_events(
+        &self,
+        request: Request<StreamTradeEventsRequest>,
+    ) -> Result<Response<Self::StreamTradeEventsStream>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let subscription = match (&data.trade_id, &data.user_id) {
+            (Some(trade_id), _) => Subscription::Trade(
+                Uuid::parse_str(trade_id).expect("parse valid uuid from request"),
+            ),
+            (_, Some(user_id)) => Subscription::Player(
+                Uuid::parse_str(user_id).expect("parse valid uuid from request"),
+            ),
+            _ => unreachable!("validated request"),
+        };
+
+        let events = self.events.subscribe(subscription.clone());
+
+        // The trade can be closed before the subscription started, so its
+        // stream ends right away with the event that closed the trade
+        if let Subscription::Trade(trade_id) = subscription {
+            let read_query = QueryBuilder::new(&TRADE_TABLE)
+                .query_type(QueryType::Select)
+                .columns(&TRADE_ALL_COLUMNS)
+                .limit(1)
+                .filter_by(Filter::new(
+                    "realm_id",
+                    Operator::Eq,
+                    Some(realm_id.as_str().into()),
+                ))
+                .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
+                .allow_filtering(true)
+                .build();
+            let trade = read_query.get_instance::<Trade>(&self.db).await?;
+
+            if let Some(event) = get_closing_event(&trade) {
+                let closed = tokio_stream::once(Ok(TradeEventDetail::from(&event)));
+                return Ok(Response::new(Box::pin(closed)));
+            }
+        }
+
+        let events = events
+            .filter(move |event| match event {
+                Ok(event) => event.realm_id() == realm_id,
+                // Errors are passed through, so that the client learns why
+                // the stream was closed
+                Err(_) => true,
+            })
+            .map(get_event_detail);
+
+        Ok(Response::new(Box::pin(events)))
+    }
+
+    async fn offer_barter(
+        &self,
+        request: Request<OfferBarterRequest>,
+    ) -> Result<Response<OfferBarterResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let trade_id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+        let bid = self.policies.limits.reserve_bid(user_id)?;
+        let visible_realms = self.realm_visibility.get_visible_realms(&realm_id).await?;
+
+        let read_query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .limit(1)
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::In,
+                Some(visible_realms.into()),
+            ))
+            .filter_by(Filter::new("id", Operator::Eq, Some(trade_id.into())))
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
+            .allow_filtering(true)
+            .build();
+        let trade = read_query.get_instance::<Trade>(&self.db).await?;
+        if !trade.is_barter() {
+            return Err(Status::from(Error::ValidationError {
+                field: "id".to_string(),
+                message: "The trade doesn't accept barter offers.".to_string(),
+            }));
+        }
+        self.policies.bidding.check(&trade, user_id)?;
+        self.policies.houses.check_bidder(&trade, &data.faction)?;
+
+        let items: Vec<LineItem> = data.items.iter().cloned().map(LineItem::from).collect();
+        if !covers_wanted_items(&trade.wanted_items()?, &items) {
+            return Err(Status::from(Error::ValidationError {
+                field: "items".to_string(),
+                message: "The offer must include every wanted item of the trade.".to_string(),
+            }));
+        }
+
+        let offer = BarterOffer::new(&trade, user_id, &data.username, &items);
+        let offer_id = self.barter_offers.offer(offer).await?;
+        bid.confirm();
+
+        Ok(Response::new(OfferBarterResponse {
+            offer_id: offer_id.to_string(),
+        }))
+    }
+
+    async fn list_barter_offers(
+        &self,
+        request: Request<ListBarterOffersRequest>,
+    ) -> Result<Response<ListBarterOffersResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let trade_id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        let trade = self
+            .get_own_barter_trade(&realm_id, trade_id, user_id)
+            .await?;
+        let offers = self.barter_offers.list(trade.realm_id(), trade_id).await?;
+
+        Ok(Response::new(ListBarterOffersResponse {
+            offers: offers.iter().map(BarterOfferDetail::from).collect(),
+        }))
+    }
+
+    async fn accept_barter_offer(
+        &self,
+        request: Request<AcceptBarterOfferRequest>,
+    ) -> Result<Response<AcceptBarterOfferResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let trade_id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
+        let offer_id = Uuid::parse_str(&data.offer_id).expect("parse valid uuid from request");
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
+
+        let mut trade = self
+            .get_own_barter_trade(&realm_id, trade_id, user_id)
+            .await?;
+        if trade.is_expired() {
+            return Err(Status::from(Error::ValidationError {
+                field: "id".to_string(),
+                message: "The trade has expired.".to_string(),
+            }));
+        }
+        self.trade_changes.finish_pending(&mut trade).await?;
+        let offer = self
+            .barter_offers
+            .get(trade.realm_id(), trade_id, offer_id)
+            .await?;
+        let event = TradeEvent::new(TradeEventKind::BoughtOut, &trade)
+            .with_bid(offer.offered_by(), Money::ZERO);
+        let change = TradeChange::new(&trade)
+            .with_event(get_outbox_entry(&event))
+            .with_mail(get_barter_mail(&trade, offer.offered_by())?)
+            .with_inventory_change(InventoryChange::transfer(offer.id(), trade.created_by()));
+        let pending_change = self.trade_changes.prepare(&change).await?;
+        if !self.barter_offers.accept(&offer).await? {
+            self.trade_changes.discard(&change).await;
+            return Err(Status::from(Error::ValidationError {
+                field: "offer_id".to_string(),
+                message: "The offer was already resolved.".to_string(),
+            }));
+        }
+
+        let update_query = get_open_trade_update(
+            &trade,
+            &[
+                "bought_by",
+                "bought_by_username",
+                "is_deleted",
+                "expired_at",
+                "status",
+                "pending_change",
+            ],
+        )
+        .build();
+        let update_query_values = query_values!(
+            "bought_by" => offer.offered_by(),
+            "bought_by_username" => offer.offered_by_username().to_string(),
             "is_deleted" => true,
-            "expired_at" => Utc::now()
+            "expired_at" => Utc::now(),
+            "status" => TradeStatus::Sold.as_str(),
+            "pending_change" => pending_change.to_owned()
         );
-        delete_query
-            .update(&self.db, &delete_query_values)
-            .await
-            .map_err(|_| {
-                Error::CassandraError(String::from(
-                    "The item expired or was bought by other player.",
-                ))
-            })?;
+        if !update_query
+            .update_if(&self.db, &update_query_values)
+            .await?
+        {
+            // The trade expired, was cancelled or sold for another offer
+            self.trade_changes.discard(&change).await;
+            self.barter_offers.withdraw_acceptance(&offer).await?;
+            return Err(Status::from(Error::Conflict(String::from(
+                "The item expired or was bought by other player.",
+            ))));
+        }
+
+        self.trade_changes.finish(&change, &pending_change).await;
+
+        if let Err(err) = self.barter_offers.deliver(&offer, trade.created_by()).await {
+            error!(
+                "Can't deliver the items of the barter offer {0}, the transfer will be retried: {1}",
+                offer.id(),
+                err
+            );
+        }
+        self.barter_offers.reject_pending(&trade).await;
+        self.events.publish(event);
+
+        Ok(Response::new(AcceptBarterOfferResponse {}))
+    }
+
+    async fn reject_barter_offer(
+        &self,
+        request: Request<RejectBarterOfferRequest>,
+    ) -> Result<Response<RejectBarterOfferResponse>, Status> {
+        request.validate()?;
+        let realm_id = get_realm(&request)?;
+        let data = request.get_ref();
+        let trade_id = Uuid::parse_str(&data.id).expect("parse valid uuid from request");
+        let offer_id = Uuid::parse_str(&data.offer_id).expect("parse valid uuid from request");
+        let user_id = Uuid::parse_str(&data.user_id).expect("parse valid uuid from request");
 
-        // TODO: Return an item to an inventory
+        let trade = self
+            .get_own_barter_trade(&realm_id, trade_id, user_id)
+            .await?;
+        let offer = self
+            .barter_offers
+            .get(trade.realm_id(), trade_id, offer_id)
+            .await?;
+        if !self.barter_offers.reject(&offer).await? {
+            return Err(Status::from(Error::ValidationError {
+                field: "offer_id".to_string(),
+                message: "The offer was already resolved.".to_string(),
+            }));
+        }
 
-        Ok(Response::new(CancelTradeResponse {}))
+        Ok(Response::new(RejectBarterOfferResponse {}))
     }
 }
+
+// The tonic stream items are sent to the client as they are, so the status
+// can't be boxed to make the result smaller.
+#[allow(clippy::result_large_err)]
+fn get_event_detail(event: Result<TradeEvent, Error>) -> Result<TradeEventDetail, Status> {
+    event
+        .map(|event| TradeEventDetail::from(&event))
+        .map_err(Status::from)
+}
@@ -1,5 +1,7 @@
-use crate::core::orm::filter::Operator::{Gte, LikeContains, Lte};
+use crate::core::money::Money;
+use crate::core::orm::filter::Operator::{Eq, Gte, LikeContains, Lte};
 use crate::core::orm::filter::{CustomFilter, Filter, IntoCustomFilter};
+use crate::models::trade::{get_currency, Trade, DEFAULT_CURRENCY};
 use crate::proto::FilterParams;
 
 pub struct ItemNameFilter<'a> {
@@ -48,7 +50,7 @@ impl<'a> IntoCustomFilter<'a> for ItemBidPriceRangeFilter<'a> {
                 filters.push(Filter::new(
                     "bid_price",
                     Gte,
-                    Some(min_price.to_owned().into()),
+                    Some(Money::new(*min_price).into()),
                 ));
             }
             _ => {}
@@ -59,7 +61,7 @@ impl<'a> IntoCustomFilter<'a> for ItemBidPriceRangeFilter<'a> {
                 filters.push(Filter::new(
                     "bid_price",
                     Lte,
-                    Some(max_price.to_owned().into()),
+                    Some(Money::new(*max_price).into()),
                 ));
             }
             _ => {}
@@ -94,7 +96,7 @@ impl<'a> IntoCustomFilter<'a> for ItemBuyoutPriceRangeFilter<'a> {
                 filters.push(Filter::new(
                     "buyout_price",
                     Gte,
-                    Some(min_buyout_price.to_owned().into()),
+                    Some(Money::new(*min_buyout_price).into()),
                 ));
             }
             _ => {}
@@ -105,7 +107,7 @@ impl<'a> IntoCustomFilter<'a> for ItemBuyoutPriceRangeFilter<'a> {
                 filters.push(Filter::new(
                     "buyout_price",
                     Lte,
-                    Some(max_buyout_price.to_owned().into()),
+                    Some(Money::new(*max_buyout_price).into()),
                 ));
             }
             _ => {}
@@ -120,3 +122,76 @@ impl<'a> IntoCustomFilter<'a> for ItemBuyoutPriceRangeFilter<'a> {
         }
     }
 }
+
+#[derive(Clone, Copy)]
+pub struct CurrencyFilter<'a> {
+    params: &'a FilterParams,
+}
+
+// Trades created before currencies were introduced have no currency stored,
+// and Cassandra can't match the missing value. So the default currency is
+// checked only after the trades are read.
+impl<'a> CurrencyFilter<'a> {
+    pub fn new(params: &'a FilterParams) -> Self {
+        Self { params }
+    }
+
+    pub fn accepts(&self, trade: &Trade) -> bool {
+        match &self.params.currency {
+            Some(currency) => trade.currency() == get_currency(currency),
+            None => true,
+        }
+    }
+}
+
+impl<'a> IntoCustomFilter<'a> for CurrencyFilter<'a> {
+    fn into_custom_filter(self) -> Option<CustomFilter<'a>> {
+        match &self.params.currency {
+            Some(currency) if get_currency(currency) != DEFAULT_CURRENCY => {
+                let instance = CustomFilter::new(vec![Filter::new(
+                    "currency",
+                    Eq,
+                    Some(get_currency(currency).into()),
+                )]);
+                Some(instance)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::api::auction::filters::CurrencyFilter;
+    use crate::core::orm::filter::IntoCustomFilter;
+    use crate::models::trade::Trade;
+    use crate::proto::FilterParams;
+
+    fn create_params(currency: &str) -> FilterParams {
+        FilterParams {
+            currency: Some(currency.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_currency_is_checked_after_reading() {
+        let params = create_params("Gold");
+        let filter = CurrencyFilter::new(&params);
+
+        assert!(filter.accepts(&Trade::builder().currency("").build()));
+        assert!(!filter.accepts(&Trade::builder().currency("gems").build()));
+        assert!(CurrencyFilter::new(&params).into_custom_filter().is_none());
+    }
+
+    #[test]
+    fn test_other_currencies_are_filtered_by_cassandra() {
+        let params = create_params("gems");
+        let filter = CurrencyFilter::new(&params);
+
+        assert!(filter.accepts(&Trade::builder().currency("gems").build()));
+        assert!(!filter.accepts(&Trade::builder().currency("").build()));
+        assert!(CurrencyFilter::new(&params).into_custom_filter().is_some());
+    }
+}
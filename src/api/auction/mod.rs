@@ -1,4 +1,5 @@
 pub mod api;
 pub mod filters;
+pub mod policies;
 
 pub mod validators;
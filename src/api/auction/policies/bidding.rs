@@ -0,0 +1,198 @@
+use std::str::FromStr;
+
+use log::warn;
+use uuid::Uuid;
+
+use crate::cli::CliOptions;
+use crate::core::error::{Error, Result};
+use crate::models::trade::Trade;
+use crate::services::account_links::{AccountLinks, StaticAccountLinks};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkedBidAction {
+    // Rejects bids between linked accounts.
+    Block,
+    // Accepts bids between linked accounts, but reports them in logs.
+    Log,
+}
+
+impl FromStr for LinkedBidAction {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "block" => Ok(LinkedBidAction::Block),
+            "log" => Ok(LinkedBidAction::Log),
+            _ => Err(format!(
+                "{0} is not a valid action, use `block` or `log`",
+                value
+            )),
+        }
+    }
+}
+
+// Prevents sellers from bidding up their own trades, directly or via linked
+// accounts, rejects bids on scheduled trades until they go on sale and on
+// expired trades, that weren't closed yet, and accepts bids on reserved trades only from the player they're reserved for.
+pub struct BiddingPolicy {
+    account_links: Box<dyn AccountLinks>,
+    linked_bid_action: LinkedBidAction,
+}
+
+impl BiddingPolicy {
+    pub fn new(account_links: Box<dyn AccountLinks>, linked_bid_action: LinkedBidAction) -> Self {
+        Self {
+            account_links,
+            linked_bid_action,
+        }
+    }
+
+    pub fn check(&self, trade: &Trade, bidder: Uuid) -> Result<()> {
+        if let Some(starts_at) = trade.starts_at().filter(|_| trade.is_pending()) {
+            return Err(Error::ValidationError {
+                field: "id".to_string(),
+                message: format!("The trade goes on sale at {0}.", starts_at.to_rfc3339()),
+            });
+        }
+
+        if trade.is_expired() {
+            return Err(Error::ValidationError {
+                field: "id".to_string(),
+                message: "The trade has expired.".to_string(),
+            });
+        }
+
+        if trade
+            .reserved_for()
+            .is_some_and(|reserved_for| reserved_for != bidder)
+        {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: "The trade is reserved for another player.".to_string(),
+            });
+        }
+
+        if bidder == trade.created_by() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: "The owner can't bid on own trade.".to_string(),
+            });
+        }
+
+        if let Some(link) = self.account_links.find_link(trade.created_by(), bidder) {
+            match self.linked_bid_action {
+                LinkedBidAction::Block => {
+                    return Err(Error::ValidationError {
+                        field: "user_id".to_string(),
+                        message: "The account is linked with the trade owner.".to_string(),
+                    });
+                }
+                LinkedBidAction::Log => {
+                    warn!(
+                        "Bid from {0} on the trade of the linked account {1} ({2})",
+                        bidder,
+                        trade.created_by(),
+                        link
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn create_bidding_policy(opts: &CliOptions) -> BiddingPolicy {
+    let account_links = match &opts.linked_accounts_file {
+        Some(path) => StaticAccountLinks::from_file(path),
+        None => StaticAccountLinks::new(),
+    };
+
+    BiddingPolicy::new(Box::new(account_links), opts.linked_bid_action)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use crate::api::auction::policies::bidding::{BiddingPolicy, LinkedBidAction};
+    use crate::models::trade::Trade;
+    use crate::services::account_links::StaticAccountLinks;
+
+    #[test]
+    fn test_self_bid_is_rejected() {
+        let seller = Uuid::new_v4();
+        let policy = BiddingPolicy::new(Box::new(StaticAccountLinks::new()), LinkedBidAction::Log);
+        let trade = Trade::builder().created_by(seller).build();
+
+        assert!(policy.check(&trade, seller).is_err());
+    }
+
+    #[test]
+    fn test_bid_from_linked_account_is_blocked() {
+        let seller = Uuid::new_v4();
+        let alt = Uuid::new_v4();
+        let links = StaticAccountLinks::new().with_group("same_owner", &[seller, alt]);
+        let policy = BiddingPolicy::new(Box::new(links), LinkedBidAction::Block);
+        let trade = Trade::builder().created_by(seller).build();
+
+        assert!(policy.check(&trade, alt).is_err());
+        assert!(policy.check(&trade, Uuid::new_v4()).is_ok());
+    }
+
+    #[test]
+    fn test_bid_from_linked_account_is_logged() {
+        let seller = Uuid::new_v4();
+        let alt = Uuid::new_v4();
+        let links = StaticAccountLinks::new().with_group("same_ip_hash", &[seller, alt]);
+        let policy = BiddingPolicy::new(Box::new(links), LinkedBidAction::Log);
+        let trade = Trade::builder().created_by(seller).build();
+
+        assert!(policy.check(&trade, alt).is_ok());
+    }
+
+    #[test]
+    fn test_bid_on_scheduled_trade_is_rejected() {
+        let policy = BiddingPolicy::new(Box::new(StaticAccountLinks::new()), LinkedBidAction::Log);
+        let scheduled = |starts_at: i64| Trade::builder().starts_at(starts_at).build();
+        let pending = scheduled((Utc::now() + Duration::hours(1)).timestamp());
+        let started = scheduled((Utc::now() - Duration::hours(1)).timestamp());
+
+        assert!(pending.is_pending());
+        assert!(policy.check(&pending, Uuid::new_v4()).is_err());
+        assert!(!started.is_pending());
+        assert!(policy.check(&started, Uuid::new_v4()).is_ok());
+    }
+
+    #[test]
+    fn test_bid_on_expired_trade_is_rejected() {
+        let policy = BiddingPolicy::new(Box::new(StaticAccountLinks::new()), LinkedBidAction::Log);
+        let trade = Trade::builder().build();
+        let expired = Trade::builder().build().with_lifetime(
+            Utc::now() - Duration::hours(2),
+            Utc::now() - Duration::hours(1),
+        );
+
+        assert!(policy.check(&trade, Uuid::new_v4()).is_ok());
+        assert!(policy.check(&expired, Uuid::new_v4()).is_err());
+    }
+
+    #[test]
+    fn test_reserved_trade_accepts_only_its_buyer() {
+        let policy = BiddingPolicy::new(Box::new(StaticAccountLinks::new()), LinkedBidAction::Log);
+        let (seller, buyer) = (Uuid::new_v4(), Uuid::new_v4());
+        let trade = Trade::builder()
+            .created_by(seller)
+            .reserved_for(Some(buyer))
+            .build();
+
+        assert!(policy.check(&trade, buyer).is_ok());
+        assert!(policy.check(&trade, Uuid::new_v4()).is_err());
+        assert!(trade.is_listed_for(Some(seller)));
+        assert!(!trade.is_listed_for(Some(buyer)));
+        assert!(!trade.is_listed_for(None));
+        let listed = Trade::builder().created_by(seller).build();
+        assert!(listed.is_listed_for(None));
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::cli::CliOptions;
+use crate::core::error::{Error, Result};
+use crate::core::money::Money;
+use crate::models::trade::{Trade, EMPTY_UUID};
+
+#[derive(Debug, PartialEq)]
+pub enum CancellationOutcome {
+    // Nobody did a bid yet, so the trade can be cancelled for free.
+    Free,
+    // The top bidder gets the whole bid back and the seller pays the penalty.
+    Penalized {
+        bidder: Uuid,
+        refund: Money,
+        penalty: Money,
+    },
+}
+
+pub trait CancellationPolicy: Send + Sync {
+    fn evaluate(&self, trade: &Trade) -> Result<CancellationOutcome>;
+}
+
+// Refuses to cancel a trade once someone did a bid.
+pub struct NoBidsPolicy;
+
+impl CancellationPolicy for NoBidsPolicy {
+    fn evaluate(&self, trade: &Trade) -> Result<CancellationOutcome> {
+        if trade.bought_by() != *EMPTY_UUID {
+            return Err(Error::ValidationError {
+                field: "bought_by".to_string(),
+                message: "The trade can't be deleted when someone did a bid.".to_string(),
+            });
+        }
+
+        Ok(CancellationOutcome::Free)
+    }
+}
+
+// Allows to cancel a trade with bids for a penalty, calculated
+// as a percent of the current bid price.
+pub struct PenaltyPolicy {
+    penalty_percent: i64,
+}
+
+impl PenaltyPolicy {
+    pub fn new(penalty_percent: i64) -> Self {
+        Self { penalty_percent }
+    }
+}
+
+impl CancellationPolicy for PenaltyPolicy {
+    fn evaluate(&self, trade: &Trade) -> Result<CancellationOutcome> {
+        if trade.bought_by() == *EMPTY_UUID {
+            return Ok(CancellationOutcome::Free);
+        }
+
+        let refund = trade.bid_price();
+        let penalty = refund.percent(self.penalty_percent)?;
+
+        Ok(CancellationOutcome::Penalized {
+            bidder: trade.bought_by(),
+            refund,
+            penalty,
+        })
+    }
+}
+
+// Picks the policy by the item category, with a fallback to the default one.
+pub struct CategoryPolicy {
+    default: Box<dyn CancellationPolicy>,
+    overrides: HashMap<String, Box<dyn CancellationPolicy>>,
+}
+
+impl CategoryPolicy {
+    pub fn new(default: Box<dyn CancellationPolicy>) -> Self {
+        Self {
+            default,
+            overrides: HashMap::new(),
+        }
+    }
+
+    pub fn with_override(mut self, category: &str, policy: Box<dyn CancellationPolicy>) -> Self {
+        self.overrides.insert(category.to_lowercase(), policy);
+        self
+    }
+}
+
+impl CancellationPolicy for CategoryPolicy {
+    fn evaluate(&self, trade: &Trade) -> Result<CancellationOutcome> {
+        self.overrides
+            .get(&trade.item_category().to_lowercase())
+            .unwrap_or(&self.default)
+            .evaluate(trade)
+    }
+}
+
+// Parses the penalty percent, that can't exceed the refunded bid.
+pub fn parse_penalty_percent(value: &str) -> std::result::Result<i64, String> {
+    value
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|percent| (0..=100).contains(percent))
+        .ok_or_else(|| {
+            format!(
+                "{0} is not a valid penalty percent, use a number from 0 to 100",
+                value
+            )
+        })
+}
+
+pub fn create_cancellation_policy(opts: &CliOptions) -> Box<dyn CancellationPolicy> {
+    if !opts.cancel_with_bids {
+        return Box::new(NoBidsPolicy);
+    }
+
+    let default = Box::new(PenaltyPolicy::new(opts.cancel_penalty_percent));
+    let policy = opts
+        .cancel_with_bids_disabled_categories
+        .split(',')
+        .map(|category| category.trim())
+        .filter(|category| !category.is_empty())
+        .fold(CategoryPolicy::new(default), |policy, category| {
+            policy.with_override(category, Box::new(NoBidsPolicy))
+        });
+
+    Box::new(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::api::auction::policies::cancellation::{
+        parse_penalty_percent, CancellationOutcome, CancellationPolicy, CategoryPolicy,
+        NoBidsPolicy, PenaltyPolicy,
+    };
+    use crate::core::money::Money;
+    use crate::models::trade::Trade;
+
+    #[test]
+    fn test_no_bids_policy_allows_cancel_without_bids() {
+        let trade = Trade::builder().item_category("weapon").build();
+
+        assert_eq!(
+            NoBidsPolicy.evaluate(&trade).unwrap(),
+            CancellationOutcome::Free
+        );
+    }
+
+    #[test]
+    fn test_no_bids_policy_refuses_cancel_with_bids() {
+        let trade = Trade::builder().item_category("weapon").build().with_bid(
+            Uuid::new_v4(),
+            "bidder",
+            200,
+        );
+
+        assert!(NoBidsPolicy.evaluate(&trade).is_err());
+    }
+
+    #[test]
+    fn test_penalty_policy_refunds_bidder_and_charges_seller() {
+        let bidder = Uuid::new_v4();
+        let trade = Trade::builder()
+            .item_category("weapon")
+            .build()
+            .with_bid(bidder, "bidder", 250);
+
+        assert_eq!(
+            PenaltyPolicy::new(10).evaluate(&trade).unwrap(),
+            CancellationOutcome::Penalized {
+                bidder,
+                refund: Money::new(250),
+                penalty: Money::new(25),
+            }
+        );
+    }
+
+    #[test]
+    fn test_category_policy_uses_override_for_category() {
+        let policy = CategoryPolicy::new(Box::new(PenaltyPolicy::new(10)))
+            .with_override("Mount", Box::new(NoBidsPolicy));
+        let weapon = Trade::builder().item_category("weapon").build().with_bid(
+            Uuid::new_v4(),
+            "bidder",
+            200,
+        );
+        let mount =
+            Trade::builder()
+                .item_category("mount")
+                .build()
+                .with_bid(Uuid::new_v4(), "bidder", 200);
+
+        assert!(policy.evaluate(&weapon).is_ok());
+        assert!(policy.evaluate(&mount).is_err());
+    }
+
+    #[test]
+    fn test_penalty_percent_is_limited_to_refund() {
+        assert_eq!(parse_penalty_percent("0"), Ok(0));
+        assert_eq!(parse_penalty_percent("100"), Ok(100));
+        assert!(parse_penalty_percent("101").is_err());
+        assert!(parse_penalty_percent("-5").is_err());
+        assert!(parse_penalty_percent("ten").is_err());
+    }
+}
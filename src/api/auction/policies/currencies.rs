@@ -0,0 +1,97 @@
+use crate::cli::CliOptions;
+use crate::core::error::{Error, Result};
+use crate::models::trade::{get_currency, Trade};
+
+// Defines which currencies trades can be priced in.
+pub struct CurrencyPolicy {
+    allowed: Vec<String>,
+}
+
+impl CurrencyPolicy {
+    pub fn new(allowed: Vec<String>) -> Self {
+        Self {
+            allowed: allowed
+                .iter()
+                .map(|currency| get_currency(currency))
+                .collect(),
+        }
+    }
+
+    pub fn check_listing(&self, trade: &Trade) -> Result<()> {
+        if !self
+            .allowed
+            .iter()
+            .any(|currency| currency == trade.currency())
+        {
+            return Err(Error::ValidationError {
+                field: "currency".to_string(),
+                message: format!(
+                    "{0} is not allowed, use one of: {1}.",
+                    trade.currency(),
+                    self.allowed.join(", ")
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    // Bids and buyouts must be paid in the currency of the trade.
+    pub fn check_payment(&self, trade: &Trade, currency: &str) -> Result<()> {
+        if get_currency(currency) != trade.currency() {
+            return Err(Error::ValidationError {
+                field: "currency".to_string(),
+                message: format!("The trade accepts only {0}.", trade.currency()),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+pub fn create_currency_policy(opts: &CliOptions) -> CurrencyPolicy {
+    let allowed = opts
+        .allowed_currencies
+        .split(',')
+        .map(|currency| currency.trim())
+        .filter(|currency| !currency.is_empty())
+        .map(|currency| currency.to_string())
+        .collect();
+
+    CurrencyPolicy::new(allowed)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::api::auction::policies::currencies::CurrencyPolicy;
+    use crate::models::trade::Trade;
+
+    #[test]
+    fn test_listing_in_allowed_currency() {
+        let policy = CurrencyPolicy::new(vec!["gold".to_string(), "Tokens".to_string()]);
+
+        assert!(policy
+            .check_listing(&Trade::builder().currency("").build())
+            .is_ok());
+        assert!(policy
+            .check_listing(&Trade::builder().currency("TOKENS").build())
+            .is_ok());
+        assert!(policy
+            .check_listing(&Trade::builder().currency("honor").build())
+            .is_err());
+    }
+
+    #[test]
+    fn test_payment_in_trade_currency() {
+        let policy = CurrencyPolicy::new(vec!["gold".to_string(), "tokens".to_string()]);
+        let gold_trade = Trade::builder().currency("").build();
+        let tokens_trade = Trade::builder().currency("tokens").build();
+
+        assert!(policy.check_payment(&gold_trade, "").is_ok());
+        assert!(policy.check_payment(&gold_trade, "gold").is_ok());
+        assert!(policy.check_payment(&gold_trade, "tokens").is_err());
+        assert!(policy.check_payment(&tokens_trade, "Tokens").is_ok());
+        assert!(policy.check_payment(&tokens_trade, "").is_err());
+    }
+}
@@ -0,0 +1,289 @@
+use std::str::FromStr;
+
+use crate::cli::CliOptions;
+use crate::core::error::{Error, Result};
+use crate::core::money::Money;
+use crate::models::trade::Trade;
+
+// The faction list of the house, that is open for players of any faction.
+const ANY_FACTION: &str = "*";
+
+// Returns the faction code from the request. Players without a faction
+// can trade only in houses open for any faction.
+fn get_faction(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuctionHouse {
+    name: String,
+    // None when the house is open for players of any faction.
+    factions: Option<Vec<String>>,
+    deposit_percent: i64,
+    commission_percent: i64,
+}
+
+impl AuctionHouse {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn commission_percent(&self) -> i64 {
+        self.commission_percent
+    }
+
+    fn is_open_for(&self, faction: &str) -> bool {
+        match &self.factions {
+            Some(factions) => factions.contains(&get_faction(faction)),
+            None => true,
+        }
+    }
+
+    // The deposit is a percent of the buyout price, or of the bid price
+    // when the buyout price wasn't set.
+    pub fn get_deposit(&self, trade: &Trade) -> Result<Money> {
+        let price = match trade.buyout_price().is_positive() {
+            true => trade.buyout_price(),
+            false => trade.bid_price(),
+        };
+
+        price.percent(self.deposit_percent)
+    }
+}
+
+// Parses the house in the `name:factions:deposit:commission` format, where
+// factions are separated by `|` and `*` means any faction.
+impl FromStr for AuctionHouse {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let parts: Vec<&str> = value.split(':').map(|part| part.trim()).collect();
+        let [name, factions, deposit_percent, commission_percent] = parts.as_slice() else {
+            return Err(format!(
+                "{0} is not a valid auction house, use `name:factions:deposit:commission`",
+                value
+            ));
+        };
+
+        if name.is_empty() {
+            return Err(format!("The auction house in {0} must have a name", value));
+        }
+
+        let factions = match *factions {
+            ANY_FACTION => None,
+            factions => {
+                let factions: Vec<String> = factions
+                    .split('|')
+                    .map(get_faction)
+                    .filter(|faction| !faction.is_empty())
+                    .collect();
+
+                if factions.is_empty() {
+                    return Err(format!(
+                        "The {0} auction house must be open for at least one faction",
+                        name
+                    ));
+                }
+
+                Some(factions)
+            }
+        };
+
+        let parse_percent = |percent: &str| {
+            percent
+                .parse::<i64>()
+                .ok()
+                .filter(|percent| (0..=100).contains(percent))
+                .ok_or_else(|| {
+                    format!(
+                        "{0} is not a valid percent for the {1} auction house",
+                        percent, name
+                    )
+                })
+        };
+
+        Ok(AuctionHouse {
+            name: name.to_lowercase(),
+            factions,
+            deposit_percent: parse_percent(deposit_percent)?,
+            commission_percent: parse_percent(commission_percent)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuctionHouses(Vec<AuctionHouse>);
+
+impl FromStr for AuctionHouses {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let mut houses: Vec<AuctionHouse> = Vec::new();
+
+        for house in value.split(',').filter(|house| !house.trim().is_empty()) {
+            let house = AuctionHouse::from_str(house)?;
+            if houses.iter().any(|other| other.name == house.name) {
+                return Err(format!("The {0} auction house is duplicated", house.name));
+            }
+            houses.push(house);
+        }
+
+        match houses.is_empty() {
+            true => Err("At least one auction house must be configured".to_string()),
+            false => Ok(AuctionHouses(houses)),
+        }
+    }
+}
+
+// Defines in which auction houses players of each faction can trade.
+pub struct HousePolicy {
+    houses: Vec<AuctionHouse>,
+}
+
+impl HousePolicy {
+    pub fn new(houses: AuctionHouses) -> Self {
+        Self { houses: houses.0 }
+    }
+
+    fn get_house(&self, name: &str) -> Option<&AuctionHouse> {
+        self.houses.iter().find(|house| house.name == name)
+    }
+
+    // The faction of the seller decides in which houses the trade can be listed.
+    pub fn check_listing(&self, trade: &Trade, faction: &str) -> Result<&AuctionHouse> {
+        let house = self
+            .get_house(trade.house())
+            .ok_or_else(|| Error::ValidationError {
+                field: "house".to_string(),
+                message: format!(
+                    "{0} doesn't exist, use one of: {1}.",
+                    trade.house(),
+                    self.houses
+                        .iter()
+                        .map(|house| house.name())
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                ),
+            })?;
+
+        if !house.is_open_for(faction) {
+            return Err(Error::ValidationError {
+                field: "faction".to_string(),
+                message: format!("The {0} house is closed for the faction.", house.name()),
+            });
+        }
+
+        Ok(house)
+    }
+
+    // Bids and buyouts are accepted only from factions that can trade in
+    // the house of the trade.
+    pub fn check_bidder(&self, trade: &Trade, faction: &str) -> Result<()> {
+        match self.get_house(trade.house()) {
+            Some(house) if !house.is_open_for(faction) => Err(Error::ValidationError {
+                field: "faction".to_string(),
+                message: format!("The {0} house is closed for the faction.", house.name()),
+            }),
+            // Houses removed from the settings don't restrict trades that
+            // were listed in them before
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn create_house_policy(opts: &CliOptions) -> HousePolicy {
+    HousePolicy::new(opts.auction_houses.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::api::auction::policies::houses::{AuctionHouse, AuctionHouses, HousePolicy};
+    use crate::core::money::Money;
+    use crate::models::trade::Trade;
+
+    fn create_trade(house: &str, buyout_price: i64) -> Trade {
+        Trade::builder()
+            .house(house)
+            .prices(100, buyout_price)
+            .build()
+    }
+
+    fn create_policy() -> HousePolicy {
+        let houses =
+            AuctionHouses::from_str("alliance:alliance:5:5, horde:horde:5:5, Neutral:*:5:15")
+                .unwrap();
+        HousePolicy::new(houses)
+    }
+
+    #[test]
+    fn test_parse_auction_houses() {
+        let house = AuctionHouse::from_str(" Neutral : Alliance|horde : 10 : 15 ").unwrap();
+
+        assert_eq!(house.name(), "neutral");
+        assert!(house.is_open_for("ALLIANCE"));
+        assert!(house.is_open_for("horde"));
+        assert!(!house.is_open_for("pirates"));
+        assert!(!house.is_open_for(""));
+        assert_eq!(house.commission_percent(), 15);
+
+        for value in [
+            "neutral",
+            ":*:5:5",
+            "neutral::5:5",
+            "neutral:*:-1:5",
+            "neutral:*:5:101",
+            "neutral:*:5:5:5",
+        ] {
+            assert!(AuctionHouse::from_str(value).is_err(), "{0}", value);
+        }
+
+        assert!(AuctionHouses::from_str("").is_err());
+        assert!(AuctionHouses::from_str("neutral:*:5:5,Neutral:*:0:0").is_err());
+    }
+
+    #[test]
+    fn test_listing_by_faction() {
+        let policy = create_policy();
+
+        assert!(policy
+            .check_listing(&create_trade("alliance", 0), "alliance")
+            .is_ok());
+        assert!(policy
+            .check_listing(&create_trade("alliance", 0), "horde")
+            .is_err());
+        assert!(policy
+            .check_listing(&create_trade("neutral", 0), "horde")
+            .is_ok());
+        assert!(policy.check_listing(&create_trade("", 0), "horde").is_err());
+    }
+
+    #[test]
+    fn test_bids_by_faction() {
+        let policy = create_policy();
+        let horde_trade = create_trade("Horde", 0);
+
+        assert!(policy.check_bidder(&horde_trade, "horde").is_ok());
+        assert!(policy.check_bidder(&horde_trade, "alliance").is_err());
+        assert!(policy
+            .check_bidder(&create_trade("neutral", 0), "alliance")
+            .is_ok());
+        assert!(policy
+            .check_bidder(&create_trade("closed", 0), "alliance")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_deposit_of_house() {
+        let policy = create_policy();
+        let trade = create_trade("neutral", 1000);
+        let house = policy.check_listing(&trade, "horde").unwrap();
+
+        assert_eq!(house.get_deposit(&trade).unwrap(), Money::new(50));
+        assert_eq!(
+            house.get_deposit(&create_trade("neutral", 0)).unwrap(),
+            Money::new(5)
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::cli::CliOptions;
+use crate::core::error::{Error, Result};
+use crate::services::rate_limiter::{RateLimiter, Reservation};
+
+const LISTINGS_WINDOW: Duration = Duration::from_secs(60 * 60);
+const BIDS_WINDOW: Duration = Duration::from_secs(60);
+
+// Per-player limits for listings and bids. Zero disables the limit.
+pub struct PlayerLimits {
+    max_active_listings: usize,
+    // The listings being created per player. They aren't stored yet, so
+    // they're added to the stored active listings of the player.
+    pending_listings: Mutex<HashMap<Uuid, usize>>,
+    listings: RateLimiter,
+    bids: RateLimiter,
+}
+
+impl PlayerLimits {
+    pub fn new(
+        max_active_listings: usize,
+        max_listings_per_hour: usize,
+        max_bids_per_minute: usize,
+    ) -> Self {
+        Self {
+            max_active_listings,
+            pending_listings: Mutex::new(HashMap::new()),
+            listings: RateLimiter::new(max_listings_per_hour, LISTINGS_WINDOW),
+            bids: RateLimiter::new(max_bids_per_minute, BIDS_WINDOW),
+        }
+    }
+
+    pub fn is_active_listings_limited(&self) -> bool {
+        self.max_active_listings > 0
+    }
+
+    // Counts the listing of the player as pending until the slot is dropped.
+    // Must be taken before the active listings are counted and kept until
+    // the listing is stored, so that concurrent listings see each other.
+    pub fn reserve_active_listing(&self, player_id: Uuid) -> ListingSlot<'_> {
+        let mut pending_listings = self.pending_listings.lock().unwrap();
+        let pending = pending_listings.entry(player_id).or_default();
+        *pending += 1;
+
+        ListingSlot {
+            limits: self,
+            player_id,
+            others: *pending - 1,
+        }
+    }
+
+    pub fn check_active_listings(&self, slot: &ListingSlot, active_listings: usize) -> Result<()> {
+        if self.is_active_listings_limited()
+            && active_listings + slot.others >= self.max_active_listings
+        {
+            return Err(Error::LimitExceeded {
+                subject: "active_listings".to_string(),
+                message: format!(
+                    "The player can't have more than {0} active listings.",
+                    self.max_active_listings
+                ),
+                retry_after: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Only the confirmed listings count towards the limit.
+    pub fn reserve_listing(&self, player_id: Uuid) -> Result<Reservation<'_>> {
+        self.listings
+            .reserve(player_id)
+            .map_err(|retry_after| Error::LimitExceeded {
+                subject: "listings_per_hour".to_string(),
+                message: "Too many listings were created within the last hour.".to_string(),
+                retry_after: Some(retry_after),
+            })
+    }
+
+    // Only the confirmed bids count towards the limit.
+    pub fn reserve_bid(&self, player_id: Uuid) -> Result<Reservation<'_>> {
+        self.bids
+            .reserve(player_id)
+            .map_err(|retry_after| Error::LimitExceeded {
+                subject: "bids_per_minute".to_string(),
+                message: "Too many bids were made within the last minute.".to_string(),
+                retry_after: Some(retry_after),
+            })
+    }
+
+    fn release_active_listing(&self, player_id: Uuid) {
+        let mut pending_listings = self.pending_listings.lock().unwrap();
+        if let Some(pending) = pending_listings.get_mut(&player_id) {
+            *pending -= 1;
+            if *pending == 0 {
+                pending_listings.remove(&player_id);
+            }
+        }
+    }
+}
+
+// The listing being created, counted towards the active listings of
+// the player until it's dropped.
+pub struct ListingSlot<'a> {
+    limits: &'a PlayerLimits,
+    player_id: Uuid,
+    // The other listings of the player, that were pending when the slot
+    // was taken.
+    others: usize,
+}
+
+impl Drop for ListingSlot<'_> {
+    fn drop(&mut self) {
+        self.limits.release_active_listing(self.player_id);
+    }
+}
+
+pub fn create_player_limits(opts: &CliOptions) -> PlayerLimits {
+    PlayerLimits::new(
+        opts.max_active_listings,
+        opts.max_listings_per_hour,
+        opts.max_bids_per_minute,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::api::auction::policies::limits::PlayerLimits;
+
+    #[test]
+    fn test_pending_listings_count_as_active() {
+        let limits = PlayerLimits::new(2, 0, 0);
+        let player_id = Uuid::new_v4();
+
+        let first = limits.reserve_active_listing(player_id);
+        let second = limits.reserve_active_listing(player_id);
+        assert!(limits.check_active_listings(&first, 1).is_ok());
+        assert!(limits.check_active_listings(&second, 1).is_err());
+
+        drop(first);
+        let third = limits.reserve_active_listing(player_id);
+        assert!(limits.check_active_listings(&third, 0).is_ok());
+        assert!(limits
+            .check_active_listings(&limits.reserve_active_listing(Uuid::new_v4()), 1)
+            .is_ok());
+        drop(second);
+    }
+
+    #[test]
+    fn test_failed_bids_are_given_back() {
+        let limits = PlayerLimits::new(0, 0, 1);
+        let player_id = Uuid::new_v4();
+
+        drop(limits.reserve_bid(player_id).unwrap());
+        limits.reserve_bid(player_id).unwrap().confirm();
+
+        assert!(limits.reserve_bid(player_id).is_err());
+    }
+}
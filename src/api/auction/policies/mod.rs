@@ -0,0 +1,30 @@
+pub mod bidding;
+pub mod cancellation;
+pub mod currencies;
+pub mod houses;
+pub mod limits;
+
+use crate::api::auction::policies::bidding::{create_bidding_policy, BiddingPolicy};
+use crate::api::auction::policies::cancellation::{create_cancellation_policy, CancellationPolicy};
+use crate::api::auction::policies::currencies::{create_currency_policy, CurrencyPolicy};
+use crate::api::auction::policies::houses::{create_house_policy, HousePolicy};
+use crate::api::auction::policies::limits::{create_player_limits, PlayerLimits};
+use crate::cli::CliOptions;
+
+pub struct AuctionPolicies {
+    pub cancellation: Box<dyn CancellationPolicy>,
+    pub limits: PlayerLimits,
+    pub bidding: BiddingPolicy,
+    pub currencies: CurrencyPolicy,
+    pub houses: HousePolicy,
+}
+
+pub fn create_auction_policies(opts: &CliOptions) -> AuctionPolicies {
+    AuctionPolicies {
+        cancellation: create_cancellation_policy(opts),
+        limits: create_player_limits(opts),
+        bidding: create_bidding_policy(opts),
+        currencies: create_currency_policy(opts),
+        houses: create_house_policy(opts),
+    }
+}
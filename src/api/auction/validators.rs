@@ -1,26 +1,44 @@
+use chrono::{DateTime, Utc};
 use tonic::Request;
 use uuid::Uuid;
 
 use crate::core::error::Error;
+use crate::core::money::Money;
 use crate::core::validation::Validate;
-use crate::proto::{BidRequest, BuyoutRequest, CancelTradeRequest, CreateTradeRequest};
+use crate::proto::{
+    AcceptBarterOfferRequest, BidRequest, BuyoutRequest, CancelTradeRequest, ClaimAllMailRequest,
+    ClaimMailRequest, CreateSavedSearchRequest, CreateTradeRequest, DeleteSavedSearchRequest,
+    FilterParams, GetMarketPricesRequest, GetPriceHistoryRequest, LineItem,
+    ListBarterOffersRequest, ListMailRequest, ListNotificationsRequest, ListPrivateOffersRequest,
+    ListSavedSearchesRequest, ListTradesRequest, ListWatchedTradesRequest, OfferBarterRequest,
+    RejectBarterOfferRequest, StreamTradeEventsRequest, SuggestPriceRequest, UnwatchItemRequest,
+    UnwatchTradeRequest, UpdateSavedSearchRequest, WatchItemRequest, WatchTradeRequest,
+};
+
+// The shortest item name accepted for saved searches. Shorter names would
+// match too many trades.
+const MIN_SAVED_SEARCH_ITEM_NAME_LENGTH: usize = 3;
+// The maximum amount of items per a single market prices lookup.
+const MAX_MARKET_PRICES_ITEMS: usize = 100;
+// The shortest candle interval for the price history, in seconds.
+const MIN_PRICE_HISTORY_INTERVAL: i64 = 60;
+// The maximum amount of candles returned for the price history.
+const MAX_PRICE_HISTORY_CANDLES: i64 = 1000;
+// The longest period for the price history, in seconds.
+const MAX_PRICE_HISTORY_PERIOD: i64 = 366 * 24 * 60 * 60;
+// How far ahead trades can be scheduled, in seconds.
+const MAX_SCHEDULE_AHEAD: i64 = 90 * 24 * 60 * 60;
+// The maximum amount of items sold together by a bundle trade. Also limits
+// the items wanted and offered for barter trades.
+const MAX_BUNDLE_ITEMS: usize = 20;
 
 impl Validate for Request<CreateTradeRequest> {
     fn validate(&self) -> Result<(), Error> {
         let data = self.get_ref();
 
-        if Uuid::try_parse(&data.item_id).is_err() {
-            return Err(Error::ValidationError {
-                field: "item_id".to_string(),
-                message: format!("{0} is not a valid UUID.", &data.item_id),
-            });
-        };
-
-        if data.item_name.is_empty() {
-            return Err(Error::ValidationError {
-                field: "item_name".to_string(),
-                message: "This field can't be empty.".to_string(),
-            });
+        match data.items.is_empty() {
+            true => validate_item(&data.item_id, &data.item_name, data.quantity, "")?,
+            false => validate_bundle(data)?,
         }
 
         if Uuid::try_parse(&data.created_by).is_err() {
@@ -37,14 +55,26 @@ impl Validate for Request<CreateTradeRequest> {
             });
         }
 
-        if data.bid_price <= 0 {
+        let bid_price = Money::new(data.bid_price);
+        let buyout_price = Money::new(data.buyout_price);
+        if !data.wanted_items.is_empty() {
+            validate_line_items(&data.wanted_items, "wanted_items", 1)?;
+
+            // Barter trades are exchanged only for items
+            if bid_price != Money::ZERO || buyout_price != Money::ZERO {
+                return Err(Error::ValidationError {
+                    field: "bid_price".to_string(),
+                    message: "The barter trade can't have prices.".to_string(),
+                });
+            }
+        } else if !bid_price.is_positive() {
             return Err(Error::ValidationError {
                 field: "bid_price".to_string(),
                 message: "The item must have an initial price.".to_string(),
             });
         }
 
-        if data.buyout_price > 0 && data.bid_price > data.buyout_price {
+        if buyout_price.is_positive() && bid_price > buyout_price {
             return Err(Error::ValidationError {
                 field: "buyout_price".to_string(),
                 message: "The buyout price must be greater than the bid price".to_string(),
@@ -58,6 +88,118 @@ impl Validate for Request<CreateTradeRequest> {
             });
         }
 
+        if let Some(reserved_for) = &data.reserved_for {
+            if Uuid::try_parse(reserved_for).is_err() {
+                return Err(Error::ValidationError {
+                    field: "reserved_for".to_string(),
+                    message: format!("{0} is not a valid UUID.", reserved_for),
+                });
+            }
+
+            if *reserved_for == data.created_by {
+                return Err(Error::ValidationError {
+                    field: "reserved_for".to_string(),
+                    message: "The trade can't be reserved for its owner.".to_string(),
+                });
+            }
+        }
+
+        if let Some(starts_at) = data.starts_at {
+            let now = Utc::now().timestamp();
+            if starts_at <= now || starts_at - now > MAX_SCHEDULE_AHEAD {
+                return Err(Error::ValidationError {
+                    field: "starts_at".to_string(),
+                    message: format!(
+                        "The start time must be in the future, but not later than {0} days from now.",
+                        MAX_SCHEDULE_AHEAD / (24 * 60 * 60)
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The prefix points to the line item of the bundle, when it's invalid.
+fn validate_item(item_id: &str, item_name: &str, quantity: i32, prefix: &str) -> Result<(), Error> {
+    if Uuid::try_parse(item_id).is_err() {
+        return Err(Error::ValidationError {
+            field: format!("{0}item_id", prefix),
+            message: format!("{0} is not a valid UUID.", item_id),
+        });
+    };
+
+    if item_name.is_empty() {
+        return Err(Error::ValidationError {
+            field: format!("{0}item_name", prefix),
+            message: "This field can't be empty.".to_string(),
+        });
+    }
+
+    if quantity < 0 {
+        return Err(Error::ValidationError {
+            field: format!("{0}quantity", prefix),
+            message: "The quantity must be zero or a positive value.".to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+fn validate_line_items(items: &[LineItem], field: &str, min_items: usize) -> Result<(), Error> {
+    if items.len() < min_items || items.len() > MAX_BUNDLE_ITEMS {
+        return Err(Error::ValidationError {
+            field: field.to_string(),
+            message: format!(
+                "The list must contain from {0} to {1} items.",
+                min_items, MAX_BUNDLE_ITEMS
+            ),
+        });
+    }
+
+    for (index, item) in items.iter().enumerate() {
+        validate_item(
+            &item.item_id,
+            &item.item_name,
+            item.quantity,
+            &format!("{0}[{1}].", field, index),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn validate_bundle(data: &CreateTradeRequest) -> Result<(), Error> {
+    validate_line_items(&data.items, "items", 2)
+}
+
+fn validate_barter_offer_ids(id: &str, offer_id: &str, user_id: &str) -> Result<(), Error> {
+    for (field, value) in [("id", id), ("offer_id", offer_id), ("user_id", user_id)] {
+        if Uuid::try_parse(value).is_err() {
+            return Err(Error::ValidationError {
+                field: field.to_string(),
+                message: format!("{0} is not a valid UUID.", value),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+impl Validate for Request<ListTradesRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if let Some(user_id) = &data.user_id {
+            if Uuid::try_parse(user_id).is_err() {
+                return Err(Error::ValidationError {
+                    field: "user_id".to_string(),
+                    message: format!("{0} is not a valid UUID.", user_id),
+                });
+            }
+        }
+
         Ok(())
     }
 }
@@ -87,7 +229,7 @@ impl Validate for Request<BidRequest> {
             });
         }
 
-        if data.amount <= 0 {
+        if !Money::new(data.amount).is_positive() {
             return Err(Error::ValidationError {
                 field: "amount".to_string(),
                 message: "The amount must be a positive value.".to_string(),
@@ -123,7 +265,7 @@ impl Validate for Request<BuyoutRequest> {
             });
         }
 
-        if data.amount <= 0 {
+        if !Money::new(data.amount).is_positive() {
             return Err(Error::ValidationError {
                 field: "amount".to_string(),
                 message: "The amount must be a positive value.".to_string(),
@@ -155,3 +297,533 @@ impl Validate for Request<CancelTradeRequest> {
         Ok(())
     }
 }
+
+impl Validate for Request<OfferBarterRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.id).is_err() {
+            return Err(Error::ValidationError {
+                field: "id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.id),
+            });
+        }
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        if data.username.is_empty() {
+            return Err(Error::ValidationError {
+                field: "username".to_string(),
+                message: "This field can't be empty.".to_string(),
+            });
+        }
+
+        validate_line_items(&data.items, "items", 1)
+    }
+}
+
+impl Validate for Request<ListBarterOffersRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.id).is_err() {
+            return Err(Error::ValidationError {
+                field: "id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.id),
+            });
+        }
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<AcceptBarterOfferRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+        validate_barter_offer_ids(&data.id, &data.offer_id, &data.user_id)
+    }
+}
+
+impl Validate for Request<RejectBarterOfferRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+        validate_barter_offer_ids(&data.id, &data.offer_id, &data.user_id)
+    }
+}
+
+impl Validate for Request<GetPriceHistoryRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.item_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "item_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.item_id),
+            });
+        }
+
+        if data.interval < MIN_PRICE_HISTORY_INTERVAL {
+            return Err(Error::ValidationError {
+                field: "interval".to_string(),
+                message: format!(
+                    "The interval must be at least {0} seconds.",
+                    MIN_PRICE_HISTORY_INTERVAL
+                ),
+            });
+        }
+
+        if data.from < 0 || DateTime::from_timestamp(data.from, 0).is_none() {
+            return Err(Error::ValidationError {
+                field: "from".to_string(),
+                message: "The period start must be a valid timestamp.".to_string(),
+            });
+        }
+
+        if DateTime::from_timestamp(data.to, 0).is_none() {
+            return Err(Error::ValidationError {
+                field: "to".to_string(),
+                message: "The period end must be a valid timestamp.".to_string(),
+            });
+        }
+
+        if data.to <= data.from {
+            return Err(Error::ValidationError {
+                field: "to".to_string(),
+                message: "The period end must be greater than the period start.".to_string(),
+            });
+        }
+
+        if data.to - data.from > MAX_PRICE_HISTORY_PERIOD {
+            return Err(Error::ValidationError {
+                field: "to".to_string(),
+                message: "The requested period is too long.".to_string(),
+            });
+        }
+
+        if (data.to - data.from) / data.interval > MAX_PRICE_HISTORY_CANDLES {
+            return Err(Error::ValidationError {
+                field: "interval".to_string(),
+                message: format!(
+                    "The period can't be split into more than {0} candles.",
+                    MAX_PRICE_HISTORY_CANDLES
+                ),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<SuggestPriceRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.item_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "item_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.item_id),
+            });
+        }
+
+        if data.quantity <= 0 {
+            return Err(Error::ValidationError {
+                field: "quantity".to_string(),
+                message: "The quantity must be a positive value.".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<GetMarketPricesRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if data.item_ids.is_empty() {
+            return Err(Error::ValidationError {
+                field: "item_ids".to_string(),
+                message: "This field can't be empty.".to_string(),
+            });
+        }
+
+        if data.item_ids.len() > MAX_MARKET_PRICES_ITEMS {
+            return Err(Error::ValidationError {
+                field: "item_ids".to_string(),
+                message: format!(
+                    "Can't look up more than {0} items at once.",
+                    MAX_MARKET_PRICES_ITEMS
+                ),
+            });
+        }
+
+        if let Some(item_id) = data
+            .item_ids
+            .iter()
+            .find(|item_id| Uuid::try_parse(item_id).is_err())
+        {
+            return Err(Error::ValidationError {
+                field: "item_ids".to_string(),
+                message: format!("{0} is not a valid UUID.", item_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<WatchTradeRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.id).is_err() {
+            return Err(Error::ValidationError {
+                field: "id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.id),
+            });
+        }
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<UnwatchTradeRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.id).is_err() {
+            return Err(Error::ValidationError {
+                field: "id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.id),
+            });
+        }
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<WatchItemRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.item_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "item_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.item_id),
+            });
+        }
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<UnwatchItemRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.item_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "item_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.item_id),
+            });
+        }
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<ListWatchedTradesRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<CreateSavedSearchRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        validate_saved_search(&data.name, data.filter_params.as_ref())
+    }
+}
+
+impl Validate for Request<UpdateSavedSearchRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.id).is_err() {
+            return Err(Error::ValidationError {
+                field: "id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.id),
+            });
+        }
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        validate_saved_search(&data.name, data.filter_params.as_ref())
+    }
+}
+
+impl Validate for Request<DeleteSavedSearchRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.id).is_err() {
+            return Err(Error::ValidationError {
+                field: "id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.id),
+            });
+        }
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<ListSavedSearchesRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<ListNotificationsRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<ListPrivateOffersRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<ListMailRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<ClaimMailRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.id).is_err() {
+            return Err(Error::ValidationError {
+                field: "id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.id),
+            });
+        }
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for Request<ClaimAllMailRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        if Uuid::try_parse(&data.user_id).is_err() {
+            return Err(Error::ValidationError {
+                field: "user_id".to_string(),
+                message: format!("{0} is not a valid UUID.", &data.user_id),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_saved_search(name: &str, filter_params: Option<&FilterParams>) -> Result<(), Error> {
+    if name.is_empty() {
+        return Err(Error::ValidationError {
+            field: "name".to_string(),
+            message: "This field can't be empty.".to_string(),
+        });
+    }
+
+    let item_name = filter_params
+        .and_then(|params| params.item_name.as_deref())
+        .unwrap_or_default();
+    if item_name.trim().chars().count() < MIN_SAVED_SEARCH_ITEM_NAME_LENGTH {
+        return Err(Error::ValidationError {
+            field: "filter_params.item_name".to_string(),
+            message: format!(
+                "The search is too broad. The item name must contain at least {0} characters.",
+                MIN_SAVED_SEARCH_ITEM_NAME_LENGTH
+            ),
+        });
+    }
+
+    let params = filter_params.expect("filter params with item name");
+    let price_ranges = [
+        (
+            "min_price",
+            params.min_price.map(Money::new),
+            "max_price",
+            params.max_price.map(Money::new),
+        ),
+        (
+            "min_buyout_price",
+            params.min_buyout_price.map(Money::new),
+            "max_buyout_price",
+            params.max_buyout_price.map(Money::new),
+        ),
+    ];
+    for (min_field, min, max_field, max) in price_ranges {
+        for (field, value) in [(min_field, min), (max_field, max)] {
+            if value.is_some_and(|value| value < Money::ZERO) {
+                return Err(Error::ValidationError {
+                    field: format!("filter_params.{0}", field),
+                    message: "The price can't be negative.".to_string(),
+                });
+            }
+        }
+
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return Err(Error::ValidationError {
+                    field: format!("filter_params.{0}", min_field),
+                    message: format!("The value can't be greater than {0}.", max_field),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl Validate for Request<StreamTradeEventsRequest> {
+    fn validate(&self) -> Result<(), Error> {
+        let data = self.get_ref();
+
+        match (&data.trade_id, &data.user_id) {
+            (Some(trade_id), None) if Uuid::try_parse(trade_id).is_err() => {
+                Err(Error::ValidationError {
+                    field: "trade_id".to_string(),
+                    message: format!("{0} is not a valid UUID.", trade_id),
+                })
+            }
+            (None, Some(user_id)) if Uuid::try_parse(user_id).is_err() => {
+                Err(Error::ValidationError {
+                    field: "user_id".to_string(),
+                    message: format!("{0} is not a valid UUID.", user_id),
+                })
+            }
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            _ => Err(Error::ValidationError {
+                field: "trade_id".to_string(),
+                message: "Either trade_id or user_id must be set.".to_string(),
+            }),
+        }
+    }
+}
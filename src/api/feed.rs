@@ -0,0 +1,116 @@
+use std::convert::Infallible;
+
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Response;
+use futures::{Stream, StreamExt};
+use log::warn;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::api::rest::error::ApiError;
+use crate::core::error::Error;
+use crate::core::realm::{parse_realm, DEFAULT_REALM};
+use crate::services::trade_events::{MarketEvent, Subscription, TradeEventBus};
+
+// The filters for the market feed. Events of all items are sent when
+// nothing was specified. Browsers can't set headers for SSE and WebSocket
+// connections, so the realm is passed as a query parameter.
+#[derive(Deserialize, Debug)]
+pub struct FeedParams {
+    realm: Option<String>,
+    item_id: Option<Uuid>,
+    category: Option<String>,
+}
+
+impl TryFrom<FeedParams> for Subscription {
+    type Error = Error;
+
+    fn try_from(params: FeedParams) -> Result<Self, Self::Error> {
+        let realm_id = match params.realm {
+            Some(realm) => parse_realm(&realm)?,
+            None => DEFAULT_REALM.to_string(),
+        };
+
+        Ok(Subscription::Market {
+            realm_id,
+            item_id: params.item_id,
+            category: params.category,
+        })
+    }
+}
+
+// Streams the market events over Server-Sent Events. Each event is named
+// after its kind and carries the JSON payload.
+pub async fn sse(
+    State(events): State<TradeEventBus>,
+    Query(params): Query<FeedParams>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let stream = events
+        .subscribe(Subscription::try_from(params)?)
+        .map(|received| {
+            let event = match received {
+                Ok(event) => {
+                    let market_event = MarketEvent::from(&event);
+                    Event::default()
+                        .event(market_event.kind())
+                        .json_data(market_event)
+                        .expect("serialize market event")
+                }
+                Err(err) => Event::default().event("error").data(err.to_string()),
+            };
+            Ok(event)
+        });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+// Streams the market events over WebSocket as JSON text messages.
+pub async fn websocket(
+    upgrade: WebSocketUpgrade,
+    State(events): State<TradeEventBus>,
+    Query(params): Query<FeedParams>,
+) -> Result<Response, ApiError> {
+    let subscription = Subscription::try_from(params)?;
+
+    Ok(upgrade.on_upgrade(move |socket| forward_events(socket, events, subscription)))
+}
+
+async fn forward_events(mut socket: WebSocket, events: TradeEventBus, subscription: Subscription) {
+    let mut stream = events.subscribe(subscription);
+
+    loop {
+        let received = tokio::select! {
+            received = stream.next() => received,
+            message = socket.recv() => match message {
+                // Messages from clients are ignored
+                Some(Ok(_)) => continue,
+                _ => break,
+            },
+        };
+
+        let message = match received {
+            Some(Ok(event)) => {
+                let payload = serde_json::to_string(&MarketEvent::from(&event))
+                    .expect("serialize market event");
+                Message::Text(payload)
+            }
+            Some(Err(err)) => Message::Close(Some(CloseFrame {
+                code: close_code::AGAIN,
+                reason: err.to_string().into(),
+            })),
+            None => Message::Close(None),
+        };
+        let is_closing = matches!(message, Message::Close(_));
+
+        if let Err(err) = socket.send(message).await {
+            warn!("Can't send the market event: {0}", err);
+            break;
+        }
+
+        if is_closing {
+            break;
+        }
+    }
+}
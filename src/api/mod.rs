@@ -1,2 +1,5 @@
+pub mod admin;
 pub mod auction;
+pub mod feed;
 pub mod k8s;
+pub mod rest;
@@ -0,0 +1,676 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::extract::{FromRef, Path, Query, State};
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use tonic::metadata::MetadataMap;
+use tonic::{Extensions, Request};
+
+use crate::api::admin::api::AdminServiceImpl;
+use crate::api::auction::api::AuctionServiceImpl;
+use crate::api::feed;
+use crate::api::k8s::healthcheck;
+use crate::api::rest::error::ApiError;
+use crate::core::realm::REALM_METADATA_KEY;
+use crate::proto::auction_admin_server::AuctionAdmin;
+use crate::proto::auction_server::Auction;
+use crate::proto::{
+    AcceptBarterOfferRequest, AcceptBarterOfferResponse, BidRequest, BidResponse, BuyoutRequest,
+    BuyoutResponse, CancelScheduledTradeRequest, CancelScheduledTradeResponse, CancelTradeRequest,
+    CancelTradeResponse, ClaimAllMailRequest, ClaimAllMailResponse, ClaimMailRequest,
+    ClaimMailResponse, CreateSavedSearchRequest, CreateSavedSearchResponse, CreateTradeRequest,
+    CreateTradeResponse, DeleteSavedSearchRequest, DeleteSavedSearchResponse, FilterParams,
+    GetMarketPricesRequest, GetMarketPricesResponse, GetPriceHistoryRequest,
+    GetPriceHistoryResponse, GetRealmVisibilityRequest, GetRealmVisibilityResponse,
+    ListBarterOffersRequest, ListBarterOffersResponse, ListMailRequest, ListMailResponse,
+    ListNotificationsRequest, ListNotificationsResponse, ListPrivateOffersRequest,
+    ListPrivateOffersResponse, ListSavedSearchesRequest, ListSavedSearchesResponse,
+    ListScheduledTradesRequest, ListScheduledTradesResponse, ListTradeFlagsRequest,
+    ListTradeFlagsResponse, ListTradesRequest, ListTradesResponse, ListWatchedTradesRequest,
+    ListWatchedTradesResponse, OfferBarterRequest, OfferBarterResponse, RejectBarterOfferRequest,
+    RejectBarterOfferResponse, SetRealmVisibilityRequest, SetRealmVisibilityResponse,
+    StreamTradeEventsRequest, SuggestPriceRequest, SuggestPriceResponse, UnwatchItemRequest,
+    UnwatchItemResponse, UnwatchTradeRequest, UnwatchTradeResponse, UpdateSavedSearchRequest,
+    UpdateSavedSearchResponse, WatchItemRequest, WatchItemResponse, WatchTradeRequest,
+    WatchTradeResponse,
+};
+use crate::services::trade_events::TradeEventBus;
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+// The REST API mirrors the gRPC one and calls the same handlers, so
+// requests pass through the same validation and business rules.
+#[derive(Clone)]
+pub struct RestState {
+    auction: Arc<AuctionServiceImpl>,
+    admin: Arc<AdminServiceImpl>,
+    events: TradeEventBus,
+}
+
+impl RestState {
+    pub fn new(
+        auction: Arc<AuctionServiceImpl>,
+        admin: Arc<AdminServiceImpl>,
+        events: TradeEventBus,
+    ) -> Self {
+        Self {
+            auction,
+            admin,
+            events,
+        }
+    }
+}
+
+impl FromRef<RestState> for TradeEventBus {
+    fn from_ref(state: &RestState) -> Self {
+        state.events.clone()
+    }
+}
+
+pub fn create_router(state: RestState) -> Router {
+    Router::new()
+        .route("/health", get(healthcheck))
+        .route("/feed/sse", get(feed::sse))
+        .route("/feed/ws", get(feed::websocket))
+        .route("/trades", get(list_trades).post(create_trade))
+        .route("/trades/:id/bid", post(bid))
+        .route("/trades/:id/buyout", post(buyout))
+        .route("/trades/:id/cancel", post(cancel_trade))
+        .route("/trades/:id/watch", post(watch_trade).delete(unwatch_trade))
+        .route("/trades/:id/events", get(stream_trade_events))
+        .route(
+            "/trades/:id/barter-offers",
+            get(list_barter_offers).post(offer_barter),
+        )
+        .route(
+            "/trades/:id/barter-offers/:offer_id/accept",
+            post(accept_barter_offer),
+        )
+        .route(
+            "/trades/:id/barter-offers/:offer_id/reject",
+            post(reject_barter_offer),
+        )
+        .route("/items/:item_id/price-history", get(get_price_history))
+        .route("/items/:item_id/price-suggestion", get(suggest_price))
+        .route(
+            "/items/:item_id/watch",
+            post(watch_item).delete(unwatch_item),
+        )
+        .route("/market-prices", get(get_market_prices))
+        .route("/players/:user_id/watchlist", get(list_watched_trades))
+        .route(
+            "/players/:user_id/saved-searches",
+            get(list_saved_searches).post(create_saved_search),
+        )
+        .route(
+            "/players/:user_id/saved-searches/:id",
+            put(update_saved_search).delete(delete_saved_search),
+        )
+        .route("/players/:user_id/notifications", get(list_notifications))
+        .route("/players/:user_id/offers", get(list_private_offers))
+        .route("/players/:user_id/mail", get(list_mail))
+        .route("/players/:user_id/mail/claim", post(claim_all_mail))
+        .route("/players/:user_id/mail/:id/claim", post(claim_mail))
+        .route("/players/:user_id/events", get(stream_player_events))
+        .with_state(state)
+}
+
+// The admin routes are served only on the admin listener, which isn't
+// exposed to players.
+pub fn create_admin_router(state: RestState) -> Router {
+    Router::new()
+        .route("/health", get(healthcheck))
+        .route("/admin/trade-flags", get(list_trade_flags))
+        .route(
+            "/admin/realms/:realm_id/visibility",
+            get(get_realm_visibility).put(set_realm_visibility),
+        )
+        .route("/admin/scheduled-trades", get(list_scheduled_trades))
+        .route(
+            "/admin/realms/:realm_id/scheduled-trades/:id/cancel",
+            post(cancel_scheduled_trade),
+        )
+        .with_state(state)
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct ListTradesParams {
+    page: i32,
+    page_size: i32,
+    item_name: Option<String>,
+    min_price: Option<i64>,
+    max_price: Option<i64>,
+    min_buyout_price: Option<i64>,
+    max_buyout_price: Option<i64>,
+    currency: Option<String>,
+    user_id: Option<String>,
+}
+
+impl From<ListTradesParams> for ListTradesRequest {
+    fn from(params: ListTradesParams) -> Self {
+        Self {
+            page: params.page,
+            page_size: params.page_size,
+            filter_params: Some(FilterParams {
+                item_name: params.item_name,
+                min_price: params.min_price,
+                max_price: params.max_price,
+                min_buyout_price: params.min_buyout_price,
+                max_buyout_price: params.max_buyout_price,
+                currency: params.currency,
+            }),
+            user_id: params.user_id,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct UserParams {
+    user_id: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct MarketPricesParams {
+    // The comma-separated item ids
+    item_ids: String,
+}
+
+// Creates the request for the gRPC handler. The realm header is passed
+// along as the request metadata.
+fn create_request<T>(headers: &HeaderMap, message: T) -> Request<T> {
+    let mut metadata = HeaderMap::new();
+    if let Some(realm) = headers.get(REALM_METADATA_KEY) {
+        metadata.insert(REALM_METADATA_KEY, realm.clone());
+    }
+
+    Request::from_parts(
+        MetadataMap::from_headers(metadata),
+        Extensions::default(),
+        message,
+    )
+}
+
+async fn list_trades(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    query: Result<Query<ListTradesParams>, QueryRejection>,
+) -> ApiResult<ListTradesResponse> {
+    let Query(params) = query?;
+    let request = create_request(&headers, ListTradesRequest::from(params));
+    let response = state.auction.list_trades(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn create_trade(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    payload: Result<Json<CreateTradeRequest>, JsonRejection>,
+) -> ApiResult<CreateTradeResponse> {
+    let Json(data) = payload?;
+    let response = state
+        .auction
+        .create_trade(create_request(&headers, data))
+        .await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn bid(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    payload: Result<Json<BidRequest>, JsonRejection>,
+) -> ApiResult<BidResponse> {
+    let Json(data) = payload?;
+    let request = create_request(&headers, BidRequest { id, ..data });
+    let response = state.auction.bid(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn buyout(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    payload: Result<Json<BuyoutRequest>, JsonRejection>,
+) -> ApiResult<BuyoutResponse> {
+    let Json(data) = payload?;
+    let request = create_request(&headers, BuyoutRequest { id, ..data });
+    let response = state.auction.buyout(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn cancel_trade(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    payload: Result<Json<CancelTradeRequest>, JsonRejection>,
+) -> ApiResult<CancelTradeResponse> {
+    let Json(data) = payload?;
+    let request = create_request(&headers, CancelTradeRequest { id, ..data });
+    let response = state.auction.cancel_trade(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn watch_trade(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    payload: Result<Json<WatchTradeRequest>, JsonRejection>,
+) -> ApiResult<WatchTradeResponse> {
+    let Json(data) = payload?;
+    let request = create_request(&headers, WatchTradeRequest { id, ..data });
+    let response = state.auction.watch_trade(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn unwatch_trade(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    query: Result<Query<UserParams>, QueryRejection>,
+) -> ApiResult<UnwatchTradeResponse> {
+    let Query(params) = query?;
+    let request = create_request(
+        &headers,
+        UnwatchTradeRequest {
+            id,
+            user_id: params.user_id,
+        },
+    );
+    let response = state.auction.unwatch_trade(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn offer_barter(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    payload: Result<Json<OfferBarterRequest>, JsonRejection>,
+) -> ApiResult<OfferBarterResponse> {
+    let Json(data) = payload?;
+    let request = create_request(&headers, OfferBarterRequest { id, ..data });
+    let response = state.auction.offer_barter(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn list_barter_offers(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    query: Result<Query<UserParams>, QueryRejection>,
+) -> ApiResult<ListBarterOffersResponse> {
+    let Query(params) = query?;
+    let request = create_request(
+        &headers,
+        ListBarterOffersRequest {
+            id,
+            user_id: params.user_id,
+        },
+    );
+    let response = state.auction.list_barter_offers(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn accept_barter_offer(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path((id, offer_id)): Path<(String, String)>,
+    payload: Result<Json<AcceptBarterOfferRequest>, JsonRejection>,
+) -> ApiResult<AcceptBarterOfferResponse> {
+    let Json(data) = payload?;
+    let request = create_request(
+        &headers,
+        AcceptBarterOfferRequest {
+            id,
+            offer_id,
+            ..data
+        },
+    );
+    let response = state.auction.accept_barter_offer(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn reject_barter_offer(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path((id, offer_id)): Path<(String, String)>,
+    payload: Result<Json<RejectBarterOfferRequest>, JsonRejection>,
+) -> ApiResult<RejectBarterOfferResponse> {
+    let Json(data) = payload?;
+    let request = create_request(
+        &headers,
+        RejectBarterOfferRequest {
+            id,
+            offer_id,
+            ..data
+        },
+    );
+    let response = state.auction.reject_barter_offer(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn get_price_history(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(item_id): Path<String>,
+    query: Result<Query<GetPriceHistoryRequest>, QueryRejection>,
+) -> ApiResult<GetPriceHistoryResponse> {
+    let Query(params) = query?;
+    let request = create_request(&headers, GetPriceHistoryRequest { item_id, ..params });
+    let response = state.auction.get_price_history(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn suggest_price(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(item_id): Path<String>,
+    query: Result<Query<SuggestPriceRequest>, QueryRejection>,
+) -> ApiResult<SuggestPriceResponse> {
+    let Query(params) = query?;
+    let request = create_request(&headers, SuggestPriceRequest { item_id, ..params });
+    let response = state.auction.suggest_price(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn get_market_prices(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    query: Result<Query<MarketPricesParams>, QueryRejection>,
+) -> ApiResult<GetMarketPricesResponse> {
+    let Query(params) = query?;
+    let item_ids = params
+        .item_ids
+        .split(',')
+        .filter(|item_id| !item_id.is_empty())
+        .map(|item_id| item_id.trim().to_string())
+        .collect();
+    let request = create_request(&headers, GetMarketPricesRequest { item_ids });
+    let response = state.auction.get_market_prices(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn watch_item(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(item_id): Path<String>,
+    payload: Result<Json<WatchItemRequest>, JsonRejection>,
+) -> ApiResult<WatchItemResponse> {
+    let Json(data) = payload?;
+    let request = create_request(&headers, WatchItemRequest { item_id, ..data });
+    let response = state.auction.watch_item(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn unwatch_item(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(item_id): Path<String>,
+    query: Result<Query<UserParams>, QueryRejection>,
+) -> ApiResult<UnwatchItemResponse> {
+    let Query(params) = query?;
+    let request = create_request(
+        &headers,
+        UnwatchItemRequest {
+            item_id,
+            user_id: params.user_id,
+        },
+    );
+    let response = state.auction.unwatch_item(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn list_watched_trades(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+) -> ApiResult<ListWatchedTradesResponse> {
+    let request = create_request(&headers, ListWatchedTradesRequest { user_id });
+    let response = state.auction.list_watched_trades(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn create_saved_search(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+    payload: Result<Json<CreateSavedSearchRequest>, JsonRejection>,
+) -> ApiResult<CreateSavedSearchResponse> {
+    let Json(data) = payload?;
+    let request = create_request(&headers, CreateSavedSearchRequest { user_id, ..data });
+    let response = state.auction.create_saved_search(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn update_saved_search(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path((user_id, id)): Path<(String, String)>,
+    payload: Result<Json<UpdateSavedSearchRequest>, JsonRejection>,
+) -> ApiResult<UpdateSavedSearchResponse> {
+    let Json(data) = payload?;
+    let request = create_request(
+        &headers,
+        UpdateSavedSearchRequest {
+            id,
+            user_id,
+            ..data
+        },
+    );
+    let response = state.auction.update_saved_search(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn delete_saved_search(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path((user_id, id)): Path<(String, String)>,
+) -> ApiResult<DeleteSavedSearchResponse> {
+    let request = create_request(&headers, DeleteSavedSearchRequest { id, user_id });
+    let response = state.auction.delete_saved_search(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn list_saved_searches(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+) -> ApiResult<ListSavedSearchesResponse> {
+    let request = create_request(&headers, ListSavedSearchesRequest { user_id });
+    let response = state.auction.list_saved_searches(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn list_notifications(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+    query: Result<Query<ListNotificationsRequest>, QueryRejection>,
+) -> ApiResult<ListNotificationsResponse> {
+    let Query(params) = query?;
+    let request = create_request(&headers, ListNotificationsRequest { user_id, ..params });
+    let response = state.auction.list_notifications(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn list_private_offers(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+    query: Result<Query<ListPrivateOffersRequest>, QueryRejection>,
+) -> ApiResult<ListPrivateOffersResponse> {
+    let Query(params) = query?;
+    let request = create_request(&headers, ListPrivateOffersRequest { user_id, ..params });
+    let response = state.auction.list_private_offers(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn list_mail(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+) -> ApiResult<ListMailResponse> {
+    let request = create_request(&headers, ListMailRequest { user_id });
+    let response = state.auction.list_mail(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn claim_mail(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path((user_id, id)): Path<(String, String)>,
+) -> ApiResult<ClaimMailResponse> {
+    let request = create_request(&headers, ClaimMailRequest { id, user_id });
+    let response = state.auction.claim_mail(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn claim_all_mail(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+) -> ApiResult<ClaimAllMailResponse> {
+    let request = create_request(&headers, ClaimAllMailRequest { user_id });
+    let response = state.auction.claim_all_mail(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn list_trade_flags(
+    State(state): State<RestState>,
+    query: Result<Query<ListTradeFlagsRequest>, QueryRejection>,
+) -> ApiResult<ListTradeFlagsResponse> {
+    let Query(params) = query?;
+    let response = state.admin.list_trade_flags(Request::new(params)).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn get_realm_visibility(
+    State(state): State<RestState>,
+    Path(realm_id): Path<String>,
+) -> ApiResult<GetRealmVisibilityResponse> {
+    let request = Request::new(GetRealmVisibilityRequest { realm_id });
+    let response = state.admin.get_realm_visibility(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn set_realm_visibility(
+    State(state): State<RestState>,
+    Path(realm_id): Path<String>,
+    payload: Result<Json<SetRealmVisibilityRequest>, JsonRejection>,
+) -> ApiResult<SetRealmVisibilityResponse> {
+    let Json(data) = payload?;
+    let request = Request::new(SetRealmVisibilityRequest { realm_id, ..data });
+    let response = state.admin.set_realm_visibility(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn list_scheduled_trades(
+    State(state): State<RestState>,
+    query: Result<Query<ListScheduledTradesRequest>, QueryRejection>,
+) -> ApiResult<ListScheduledTradesResponse> {
+    let Query(params) = query?;
+    let response = state
+        .admin
+        .list_scheduled_trades(Request::new(params))
+        .await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn cancel_scheduled_trade(
+    State(state): State<RestState>,
+    Path((realm_id, id)): Path<(String, String)>,
+) -> ApiResult<CancelScheduledTradeResponse> {
+    let request = Request::new(CancelScheduledTradeRequest { realm_id, id });
+    let response = state.admin.cancel_scheduled_trade(request).await?;
+
+    Ok(Json(response.into_inner()))
+}
+
+async fn stream_trade_events(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let request = StreamTradeEventsRequest {
+        trade_id: Some(id),
+        user_id: None,
+    };
+
+    stream_events(state, &headers, request).await
+}
+
+async fn stream_player_events(
+    State(state): State<RestState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let request = StreamTradeEventsRequest {
+        trade_id: None,
+        user_id: Some(user_id),
+    };
+
+    stream_events(state, &headers, request).await
+}
+
+// Sends the trade events over Server-Sent Events. Each event is named
+// after its kind and carries the JSON payload.
+async fn stream_events(
+    state: RestState,
+    headers: &HeaderMap,
+    request: StreamTradeEventsRequest,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let response = state
+        .auction
+        .stream_trade_events(create_request(headers, request))
+        .await?;
+    let stream = response.into_inner().map(|received| {
+        let event = match received {
+            Ok(event) => Event::default()
+                .event(&event.kind)
+                .json_data(event)
+                .expect("serialize trade event"),
+            Err(status) => Event::default().event("error").data(status.message()),
+        };
+        Ok(event)
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
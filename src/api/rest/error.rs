@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::http::{header::RETRY_AFTER, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use tonic::{Code, Status};
+use tonic_types::StatusExt;
+
+use crate::core::error::Error;
+
+// The error returned by the REST API. Wraps the gRPC status produced by
+// the handlers, so that both APIs report errors the same way.
+#[derive(Debug)]
+pub struct ApiError(Status);
+
+#[derive(Serialize, Debug, PartialEq)]
+struct FieldViolation {
+    field: String,
+    description: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct QuotaViolation {
+    subject: String,
+    description: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct ErrorBody {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    field_violations: Vec<FieldViolation>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    quota_violations: Vec<QuotaViolation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    metadata: HashMap<String, String>,
+}
+
+impl From<&Status> for ErrorBody {
+    fn from(status: &Status) -> Self {
+        let details = status.get_error_details();
+        let (_, code) = http_status(status.code());
+
+        Self {
+            code,
+            message: status.message().to_string(),
+            field_violations: details
+                .bad_request()
+                .map(|bad_request| {
+                    bad_request
+                        .field_violations
+                        .iter()
+                        .map(|violation| FieldViolation {
+                            field: violation.field.to_owned(),
+                            description: violation.description.to_owned(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            quota_violations: details
+                .quota_failure()
+                .map(|quota_failure| {
+                    quota_failure
+                        .violations
+                        .iter()
+                        .map(|violation| QuotaViolation {
+                            subject: violation.subject.to_owned(),
+                            description: violation.description.to_owned(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            retry_after: details
+                .retry_info()
+                .and_then(|retry_info| retry_info.retry_delay)
+                .map(|retry_delay| retry_delay.as_secs_f64().ceil() as u64),
+            reason: details
+                .error_info()
+                .map(|error_info| error_info.reason.to_owned()),
+            metadata: details
+                .error_info()
+                .map(|error_info| error_info.metadata.to_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// Maps the gRPC status code onto the HTTP status code and the
+// machine-readable code name.
+fn http_status(code: Code) -> (StatusCode, &'static str) {
+    match code {
+        Code::Ok => (StatusCode::OK, "ok"),
+        Code::Cancelled => (StatusCode::REQUEST_TIMEOUT, "cancelled"),
+        Code::Unknown => (StatusCode::INTERNAL_SERVER_ERROR, "unknown"),
+        Code::InvalidArgument => (StatusCode::BAD_REQUEST, "invalid_argument"),
+        Code::DeadlineExceeded => (StatusCode::GATEWAY_TIMEOUT, "deadline_exceeded"),
+        Code::NotFound => (StatusCode::NOT_FOUND, "not_found"),
+        Code::AlreadyExists => (StatusCode::CONFLICT, "already_exists"),
+        Code::PermissionDenied => (StatusCode::FORBIDDEN, "permission_denied"),
+        Code::ResourceExhausted => (StatusCode::TOO_MANY_REQUESTS, "resource_exhausted"),
+        Code::FailedPrecondition => (StatusCode::BAD_REQUEST, "failed_precondition"),
+        Code::Aborted => (StatusCode::CONFLICT, "aborted"),
+        Code::OutOfRange => (StatusCode::BAD_REQUEST, "out_of_range"),
+        Code::Unimplemented => (StatusCode::NOT_IMPLEMENTED, "unimplemented"),
+        Code::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        Code::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+        Code::DataLoss => (StatusCode::INTERNAL_SERVER_ERROR, "data_loss"),
+        Code::Unauthenticated => (StatusCode::UNAUTHORIZED, "unauthenticated"),
+    }
+}
+
+impl From<Status> for ApiError {
+    fn from(status: Status) -> Self {
+        Self(status)
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        Self(Status::from(err))
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::from(Error::ValidationError {
+            field: "body".to_string(),
+            message: rejection.body_text(),
+        })
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::from(Error::ValidationError {
+            field: "query".to_string(),
+            message: rejection.body_text(),
+        })
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status_code, _) = http_status(self.0.code());
+        let body = ErrorBody::from(&self.0);
+        let retry_after = body.retry_after;
+
+        let mut response = (status_code, Json(body)).into_response();
+        if let Some(retry_after) = retry_after {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, retry_after.into());
+        }
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::http::{header::RETRY_AFTER, StatusCode};
+    use axum::response::IntoResponse;
+    use tonic::Status;
+
+    use crate::api::rest::error::{ApiError, ErrorBody, FieldViolation};
+    use crate::core::error::Error;
+
+    #[test]
+    fn test_validation_error_maps_to_bad_request() {
+        let status = Status::from(Error::ValidationError {
+            field: "amount".to_string(),
+            message: "The bid is too low.".to_string(),
+        });
+        let body = ErrorBody::from(&status);
+
+        assert_eq!(body.code, "invalid_argument");
+        assert_eq!(
+            body.field_violations,
+            vec![FieldViolation {
+                field: "amount".to_string(),
+                description: "The bid is too low.".to_string(),
+            }]
+        );
+        assert_eq!(
+            ApiError::from(status).into_response().status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn test_conflict_keeps_message() {
+        let status = Status::from(Error::Conflict(
+            "The item expired or was bought by other player.".to_string(),
+        ));
+        let body = ErrorBody::from(&status);
+
+        assert_eq!(body.code, "aborted");
+        assert_eq!(
+            body.message,
+            "The item expired or was bought by other player."
+        );
+        assert_eq!(
+            ApiError::from(status).into_response().status(),
+            StatusCode::CONFLICT
+        );
+    }
+
+    #[test]
+    fn test_limit_exceeded_sets_retry_after() {
+        let response = ApiError::from(Error::LimitExceeded {
+            subject: "bids_per_minute".to_string(),
+            message: "Too many bids.".to_string(),
+            retry_after: Some(Duration::from_millis(1500)),
+        })
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "2");
+    }
+}
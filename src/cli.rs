@@ -1,5 +1,12 @@
 use structopt::StructOpt;
 
+use crate::api::auction::policies::bidding::LinkedBidAction;
+use crate::api::auction::policies::cancellation::parse_penalty_percent;
+use crate::api::auction::policies::houses::AuctionHouses;
+use crate::multiplex_service::validate_origins;
+use crate::services::inventory::InventoryHookKind;
+use crate::services::outbox::sinks::OutboxSinkKind;
+
 #[derive(StructOpt, Debug)]
 #[structopt(
     name = "TradingPost",
@@ -22,6 +29,22 @@ pub struct CliOptions {
     )]
     pub port: u16,
 
+    #[structopt(
+        long = "admin-host",
+        help = "The IP the admin API listens on. Keep it private, the admin API has no authentication",
+        default_value = "127.0.0.1",
+        env = "ADMIN_HOST"
+    )]
+    pub admin_host: String,
+
+    #[structopt(
+        long = "admin-port",
+        help = "The port the admin API listens on",
+        default_value = "8001",
+        env = "ADMIN_PORT"
+    )]
+    pub admin_port: u16,
+
     #[structopt(
         long = "cassandra-nodes",
         help = "Cassandra nodes",
@@ -45,4 +68,257 @@ pub struct CliOptions {
         env = "CASSANDRA_PASSWORD"
     )]
     pub cassandra_password: String,
+
+    #[structopt(
+        long = "backfill-default-realm",
+        help = "Copy the trades, sales and market summaries stored before realms were introduced into the default realm and exit"
+    )]
+    pub backfill_default_realm: bool,
+
+    #[structopt(
+        long = "cancel-with-bids",
+        help = "Allow sellers to cancel trades with bids for a penalty: `true` or `false`",
+        default_value = "false",
+        parse(try_from_str),
+        env = "CANCEL_WITH_BIDS"
+    )]
+    pub cancel_with_bids: bool,
+
+    #[structopt(
+        long = "cancel-penalty-percent",
+        help = "The penalty for cancelling a trade with bids, as a percent of the current bid from 0 to 100",
+        default_value = "10",
+        parse(try_from_str = parse_penalty_percent),
+        env = "CANCEL_PENALTY_PERCENT"
+    )]
+    pub cancel_penalty_percent: i64,
+
+    #[structopt(
+        long = "cancel-with-bids-disabled-categories",
+        help = "Comma-separated item categories that can't be cancelled once someone did a bid",
+        default_value = "",
+        env = "CANCEL_WITH_BIDS_DISABLED_CATEGORIES"
+    )]
+    pub cancel_with_bids_disabled_categories: String,
+
+    #[structopt(
+        long = "max-active-listings",
+        help = "The maximum amount of active listings per player. Zero disables the limit",
+        default_value = "50",
+        env = "MAX_ACTIVE_LISTINGS"
+    )]
+    pub max_active_listings: usize,
+
+    #[structopt(
+        long = "max-listings-per-hour",
+        help = "The maximum amount of created listings per player per hour. Zero disables the limit",
+        default_value = "100",
+        env = "MAX_LISTINGS_PER_HOUR"
+    )]
+    pub max_listings_per_hour: usize,
+
+    #[structopt(
+        long = "max-bids-per-minute",
+        help = "The maximum amount of bids per player per minute. Zero disables the limit",
+        default_value = "30",
+        env = "MAX_BIDS_PER_MINUTE"
+    )]
+    pub max_bids_per_minute: usize,
+
+    #[structopt(
+        long = "linked-accounts-file",
+        help = "The file with groups of linked accounts, one group per line",
+        env = "LINKED_ACCOUNTS_FILE"
+    )]
+    pub linked_accounts_file: Option<String>,
+
+    #[structopt(
+        long = "linked-bid-action",
+        help = "What to do with bids between linked accounts: `block` or `log`",
+        default_value = "block",
+        env = "LINKED_BID_ACTION"
+    )]
+    pub linked_bid_action: LinkedBidAction,
+
+    #[structopt(
+        long = "trade-events-capacity",
+        help = "The amount of trade events buffered for streaming subscribers",
+        default_value = "1024",
+        env = "TRADE_EVENTS_CAPACITY"
+    )]
+    pub trade_events_capacity: usize,
+
+    #[structopt(
+        long = "expiry-scan-interval",
+        help = "The interval between scans for expired trades in seconds. Zero disables scans",
+        default_value = "60",
+        env = "EXPIRY_SCAN_INTERVAL"
+    )]
+    pub expiry_scan_interval: u64,
+
+    #[structopt(
+        long = "schedule-scan-interval",
+        help = "The interval between scans for scheduled trades to put on sale in seconds. Zero disables scans",
+        default_value = "10",
+        env = "SCHEDULE_SCAN_INTERVAL"
+    )]
+    pub schedule_scan_interval: u64,
+
+    #[structopt(
+        long = "wash-trading-scan-interval",
+        help = "The interval between wash trading scans in seconds. Zero disables scans",
+        default_value = "3600",
+        env = "WASH_TRADING_SCAN_INTERVAL"
+    )]
+    pub wash_trading_scan_interval: u64,
+
+    #[structopt(
+        long = "wash-trading-lookback-hours",
+        help = "How many hours of completed trades are analyzed by a wash trading scan",
+        default_value = "24",
+        env = "WASH_TRADING_LOOKBACK_HOURS"
+    )]
+    pub wash_trading_lookback_hours: u64,
+
+    #[structopt(
+        long = "wash-trading-pair-threshold",
+        help = "The amount of sales between the same seller and buyer to flag them",
+        default_value = "3",
+        env = "WASH_TRADING_PAIR_THRESHOLD"
+    )]
+    pub wash_trading_pair_threshold: usize,
+
+    #[structopt(
+        long = "wash-trading-price-multiplier",
+        help = "How many times a price must exceed the median price of the item to flag the sale",
+        default_value = "5",
+        env = "WASH_TRADING_PRICE_MULTIPLIER"
+    )]
+    pub wash_trading_price_multiplier: i64,
+
+    #[structopt(
+        long = "wash-trading-min-samples",
+        help = "The minimal amount of sales of the item to calculate its median price",
+        default_value = "5",
+        env = "WASH_TRADING_MIN_SAMPLES"
+    )]
+    pub wash_trading_min_samples: usize,
+
+    #[structopt(
+        long = "cors-allowed-origins",
+        help = "Comma-separated origins allowed to call the API from browsers, like https://game.example.com. Use * to allow any origin. No origin is allowed by default",
+        default_value = "",
+        validator = validate_origins,
+        env = "CORS_ALLOWED_ORIGINS"
+    )]
+    pub cors_allowed_origins: String,
+
+    #[structopt(
+        long = "outbox-sink",
+        help = "Where the relay publishes domain events: `none`, `file` or `webhook`",
+        default_value = "none",
+        env = "OUTBOX_SINK"
+    )]
+    pub outbox_sink: OutboxSinkKind,
+
+    #[structopt(
+        long = "outbox-file",
+        help = "The path to the JSON-lines file used by the `file` sink",
+        default_value = "outbox.jsonl",
+        env = "OUTBOX_FILE"
+    )]
+    pub outbox_file: String,
+
+    #[structopt(
+        long = "outbox-webhook-url",
+        help = "The URL that receives events from the `webhook` sink",
+        env = "OUTBOX_WEBHOOK_URL"
+    )]
+    pub outbox_webhook_url: Option<String>,
+
+    #[structopt(
+        long = "outbox-relay-interval",
+        help = "The interval between outbox relay runs in seconds",
+        default_value = "5",
+        env = "OUTBOX_RELAY_INTERVAL"
+    )]
+    pub outbox_relay_interval: u64,
+
+    #[structopt(
+        long = "game-server-webhook-urls",
+        help = "Comma-separated URLs notified about sales, outbids and expired trades",
+        default_value = "",
+        env = "GAME_SERVER_WEBHOOK_URLS"
+    )]
+    pub game_server_webhook_urls: String,
+
+    #[structopt(
+        long = "game-server-webhook-secret",
+        help = "The secret used to sign webhook payloads with HMAC-SHA256",
+        env = "GAME_SERVER_WEBHOOK_SECRET"
+    )]
+    pub game_server_webhook_secret: Option<String>,
+
+    #[structopt(
+        long = "webhook-max-attempts",
+        help = "How many times a webhook is sent before it's moved to the dead-letter table",
+        default_value = "5",
+        env = "WEBHOOK_MAX_ATTEMPTS"
+    )]
+    pub webhook_max_attempts: u32,
+
+    #[structopt(
+        long = "webhook-retry-delay",
+        help = "The delay before the first webhook retry in milliseconds. Doubles with every attempt",
+        default_value = "500",
+        env = "WEBHOOK_RETRY_DELAY"
+    )]
+    pub webhook_retry_delay: u64,
+
+    #[structopt(
+        long = "webhook-retry-interval",
+        help = "The interval between retries of the webhooks failed by game servers in seconds",
+        default_value = "5",
+        env = "WEBHOOK_RETRY_INTERVAL"
+    )]
+    pub webhook_retry_interval: u64,
+
+    #[structopt(
+        long = "inventory-hook",
+        help = "Where the items of barter trades are reserved and moved: `webhook` or `local`. The `local` hook keeps them in memory and is meant only for local runs and tests",
+        default_value = "webhook",
+        env = "INVENTORY_HOOK"
+    )]
+    pub inventory_hook: InventoryHookKind,
+
+    #[structopt(
+        long = "inventory-webhook-url",
+        help = "The base URL of the game server used by the `webhook` inventory hook",
+        env = "INVENTORY_WEBHOOK_URL"
+    )]
+    pub inventory_webhook_url: Option<String>,
+
+    #[structopt(
+        long = "inventory-retry-interval",
+        help = "The interval between retries of the inventory changes failed by the hook in seconds. Zero disables retries",
+        default_value = "30",
+        env = "INVENTORY_RETRY_INTERVAL"
+    )]
+    pub inventory_retry_interval: u64,
+
+    #[structopt(
+        long = "allowed-currencies",
+        help = "Comma-separated currencies that trades can be priced in",
+        default_value = "gold",
+        env = "ALLOWED_CURRENCIES"
+    )]
+    pub allowed_currencies: String,
+
+    #[structopt(
+        long = "auction-houses",
+        help = "Comma-separated auction houses as `name:factions:deposit:commission`. Factions are separated by `|`, use * to open the house for any faction. Deposit and commission are percents of the price",
+        default_value = "default:*:0:0",
+        env = "AUCTION_HOUSES"
+    )]
+    pub auction_houses: AuctionHouses,
 }
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// The amount of cached entries after which the expired ones are dropped.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+// An in-process cache, that keeps each entry for the `ttl` duration.
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Eq + Hash,
+    V: Clone,
+{
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.lock().unwrap();
+
+        entries
+            .get(key)
+            .filter(|(cached_at, _)| cached_at.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() > PRUNE_THRESHOLD {
+            entries.retain(|_, (cached_at, _)| cached_at.elapsed() < self.ttl);
+        }
+
+        entries.insert(key, (Instant::now(), value));
+    }
+
+    pub fn remove(&self, key: &K) {
+        self.entries.lock().unwrap().remove(key);
+    }
+}
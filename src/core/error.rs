@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
 use cdrs_tokio::error::Error as CdrsError;
 use derive_more::Display;
 use tonic::{Code, Status};
@@ -13,6 +16,24 @@ pub enum Error {
         message: String,
     },
     CassandraError(String),
+    #[display(fmt = "Limit exceeded for `{0}`: {1}", subject, message)]
+    LimitExceeded {
+        subject: String,
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    DeliveryFailed(String),
+    #[display(fmt = "The amount of currency is out of range in `{0}`", _0)]
+    MoneyOverflow(String),
+    #[display(fmt = "The subscriber is too slow and missed {0} events", skipped)]
+    SubscriberLagged {
+        skipped: u64,
+    },
+    #[display(fmt = "The stored {0} can't be decoded", _0)]
+    CorruptData(String),
+    // The concurrent change was applied first. The message tells the player
+    // what happened to the trade.
+    Conflict(String),
 }
 
 impl Error {
@@ -20,11 +41,20 @@ impl Error {
         match self {
             Error::ValidationError { .. } => Code::InvalidArgument,
             Error::CassandraError(_) => Code::Internal,
+            Error::DeliveryFailed(_) => Code::Unavailable,
+            Error::MoneyOverflow(_) => Code::OutOfRange,
+            Error::LimitExceeded { .. } => Code::ResourceExhausted,
+            Error::SubscriberLagged { .. } => Code::Aborted,
+            Error::CorruptData(_) => Code::DataLoss,
+            Error::Conflict(_) => Code::Aborted,
         }
     }
 
     fn message(&self) -> String {
-        self.code().description().to_string()
+        match self {
+            Error::Conflict(message) => message.to_owned(),
+            _ => self.code().description().to_string(),
+        }
     }
 
     fn details(&self) -> ErrorDetails {
@@ -34,6 +64,21 @@ impl Error {
             Error::ValidationError { field, message } => {
                 details.add_bad_request_violation(field, message);
             }
+            Error::LimitExceeded {
+                subject,
+                message,
+                retry_after,
+            } => {
+                details.add_quota_failure_violation(subject, message);
+                details.set_retry_info(*retry_after);
+            }
+            Error::SubscriberLagged { skipped } => {
+                details.set_error_info(
+                    "SUBSCRIBER_LAGGED",
+                    "trading-post",
+                    HashMap::from([("skipped".to_string(), skipped.to_string())]),
+                );
+            }
             _ => {}
         };
 
@@ -1,4 +1,7 @@
+pub mod cache;
 pub mod error;
+pub mod money;
 pub mod orm;
 pub mod pagination;
+pub mod realm;
 pub mod validation;
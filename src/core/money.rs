@@ -0,0 +1,189 @@
+use std::fmt;
+
+use cdrs_tokio::types::rows::Row;
+use cdrs_tokio::types::value::Bytes;
+use cdrs_tokio::types::IntoRustByName;
+use serde::{Deserialize, Serialize};
+
+use crate::core::error::{Error, Result};
+
+const COPPER_PER_SILVER: i64 = 100;
+const COPPER_PER_GOLD: i64 = 100 * COPPER_PER_SILVER;
+
+// The amount of currency in its smallest denomination, e.g. copper for
+// the default currency. Stored and sent over the wire as a plain integer,
+// but every calculation goes through the checked arithmetic, so that large
+// prices never wrap around.
+#[derive(
+    Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(transparent)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+    pub const ONE: Money = Money(1);
+
+    pub fn new(amount: i64) -> Self {
+        Self(amount)
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Money> {
+        self.0
+            .checked_add(other.0)
+            .map(Money)
+            .ok_or_else(|| Error::MoneyOverflow(format!("{0} + {1}", self, other)))
+    }
+
+    pub fn checked_sub(self, other: Money) -> Result<Money> {
+        self.0
+            .checked_sub(other.0)
+            .map(Money)
+            .ok_or_else(|| Error::MoneyOverflow(format!("{0} - {1}", self, other)))
+    }
+
+    pub fn checked_mul(self, quantity: i64) -> Result<Money> {
+        self.0
+            .checked_mul(quantity)
+            .map(Money)
+            .ok_or_else(|| Error::MoneyOverflow(format!("{0} * {1}", self, quantity)))
+    }
+
+    // Splits the amount into equal parts, rounded down. Used to get the
+    // price of a single item in a stack.
+    pub fn checked_div(self, quantity: i64) -> Result<Money> {
+        self.0
+            .checked_div(quantity)
+            .map(Money)
+            .ok_or_else(|| Error::MoneyOverflow(format!("{0} / {1}", self, quantity)))
+    }
+
+    // Returns the given percent of the amount, rounded down.
+    pub fn percent(self, percent: i64) -> Result<Money> {
+        let amount = i128::from(self.0) * i128::from(percent) / 100;
+        i64::try_from(amount)
+            .map(Money)
+            .map_err(|_| Error::MoneyOverflow(format!("{0}% of {1}", percent, self)))
+    }
+
+    // Formats the amount as gold, silver and copper coins, e.g. "12g 5s 30c".
+    // Empty denominations are skipped, except for the zero amount. Only
+    // the default currency is minted in coins, other currencies are shown
+    // as plain amounts.
+    pub fn to_coins(self) -> String {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let amount = self.0.unsigned_abs();
+        let gold = amount / COPPER_PER_GOLD as u64;
+        let silver = amount % COPPER_PER_GOLD as u64 / COPPER_PER_SILVER as u64;
+        let copper = amount % COPPER_PER_SILVER as u64;
+
+        let denominations: Vec<String> = [(gold, "g"), (silver, "s"), (copper, "c")]
+            .into_iter()
+            .filter(|(value, _)| *value > 0)
+            .map(|(value, suffix)| format!("{0}{1}", value, suffix))
+            .collect();
+
+        match denominations.is_empty() {
+            true => "0c".to_string(),
+            false => format!("{0}{1}", sign, denominations.join(" ")),
+        }
+    }
+}
+
+// Formats the plain amount, that doesn't depend on the currency.
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{0}", self.0)
+    }
+}
+
+impl From<i64> for Money {
+    fn from(amount: i64) -> Self {
+        Self(amount)
+    }
+}
+
+impl From<Money> for i64 {
+    fn from(money: Money) -> Self {
+        money.0
+    }
+}
+
+// Stored in the bigint columns.
+impl From<Money> for Bytes {
+    fn from(money: Money) -> Self {
+        Bytes::from(money.0)
+    }
+}
+
+impl IntoRustByName<Money> for Row {
+    fn get_by_name(&self, name: &str) -> cdrs_tokio::Result<Option<Money>> {
+        let amount: Option<i64> = self.get_by_name(name)?;
+        Ok(amount.map(Money))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::error::Error;
+    use crate::core::money::Money;
+
+    #[test]
+    fn test_to_coins_formats_denominations() {
+        assert_eq!(Money::new(0).to_coins(), "0c");
+        assert_eq!(Money::new(7).to_coins(), "7c");
+        assert_eq!(Money::new(1_05).to_coins(), "1s 5c");
+        assert_eq!(Money::new(12_05_30).to_coins(), "12g 5s 30c");
+        assert_eq!(Money::new(3_00_00).to_coins(), "3g");
+        assert_eq!(Money::new(-2_00_01).to_coins(), "-2g 1c");
+        assert_eq!(Money::new(i64::MIN).to_coins(), "-922337203685477g 58s 8c");
+    }
+
+    #[test]
+    fn test_display_formats_plain_amount() {
+        assert_eq!(Money::new(12_05_30).to_string(), "120530");
+        assert_eq!(Money::new(-7).to_string(), "-7");
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let amount = Money::new(250);
+
+        assert_eq!(amount.checked_add(Money::new(50)).unwrap(), Money::new(300));
+        assert_eq!(
+            amount.checked_sub(Money::new(300)).unwrap(),
+            Money::new(-50)
+        );
+        assert_eq!(amount.checked_mul(4).unwrap(), Money::new(1000));
+        assert_eq!(amount.checked_div(3).unwrap(), Money::new(83));
+        assert_eq!(amount.percent(10).unwrap(), Money::new(25));
+    }
+
+    #[test]
+    fn test_checked_arithmetic_reports_overflow() {
+        let amount = Money::new(i64::MAX);
+
+        let results = [
+            amount.checked_add(Money::ONE),
+            Money::new(i64::MIN).checked_sub(Money::ONE),
+            amount.checked_mul(2),
+            amount.checked_div(0),
+            amount.percent(200),
+        ];
+
+        for result in results {
+            assert!(matches!(result, Err(Error::MoneyOverflow(_))));
+        }
+    }
+
+    #[test]
+    fn test_percent_doesnt_overflow_on_intermediate_values() {
+        let amount = Money::new(i64::MAX);
+
+        assert_eq!(amount.percent(50).unwrap(), Money::new(i64::MAX / 2));
+    }
+}
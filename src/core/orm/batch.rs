@@ -0,0 +1,39 @@
+use cdrs_tokio::frame::Envelope;
+use cdrs_tokio::query::{BatchQueryBuilder, QueryValues};
+use log::error;
+
+use crate::core::error::{Error, Result};
+use crate::core::orm::query::Query;
+use crate::core::orm::session::CassandraSession;
+
+// Groups the queries into a logged batch, so that either all of them
+// are applied or none.
+pub struct Batch {
+    builder: BatchQueryBuilder,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self {
+            builder: BatchQueryBuilder::new(),
+        }
+    }
+
+    pub fn add(mut self, query: &Query, query_values: &QueryValues) -> Result<Self> {
+        let values = query.get_positional_values(query_values)?;
+        self.builder = self.builder.add_query(query.get_raw_cql(), values);
+        Ok(self)
+    }
+
+    pub async fn execute(self, session: &CassandraSession) -> Result<Envelope> {
+        let batch = self.builder.build().map_err(|err| {
+            error!("{}", err);
+            Error::CassandraError("Can't build the batch.".to_string())
+        })?;
+
+        session.batch(batch).await.map_err(|err| {
+            error!("{}", err);
+            err.into()
+        })
+    }
+}
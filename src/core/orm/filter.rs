@@ -34,6 +34,9 @@ pub enum Operator {
     Eq,
     Lte,
     Gte,
+    Gt,
+    // Matches any value of the list, passed as the filter value.
+    In,
     LikeContains(String),
 }
 
@@ -43,6 +46,8 @@ impl Operator {
             Operator::Eq => String::from("="),
             Operator::Lte => String::from("<="),
             Operator::Gte => String::from(">="),
+            Operator::Gt => String::from(">"),
+            Operator::In => String::from("IN"),
             Operator::LikeContains(pattern) => pattern.to_owned(),
         }
     }
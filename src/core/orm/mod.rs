@@ -1,3 +1,4 @@
+pub mod batch;
 pub mod filter;
 pub mod query;
 pub mod query_builder;
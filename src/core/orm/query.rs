@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use cdrs_tokio::frame::{Envelope, TryFromRow};
 use cdrs_tokio::query::{QueryParamsBuilder, QueryValues};
 use cdrs_tokio::types::rows::Row;
+use cdrs_tokio::types::IntoRustByName;
 use log::error;
 use serde::Serialize;
 
@@ -10,10 +11,14 @@ use crate::core::error::{Error, Result};
 use crate::core::orm::session::CassandraSession;
 use crate::core::pagination::PaginationParams;
 
+// The amount of rows fetched per request when reading all entries.
+const ENTRIES_PAGE_SIZE: i32 = 1000;
+
 #[derive(Debug)]
 pub struct Query {
     raw_cql: String,
     query_values: QueryValues,
+    value_names: Vec<String>,
 }
 
 impl Query {
@@ -21,9 +26,38 @@ impl Query {
         Query {
             raw_cql: raw_cql.to_owned(),
             query_values,
+            value_names: vec![],
         }
     }
 
+    pub fn with_value_names(mut self, value_names: Vec<String>) -> Self {
+        self.value_names = value_names;
+        self
+    }
+
+    pub fn get_raw_cql(&self) -> &str {
+        &self.raw_cql
+    }
+
+    // Returns the query values ordered the same way as placeholders in the query.
+    // Required for batches, because Cassandra doesn't accept named values there.
+    pub fn get_positional_values(&self, custom_query_values: &QueryValues) -> Result<QueryValues> {
+        let mut values = match self.get_merged_query_values(custom_query_values) {
+            QueryValues::NamedValues(values) => values,
+            simple_values => return Ok(simple_values),
+        };
+
+        self.value_names
+            .iter()
+            .map(|name| {
+                values.remove(name).ok_or_else(|| {
+                    Error::CassandraError(format!("The `{0}` value is missing.", name))
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(QueryValues::SimpleValues)
+    }
+
     pub async fn insert(&self, session: &CassandraSession, query_values: &QueryValues) {
         session
             .query_with_values(&self.raw_cql, query_values.to_owned())
@@ -31,17 +65,59 @@ impl Query {
             .expect("Error inserting data");
     }
 
-    pub async fn update(
+    // Same as `insert`, but returns the error to the caller, that has to undo
+    // the changes made outside of Cassandra.
+    pub async fn try_insert(
         &self,
         session: &CassandraSession,
         query_values: &QueryValues,
-    ) -> Result<Envelope> {
-        let update_query_values = self.get_merged_query_values(&query_values);
-
+    ) -> Result<()> {
         session
+            .query_with_values(&self.raw_cql, query_values.to_owned())
+            .await
+            .map_err(|err| {
+                error!("{}", err);
+                Error::from(err)
+            })?;
+        Ok(())
+    }
+
+    // Executes the lightweight transaction and returns whether it was applied.
+    // The conditions can check the same columns that get new values, so the
+    // values are bound by their position.
+    pub async fn update_if(
+        &self,
+        session: &CassandraSession,
+        query_values: &QueryValues,
+    ) -> Result<bool> {
+        let update_query_values = self.get_positional_values(query_values)?;
+
+        let rows = session
             .query_with_values(&self.raw_cql, update_query_values)
             .await
-            .map(|env| env)
+            .map_err(|err| {
+                error!("{}", err);
+                Error::from(err)
+            })?
+            .response_body()
+            .map_err(|err| {
+                error!("{}", err);
+                Error::CassandraError("Can't read the response body.".to_string())
+            })?
+            .into_rows()
+            .unwrap_or_default();
+
+        let applied: Option<bool> = match rows.first() {
+            Some(row) => row.get_by_name("[applied]")?,
+            None => None,
+        };
+        Ok(applied.unwrap_or(false))
+    }
+
+    pub async fn delete(&self, session: &CassandraSession) -> Result<Envelope> {
+        session
+            .query_with_values(&self.raw_cql, self.query_values.to_owned())
+            .await
             .map_err(|err| {
                 error!("{}", err);
                 err.into()
@@ -77,6 +153,34 @@ impl Query {
         Ok(T::try_from_row(row).expect("decode row"))
     }
 
+    pub async fn get_entries<T>(&self, session: &CassandraSession) -> Result<Vec<T>>
+    where
+        T: Serialize + TryFromRow,
+    {
+        let mut pager = session.paged(ENTRIES_PAGE_SIZE);
+        let mut query_pager = pager.query_with_params(
+            &self.raw_cql,
+            QueryParamsBuilder::new()
+                .with_values(self.query_values.to_owned())
+                .build(),
+        );
+
+        let mut entries = vec![];
+        loop {
+            let rows = query_pager.next().await?;
+            entries.extend(
+                rows.into_iter()
+                    .map(|row| T::try_from_row(row).expect("decode row")),
+            );
+
+            if !query_pager.has_more() {
+                break;
+            }
+        }
+
+        Ok(entries)
+    }
+
     pub async fn get_paginated_entries<T>(
         &self,
         session: &CassandraSession,
@@ -115,6 +219,61 @@ impl Query {
             .collect())
     }
 
+    // Same as `get_paginated_entries`, but pages only the entries accepted by
+    // the predicate, so that the entries filtered out by the application don't
+    // leave short pages.
+    pub async fn get_paginated_entries_where<T, F>(
+        &self,
+        session: &CassandraSession,
+        pagination_params: &PaginationParams,
+        predicate: F,
+    ) -> Result<Vec<T>>
+    where
+        T: Serialize + TryFromRow,
+        F: Fn(&T) -> bool,
+    {
+        let page_size = pagination_params.page_size as usize;
+        if page_size == 0 {
+            return Ok(vec![]);
+        }
+        let skipped = (pagination_params.page.max(1) as usize - 1) * page_size;
+
+        let mut pager = session.paged(pagination_params.page_size);
+        let mut query_pager = pager.query_with_params(
+            &self.raw_cql,
+            QueryParamsBuilder::new()
+                .with_values(self.query_values.to_owned())
+                .build(),
+        );
+
+        let mut accepted = 0;
+        let mut entries = vec![];
+        loop {
+            let rows = query_pager.next().await?;
+            for entry in rows
+                .into_iter()
+                .map(|row| T::try_from_row(row).expect("decode row"))
+                .filter(|entry| predicate(entry))
+            {
+                accepted += 1;
+                if accepted <= skipped {
+                    continue;
+                }
+
+                entries.push(entry);
+                if entries.len() == page_size {
+                    return Ok(entries);
+                }
+            }
+
+            if !query_pager.has_more() {
+                break;
+            }
+        }
+
+        Ok(entries)
+    }
+
     fn get_merged_query_values(&self, custom_query_values: &QueryValues) -> QueryValues {
         match custom_query_values {
             QueryValues::SimpleValues(_) => self.get_merged_simple_values(custom_query_values),
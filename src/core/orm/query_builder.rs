@@ -11,6 +11,8 @@ pub struct QueryBuilder<'a> {
     columns: &'a [&'a str],
     limit: Option<usize>,
     filters: Vec<Filter<'a>>,
+    conditions: Vec<Filter<'a>>,
+    if_not_exists: bool,
     allow_filtering: bool,
 }
 
@@ -22,6 +24,8 @@ impl<'a> QueryBuilder<'a> {
             columns: &[],
             limit: None,
             filters: vec![],
+            conditions: vec![],
+            if_not_exists: false,
             allow_filtering: false,
         }
     }
@@ -56,6 +60,28 @@ impl<'a> QueryBuilder<'a> {
         self
     }
 
+    // Applies the update only when the column has no value yet.
+    // Turns the query into a lightweight transaction.
+    pub fn only_if_null(mut self, column: &'a str) -> Self {
+        self.conditions
+            .push(Filter::new(column, Operator::Eq, None));
+        self
+    }
+
+    // Applies the update only when the column value matches the condition.
+    // Turns the query into a lightweight transaction.
+    pub fn only_if(mut self, condition: Filter<'a>) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    // Inserts the row only when it doesn't exist yet.
+    // Turns the query into a lightweight transaction.
+    pub fn if_not_exists(mut self) -> Self {
+        self.if_not_exists = true;
+        self
+    }
+
     pub fn allow_filtering(mut self, value: bool) -> Self {
         self.allow_filtering = value;
         self
@@ -66,10 +92,11 @@ impl<'a> QueryBuilder<'a> {
             QueryType::Select => self.build_select_query(),
             QueryType::Insert => self.build_insert_query(),
             QueryType::Update => self.build_update_query(),
+            QueryType::Delete => self.build_delete_query(),
         };
         let query_values = self.get_query_values();
 
-        Query::new(&raw_cql, query_values)
+        Query::new(&raw_cql, query_values).with_value_names(self.get_value_names())
     }
 
     fn build_select_query(&self) -> String {
@@ -109,6 +136,10 @@ impl<'a> QueryBuilder<'a> {
                 .join(", ")
         ));
 
+        if self.if_not_exists {
+            query.push("IF NOT EXISTS".to_owned());
+        }
+
         if self.allow_filtering {
             query.push("ALLOW FILTERING".to_owned());
         }
@@ -133,6 +164,10 @@ impl<'a> QueryBuilder<'a> {
             query.push(self.build_where_clause());
         }
 
+        if !self.conditions.is_empty() {
+            query.push(self.build_if_clause());
+        }
+
         if self.allow_filtering {
             query.push("ALLOW FILTERING".to_owned());
         }
@@ -140,6 +175,18 @@ impl<'a> QueryBuilder<'a> {
         query.join(" ")
     }
 
+    fn build_delete_query(&self) -> String {
+        let mut query = Vec::<String>::new();
+        query.push(QueryType::Delete.to_string());
+        query.push(self.table.to_owned());
+
+        if !self.filters.is_empty() {
+            query.push(self.build_where_clause());
+        }
+
+        query.join(" ")
+    }
+
     fn build_where_clause(&self) -> String {
         let conditions = self
             .filters
@@ -164,6 +211,49 @@ impl<'a> QueryBuilder<'a> {
         format!("WHERE {}", conditions)
     }
 
+    fn build_if_clause(&self) -> String {
+        let conditions = self
+            .conditions
+            .iter()
+            .map(|condition| match condition.get_value() {
+                Some(_) => format!(
+                    "{} {} ?",
+                    condition.get_field_name(),
+                    condition.get_operator().to_string()
+                ),
+                None => format!("{} = null", condition.get_field_name()),
+            })
+            .collect::<Vec<String>>()
+            .join(" AND ");
+
+        format!("IF {}", conditions)
+    }
+
+    // Returns the names of values in the same order as placeholders
+    // appear in the query.
+    fn get_value_names(&self) -> Vec<String> {
+        let filter_names = self
+            .filters
+            .iter()
+            .filter(|filter| filter.get_value().is_some())
+            .map(|filter| filter.get_field_name().to_owned());
+        let column_names = self.columns.iter().map(|column| column.to_string());
+        let condition_names = self
+            .conditions
+            .iter()
+            .filter(|condition| condition.get_value().is_some())
+            .map(|condition| get_condition_value_name(condition.get_field_name()));
+
+        match self.query_type {
+            QueryType::Select | QueryType::Delete => filter_names.collect(),
+            QueryType::Insert => column_names.collect(),
+            QueryType::Update => column_names
+                .chain(filter_names)
+                .chain(condition_names)
+                .collect(),
+        }
+    }
+
     fn get_query_values(&self) -> QueryValues {
         let mut values = HashMap::new();
 
@@ -174,15 +264,29 @@ impl<'a> QueryBuilder<'a> {
             }
         }
 
+        for condition in self.conditions.iter() {
+            if let Some(value) = condition.get_value() {
+                let value_name = get_condition_value_name(condition.get_field_name());
+                values.insert(value_name, value);
+            }
+        }
+
         QueryValues::NamedValues(values)
     }
 }
 
+// Conditions often check the previous value of the updated column, so their
+// values are named differently from the new values.
+fn get_condition_value_name(column: &str) -> String {
+    format!("if_{}", column)
+}
+
 #[derive(Debug, Clone)]
 pub enum QueryType {
     Select,
     Insert,
     Update,
+    Delete,
 }
 
 impl QueryType {
@@ -191,6 +295,7 @@ impl QueryType {
             QueryType::Select => String::from("SELECT"),
             QueryType::Insert => String::from("INSERT INTO"),
             QueryType::Update => String::from("UPDATE"),
+            QueryType::Delete => String::from("DELETE FROM"),
         }
     }
 }
@@ -316,6 +421,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_build_select_query_with_in_filter() {
+        let query = QueryBuilder::new("trading_post.trade")
+            .columns(&["id", "item_id", "item_name"])
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::In,
+                Some(vec!["eu", "us"].into()),
+            ))
+            .build_select_query();
+
+        assert_eq!(
+            query,
+            "SELECT id, item_id, item_name FROM trading_post.trade WHERE realm_id IN ?"
+        );
+    }
+
     #[test]
     fn test_build_select_query_with_like_check() {
         let query = QueryBuilder::new("trading_post.trade")
@@ -480,4 +602,104 @@ mod tests {
             "SELECT id, item_id, item_name FROM trading_post.trade WHERE id = ? AND item_id = ? ALLOW FILTERING"
         );
     }
+
+    #[test]
+    fn test_build_update_query_with_null_condition() {
+        let query = QueryBuilder::new("trading_post.mail")
+            .query_type(QueryType::Update)
+            .columns(&["claimed_at"])
+            .filter_by(Filter::new("id", Operator::Eq, Some(5.into())))
+            .only_if_null("claimed_at")
+            .build_update_query();
+
+        assert_eq!(
+            query,
+            "UPDATE trading_post.mail SET claimed_at = ? WHERE id = ? IF claimed_at = null"
+        );
+    }
+
+    #[test]
+    fn test_build_update_query_with_conditions() {
+        let query = QueryBuilder::new("trading_post.trade")
+            .query_type(QueryType::Update)
+            .columns(&["bid_price", "bought_by"])
+            .filter_by(Filter::new("id", Operator::Eq, Some(5.into())))
+            .only_if(Filter::new("bid_price", Operator::Eq, Some(10.into())))
+            .only_if_null("expired_at")
+            .only_if(Filter::new("bought_by", Operator::Lte, Some(5.into())))
+            .build_update_query();
+
+        assert_eq!(
+            query,
+            "UPDATE trading_post.trade SET bid_price = ?, bought_by = ? WHERE id = ? IF bid_price = ? AND expired_at = null AND bought_by <= ?"
+        );
+    }
+
+    #[test]
+    fn test_build_insert_query_if_not_exists() {
+        let query = QueryBuilder::new("trading_post.lease")
+            .query_type(QueryType::Insert)
+            .columns(&["name", "holder"])
+            .if_not_exists()
+            .build_insert_query();
+
+        assert_eq!(
+            query,
+            "INSERT INTO trading_post.lease (name, holder) VALUES (?, ?) IF NOT EXISTS"
+        );
+    }
+
+    #[test]
+    fn test_build_delete_query_with_filters() {
+        let query = QueryBuilder::new("trading_post.watchlist")
+            .query_type(QueryType::Delete)
+            .filter_by(Filter::new("trade_id", Operator::Eq, Some(5.into())))
+            .filter_by(Filter::new("player_id", Operator::Eq, Some(5.into())))
+            .build_delete_query();
+
+        assert_eq!(
+            query,
+            "DELETE FROM trading_post.watchlist WHERE player_id = ? AND trade_id = ?"
+        );
+    }
+
+    #[test]
+    fn test_get_value_names_for_update_query() {
+        let value_names = QueryBuilder::new("trading_post.trade")
+            .query_type(QueryType::Update)
+            .columns(&["status", "is_deleted"])
+            .filter_by(Filter::new("id", Operator::Eq, Some(5.into())))
+            .filter_by(Filter::new("item_id", Operator::Eq, Some(5.into())))
+            .get_value_names();
+
+        assert_eq!(value_names, vec!["status", "is_deleted", "item_id", "id"]);
+    }
+
+    #[test]
+    fn test_get_value_names_for_conditional_update_query() {
+        let value_names = QueryBuilder::new("trading_post.trade")
+            .query_type(QueryType::Update)
+            .columns(&["bid_price"])
+            .filter_by(Filter::new("id", Operator::Eq, Some(5.into())))
+            .only_if(Filter::new("bid_price", Operator::Eq, Some(10.into())))
+            .only_if_null("expired_at")
+            .get_value_names();
+
+        assert_eq!(value_names, vec!["bid_price", "id", "if_bid_price"]);
+    }
+
+    #[test]
+    fn test_get_value_names_skips_inlined_filters() {
+        let value_names = QueryBuilder::new("trading_post.trade")
+            .columns(&["id", "item_id", "item_name"])
+            .filter_by(Filter::new(
+                "item_name",
+                Operator::LikeContains(String::from("%sword")),
+                None,
+            ))
+            .filter_by(Filter::new("id", Operator::Eq, Some(5.into())))
+            .get_value_names();
+
+        assert_eq!(value_names, vec!["id"]);
+    }
 }
@@ -0,0 +1,72 @@
+use tonic::Request;
+
+use crate::core::error::{Error, Result};
+
+// The request metadata key with the realm the request is made in. REST
+// clients send it as the HTTP header with the same name.
+pub const REALM_METADATA_KEY: &str = "x-realm-id";
+// The realm used when the request doesn't specify any. Trades created
+// before realms were introduced belong to it as well.
+pub const DEFAULT_REALM: &str = "default";
+const MAX_REALM_LENGTH: usize = 64;
+
+// Returns the realm of the request, or the default one when it's missing.
+pub fn get_realm<T>(request: &Request<T>) -> Result<String> {
+    match request.metadata().get(REALM_METADATA_KEY) {
+        Some(value) => parse_realm(value.to_str().unwrap_or_default()),
+        None => Ok(DEFAULT_REALM.to_string()),
+    }
+}
+
+// Realms are used as a part of partition keys, so only short identifiers
+// of letters, digits, dashes and underscores are accepted.
+pub fn parse_realm(value: &str) -> Result<String> {
+    let realm = value.trim().to_lowercase();
+    let is_valid = !realm.is_empty()
+        && realm.len() <= MAX_REALM_LENGTH
+        && realm
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    match is_valid {
+        true => Ok(realm),
+        false => Err(Error::ValidationError {
+            field: REALM_METADATA_KEY.to_string(),
+            message: format!(
+                "The realm must contain up to {0} letters, digits, dashes or underscores.",
+                MAX_REALM_LENGTH
+            ),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Request;
+
+    use crate::core::realm::{get_realm, parse_realm, DEFAULT_REALM, REALM_METADATA_KEY};
+
+    #[test]
+    fn test_get_realm_from_metadata() {
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert(REALM_METADATA_KEY, "EU-Realm_1".parse().unwrap());
+
+        assert_eq!(get_realm(&request).unwrap(), "eu-realm_1");
+    }
+
+    #[test]
+    fn test_get_realm_falls_back_to_default() {
+        assert_eq!(get_realm(&Request::new(())).unwrap(), DEFAULT_REALM);
+    }
+
+    #[test]
+    fn test_parse_realm_rejects_invalid_values() {
+        let too_long = "a".repeat(65);
+
+        for value in ["", "  ", "eu realm", "eu.realm", "realm'--", &too_long] {
+            assert!(parse_realm(value).is_err(), "{0} must be rejected", value);
+        }
+    }
+}
@@ -3,16 +3,33 @@ mod cli;
 mod core;
 mod models;
 mod multiplex_service;
+mod services;
+
+use std::sync::Arc;
+use std::time::Duration;
 
-use axum::{routing::get, Router};
 use log::info;
 use structopt::StructOpt;
+use tonic_web::GrpcWebLayer;
+use tower::ServiceBuilder;
 
+use crate::api::admin::api::AdminServiceImpl;
 use crate::api::auction::api::AuctionServiceImpl;
-use crate::api::k8s::healthcheck;
+use crate::api::auction::policies::create_auction_policies;
+use crate::api::rest::api::{create_admin_router, create_router, RestState};
 use crate::cli::CliOptions;
 use crate::core::orm::session::create_cassandra_session;
-use crate::multiplex_service::MultiplexService;
+use crate::multiplex_service::{create_cors_layer, MultiplexService};
+use crate::services::barter::BarterOffers;
+use crate::services::expiry::ExpiryWatcher;
+use crate::services::inventory::create_inventory_hook;
+use crate::services::outbox::create_outbox_relay;
+use crate::services::realm_backfill::RealmBackfill;
+use crate::services::realm_visibility::RealmVisibility;
+use crate::services::scheduler::ListingScheduler;
+use crate::services::trade_events::TradeEventBus;
+use crate::services::wash_trading::create_wash_trading_detector;
+use crate::services::webhooks::create_game_server_webhooks;
 
 mod proto {
     tonic::include_proto!("auction");
@@ -26,32 +43,120 @@ async fn main() -> std::io::Result<()> {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
     let cassandra_session = create_cassandra_session(&opts).await;
+    if opts.backfill_default_realm {
+        let copied = RealmBackfill::new(cassandra_session)
+            .run()
+            .await
+            .expect("backfill the default realm");
+        info!("Backfill finished, {0} rows copied", copied);
+        return Ok(());
+    }
 
-    // build the rest service
-    let rest = Router::new().route("/health", get(healthcheck));
+    let auction_policies = create_auction_policies(&opts);
+    let trade_events = TradeEventBus::new(opts.trade_events_capacity);
+    let realm_visibility = RealmVisibility::new(cassandra_session.clone());
+    let inventory = create_inventory_hook(&opts);
 
-    // build the grpc service
-    let reflection_service = tonic_reflection::server::Builder::configure()
-        .register_encoded_file_descriptor_set(proto::AUCTION_DESCRIPTOR_SET)
-        .build()
-        .unwrap();
+    // run the background jobs
+    if opts.wash_trading_scan_interval > 0 {
+        let detector = create_wash_trading_detector(&opts, cassandra_session.clone());
+        let interval = Duration::from_secs(opts.wash_trading_scan_interval);
+        tokio::spawn(detector.run(interval));
+    }
+    if opts.expiry_scan_interval > 0 {
+        let watcher = ExpiryWatcher::new(
+            cassandra_session.clone(),
+            trade_events.clone(),
+            inventory.clone(),
+        );
+        let interval = Duration::from_secs(opts.expiry_scan_interval);
+        tokio::spawn(watcher.run(interval));
+    }
+    if opts.schedule_scan_interval > 0 {
+        let scheduler = ListingScheduler::new(cassandra_session.clone(), trade_events.clone());
+        let interval = Duration::from_secs(opts.schedule_scan_interval);
+        tokio::spawn(scheduler.run(interval));
+    }
+    if opts.inventory_retry_interval > 0 {
+        let barter_offers = BarterOffers::new(cassandra_session.clone(), inventory.clone());
+        let interval = Duration::from_secs(opts.inventory_retry_interval);
+        tokio::spawn(barter_offers.run(interval));
+    }
+    let relay = create_outbox_relay(&opts, cassandra_session.clone());
+    let interval = Duration::from_secs(opts.outbox_relay_interval);
+    tokio::spawn(relay.run(interval));
+    if let Some(webhooks) = create_game_server_webhooks(&opts, cassandra_session.clone()) {
+        let interval = Duration::from_secs(opts.webhook_retry_interval);
+        tokio::spawn(webhooks.run(interval));
+    }
+
+    // build the services shared by the rest and grpc apis
+    let auction_service = Arc::new(AuctionServiceImpl::new(
+        cassandra_session.clone(),
+        trade_events.clone(),
+        auction_policies,
+        realm_visibility.clone(),
+        inventory,
+    ));
+    let admin_service = Arc::new(AdminServiceImpl::new(
+        cassandra_session,
+        trade_events.clone(),
+        realm_visibility,
+    ));
+
+    // build the rest services
+    let rest_state = RestState::new(auction_service.clone(), admin_service.clone(), trade_events);
+    let rest = create_router(rest_state.clone());
+    let admin_rest = create_admin_router(rest_state);
+
+    // build the grpc services
     let grpc = tonic::transport::Server::builder()
-        .add_service(reflection_service)
-        .add_service(proto::auction_server::AuctionServer::new(
-            AuctionServiceImpl::new(cassandra_session),
+        .accept_http1(true)
+        .layer(GrpcWebLayer::new())
+        .add_service(create_reflection_service())
+        .add_service(proto::auction_server::AuctionServer::from_arc(
+            auction_service,
+        ))
+        .into_service();
+    let admin_grpc = tonic::transport::Server::builder()
+        .accept_http1(true)
+        .add_service(create_reflection_service())
+        .add_service(proto::auction_admin_server::AuctionAdminServer::from_arc(
+            admin_service,
         ))
         .into_service();
 
-    // combine them into one service
-    let service = MultiplexService::new(rest, grpc);
+    // combine them into one service per listener, the admin one is not
+    // meant for browsers, so it doesn't allow cross-origin requests
+    let service = ServiceBuilder::new()
+        .layer(create_cors_layer(&opts))
+        .service(MultiplexService::new(rest, grpc));
+    let admin_service = MultiplexService::new(admin_rest, admin_grpc);
 
     info!("Listening {0}:{1}...", opts.host, opts.port);
     let addr = format!("{}:{}", opts.host, opts.port);
     let socket = &addr.parse().unwrap();
-    hyper::Server::bind(socket)
-        .serve(tower::make::Shared::new(service))
-        .await
-        .unwrap();
+    let server = hyper::Server::bind(socket).serve(tower::make::Shared::new(service));
+
+    info!(
+        "Listening {0}:{1} for admin requests...",
+        opts.admin_host, opts.admin_port
+    );
+    let admin_addr = format!("{}:{}", opts.admin_host, opts.admin_port);
+    let admin_socket = &admin_addr.parse().unwrap();
+    let admin_server =
+        hyper::Server::bind(admin_socket).serve(tower::make::Shared::new(admin_service));
+
+    tokio::try_join!(server, admin_server).unwrap();
 
     Ok(())
 }
+
+fn create_reflection_service(
+) -> tonic_reflection::server::ServerReflectionServer<impl tonic_reflection::server::ServerReflection>
+{
+    tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(proto::AUCTION_DESCRIPTOR_SET)
+        .build()
+        .unwrap()
+}
@@ -0,0 +1,146 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::core::error::Result;
+use crate::models::trade::{decode_line_items, LineItem, Trade};
+use crate::proto::{BarterOffer as BarterOfferDetail, LineItem as LineItemDetail};
+
+lazy_static! {
+    pub static ref BARTER_OFFER_TABLE: &'static str = "trading_post.barter_offer";
+    pub static ref BARTER_OFFER_ALL_COLUMNS: &'static [&'static str] = &[
+        "realm_id",
+        "trade_id",
+        "id",
+        "offered_by",
+        "offered_by_username",
+        "items",
+        "status",
+        "created_at",
+        "resolved_at",
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarterOfferStatus {
+    // The offered items are reserved until the seller answers.
+    Pending,
+    Accepted,
+    // Also set for the remaining offers, once the trade was closed.
+    Rejected,
+}
+
+impl BarterOfferStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BarterOfferStatus::Pending => "pending",
+            BarterOfferStatus::Accepted => "accepted",
+            BarterOfferStatus::Rejected => "rejected",
+        }
+    }
+}
+
+// The items offered in exchange for the barter trade.
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug)]
+pub struct BarterOffer {
+    realm_id: String,
+    trade_id: Uuid,
+    id: Uuid,
+    offered_by: Uuid,
+    offered_by_username: String,
+    // The JSON encoded offered items.
+    items: String,
+    status: String,
+    created_at: DateTime<Utc>,
+    // The POSIX timestamp in seconds, when the offer was accepted or rejected.
+    resolved_at: Option<i64>,
+}
+
+impl BarterOffer {
+    pub fn new(trade: &Trade, offered_by: Uuid, username: &str, items: &[LineItem]) -> Self {
+        Self {
+            realm_id: trade.realm_id().to_string(),
+            trade_id: trade.id(),
+            id: Uuid::new_v4(),
+            offered_by,
+            offered_by_username: username.to_string(),
+            items: serde_json::to_string(items).expect("serialize offered items"),
+            status: BarterOfferStatus::Pending.as_str().to_string(),
+            created_at: Utc::now(),
+            resolved_at: None,
+        }
+    }
+
+    pub fn realm_id(&self) -> &str {
+        &self.realm_id
+    }
+
+    pub fn trade_id(&self) -> Uuid {
+        self.trade_id
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn offered_by(&self) -> Uuid {
+        self.offered_by
+    }
+
+    pub fn offered_by_username(&self) -> &str {
+        &self.offered_by_username
+    }
+
+    pub fn items(&self) -> Result<Vec<LineItem>> {
+        decode_line_items(&self.items)
+    }
+
+    pub fn status(&self) -> BarterOfferStatus {
+        match self.status.as_str() {
+            "accepted" => BarterOfferStatus::Accepted,
+            "rejected" => BarterOfferStatus::Rejected,
+            _ => BarterOfferStatus::Pending,
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.status() == BarterOfferStatus::Pending
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "realm_id" => self.realm_id,
+            "trade_id" => self.trade_id,
+            "id" => self.id,
+            "offered_by" => self.offered_by,
+            "offered_by_username" => self.offered_by_username,
+            "items" => self.items,
+            "status" => self.status,
+            "created_at" => self.created_at,
+            "resolved_at" => self.resolved_at
+        )
+    }
+}
+
+impl From<&BarterOffer> for BarterOfferDetail {
+    fn from(instance: &BarterOffer) -> Self {
+        Self {
+            id: instance.id.to_string(),
+            trade_id: instance.trade_id.to_string(),
+            offered_by: instance.offered_by.to_string(),
+            offered_by_username: instance.offered_by_username.clone(),
+            items: instance
+                .items()
+                .unwrap_or_default()
+                .iter()
+                .map(LineItemDetail::from)
+                .collect(),
+            status: instance.status.clone(),
+            created_at: instance.created_at.timestamp(),
+        }
+    }
+}
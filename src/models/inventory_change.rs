@@ -0,0 +1,79 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+lazy_static! {
+    pub static ref INVENTORY_CHANGE_TABLE: &'static str = "trading_post.inventory_change";
+    pub static ref INVENTORY_CHANGE_ALL_COLUMNS: &'static [&'static str] =
+        &["id", "kind", "recipient", "created_at"];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InventoryChangeKind {
+    // The items of the accepted offer go to the seller.
+    Transfer,
+    // The items of the rejected offer go back to the player.
+    Release,
+}
+
+impl InventoryChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InventoryChangeKind::Transfer => "transfer",
+            InventoryChangeKind::Release => "release",
+        }
+    }
+}
+
+// The change of the reservation, that the inventory hook didn't confirm yet.
+// Stored when the barter offer is resolved and removed once the hook applied
+// it, so that the failed changes are retried. The id is the id of the offer
+// and its reservation.
+#[derive(Serialize, Deserialize, IntoCdrsValue, TryFromRow, Debug, Clone)]
+pub struct InventoryChange {
+    id: Uuid,
+    kind: String,
+    recipient: Option<Uuid>,
+    created_at: DateTime<Utc>,
+}
+
+impl InventoryChange {
+    pub fn transfer(id: Uuid, recipient: Uuid) -> Self {
+        Self::new(id, InventoryChangeKind::Transfer, Some(recipient))
+    }
+
+    pub fn release(id: Uuid) -> Self {
+        Self::new(id, InventoryChangeKind::Release, None)
+    }
+
+    fn new(id: Uuid, kind: InventoryChangeKind, recipient: Option<Uuid>) -> Self {
+        Self {
+            id,
+            kind: kind.as_str().to_string(),
+            recipient,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    // Returns the recipient of the transferred items, none for the release.
+    pub fn recipient(&self) -> Option<Uuid> {
+        self.recipient
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "id" => self.id,
+            "kind" => self.kind,
+            "recipient" => self.recipient,
+            "created_at" => self.created_at
+        )
+    }
+}
@@ -0,0 +1,8 @@
+use lazy_static::lazy_static;
+
+lazy_static! {
+    // The replica allowed to run the background job. The expiration time
+    // is stored as the POSIX timestamp in seconds.
+    pub static ref LEASE_TABLE: &'static str = "trading_post.lease";
+    pub static ref LEASE_ALL_COLUMNS: &'static [&'static str] = &["name", "holder", "expires_at"];
+}
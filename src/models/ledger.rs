@@ -0,0 +1,91 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::core::money::Money;
+
+lazy_static! {
+    pub static ref LEDGER_TABLE: &'static str = "trading_post.ledger";
+    pub static ref LEDGER_ALL_COLUMNS: &'static [&'static str] = &[
+        "player_id",
+        "id",
+        "trade_id",
+        "amount",
+        "reason",
+        "created_at",
+        "currency",
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LedgerReason {
+    CancellationPenalty,
+    // The deposit charged by the auction house for listing the trade.
+    ListingDeposit,
+    // The currency attached to the claimed mail.
+    MailClaim,
+}
+
+impl LedgerReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LedgerReason::CancellationPenalty => "cancellation_penalty",
+            LedgerReason::ListingDeposit => "listing_deposit",
+            LedgerReason::MailClaim => "mail_claim",
+        }
+    }
+}
+
+// A single currency movement for the player. Positive amounts are credited
+// to the player, negative amounts are debited from the player.
+#[derive(Serialize, Deserialize, IntoCdrsValue, TryFromRow, Debug, Clone)]
+pub struct LedgerEntry {
+    player_id: Uuid,
+    id: Uuid,
+    trade_id: Uuid,
+    amount: i64,
+    reason: String,
+    created_at: DateTime<Utc>,
+    currency: Option<String>,
+}
+
+impl LedgerEntry {
+    pub fn new(
+        player_id: Uuid,
+        trade_id: Uuid,
+        amount: Money,
+        currency: &str,
+        reason: LedgerReason,
+    ) -> Self {
+        Self {
+            player_id,
+            id: Uuid::new_v4(),
+            trade_id,
+            amount: amount.into(),
+            reason: reason.as_str().to_string(),
+            created_at: Utc::now(),
+            currency: Some(currency.to_string()),
+        }
+    }
+
+    pub fn with_id(mut self, id: Uuid) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "player_id" => self.player_id,
+            "id" => self.id,
+            "trade_id" => self.trade_id,
+            "amount" => self.amount,
+            "reason" => self.reason,
+            "created_at" => self.created_at,
+            "currency" => self.currency
+        )
+    }
+}
@@ -0,0 +1,133 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+// The tables written before realms were introduced. They are only read
+// to copy their rows into the default realm.
+lazy_static! {
+    pub static ref LEGACY_TRADE_TABLE: &'static str = "trading_post.trade";
+    pub static ref LEGACY_TRADE_ALL_COLUMNS: &'static [&'static str] = &[
+        "id",
+        "item_id",
+        "item_name",
+        "bid_price",
+        "buyout_price",
+        "created_by",
+        "created_by_username",
+        "created_at",
+        "bought_by",
+        "bought_by_username",
+        "expired_at",
+        "is_deleted",
+        "item_category",
+        "status",
+        "quantity",
+        "currency",
+    ];
+    pub static ref LEGACY_SALE_HISTORY_TABLE: &'static str = "trading_post.sale_history";
+    pub static ref LEGACY_SALE_HISTORY_ALL_COLUMNS: &'static [&'static str] =
+        &["item_id", "bucket", "sold_at", "trade_id", "price", "quantity"];
+    pub static ref LEGACY_MARKET_SUMMARY_TABLE: &'static str = "trading_post.market_summary";
+    pub static ref LEGACY_MARKET_SUMMARY_ALL_COLUMNS: &'static [&'static str] = &[
+        "item_id",
+        "min_unit_bid_price",
+        "min_unit_buyout_price",
+        "active_listings",
+        "updated_at",
+    ];
+}
+
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug)]
+pub struct LegacyTrade {
+    id: Uuid,
+    item_id: Uuid,
+    item_name: String,
+    bid_price: i64,
+    buyout_price: i64,
+    created_by: Uuid,
+    created_by_username: String,
+    created_at: DateTime<Utc>,
+    bought_by: Uuid,
+    bought_by_username: String,
+    expired_at: DateTime<Utc>,
+    is_deleted: bool,
+    item_category: Option<String>,
+    status: Option<String>,
+    quantity: Option<i32>,
+    currency: Option<String>,
+}
+
+impl LegacyTrade {
+    // Returns the values of the same trade in the realm table.
+    pub fn into_query_values(self, realm_id: &str) -> QueryValues {
+        query_values!(
+            "realm_id" => realm_id.to_string(),
+            "id" => self.id,
+            "item_id" => self.item_id,
+            "item_name" => self.item_name,
+            "bid_price" => self.bid_price,
+            "buyout_price" => self.buyout_price,
+            "created_by" => self.created_by,
+            "created_by_username" => self.created_by_username,
+            "created_at" => self.created_at,
+            "bought_by" => self.bought_by,
+            "bought_by_username" => self.bought_by_username,
+            "expired_at" => self.expired_at,
+            "is_deleted" => self.is_deleted,
+            "item_category" => self.item_category,
+            "status" => self.status,
+            "quantity" => self.quantity,
+            "currency" => self.currency
+        )
+    }
+}
+
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug)]
+pub struct LegacySaleHistoryEntry {
+    item_id: Uuid,
+    bucket: DateTime<Utc>,
+    sold_at: DateTime<Utc>,
+    trade_id: Uuid,
+    price: i64,
+    quantity: Option<i32>,
+}
+
+impl LegacySaleHistoryEntry {
+    pub fn into_query_values(self, realm_id: &str) -> QueryValues {
+        query_values!(
+            "realm_id" => realm_id.to_string(),
+            "item_id" => self.item_id,
+            "bucket" => self.bucket,
+            "sold_at" => self.sold_at,
+            "trade_id" => self.trade_id,
+            "price" => self.price,
+            "quantity" => self.quantity
+        )
+    }
+}
+
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug)]
+pub struct LegacyMarketSummary {
+    item_id: Uuid,
+    min_unit_bid_price: Option<i64>,
+    min_unit_buyout_price: Option<i64>,
+    active_listings: i32,
+    updated_at: DateTime<Utc>,
+}
+
+impl LegacyMarketSummary {
+    pub fn into_query_values(self, realm_id: &str) -> QueryValues {
+        query_values!(
+            "realm_id" => realm_id.to_string(),
+            "item_id" => self.item_id,
+            "min_unit_bid_price" => self.min_unit_bid_price,
+            "min_unit_buyout_price" => self.min_unit_buyout_price,
+            "active_listings" => self.active_listings,
+            "updated_at" => self.updated_at
+        )
+    }
+}
@@ -0,0 +1,213 @@
+use cdrs_tokio::frame::TryFromRow;
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio::types::rows::Row;
+use cdrs_tokio::types::IntoRustByName;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::core::money::Money;
+use crate::models::trade::{LineItem, Trade, DEFAULT_CURRENCY};
+use crate::proto::Mail as MailDetail;
+
+lazy_static! {
+    pub static ref MAIL_TABLE: &'static str = "trading_post.mail";
+    pub static ref MAIL_ALL_COLUMNS: &'static [&'static str] = &[
+        "player_id",
+        "id",
+        "created_at",
+        "kind",
+        "trade_id",
+        "subject",
+        "currency",
+        "item_id",
+        "item_name",
+        "quantity",
+        "claimed_at",
+        "currency_code",
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MailKind {
+    // The currency paid by the buyer, sent to the seller.
+    SaleProceeds,
+    // The item sent to the buyer or to the winner of the expired trade.
+    WonItem,
+    // The bid returned to the player, when someone placed a higher bid.
+    OutbidRefund,
+    // The item returned to the seller of the expired trade without bids.
+    ExpiredItem,
+    // The item returned to the seller of the cancelled trade.
+    CancelledItem,
+    // The bid returned to the top bidder of the cancelled trade.
+    CancellationRefund,
+}
+
+impl MailKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MailKind::SaleProceeds => "sale_proceeds",
+            MailKind::WonItem => "won_item",
+            MailKind::OutbidRefund => "outbid_refund",
+            MailKind::ExpiredItem => "expired_item",
+            MailKind::CancelledItem => "cancelled_item",
+            MailKind::CancellationRefund => "cancellation_refund",
+        }
+    }
+
+    fn subject(&self, item_name: &str) -> String {
+        match self {
+            MailKind::SaleProceeds => format!("Auction successful: {0}", item_name),
+            MailKind::WonItem => format!("Auction won: {0}", item_name),
+            MailKind::OutbidRefund => format!("Outbid on {0}", item_name),
+            MailKind::ExpiredItem => format!("Auction expired: {0}", item_name),
+            MailKind::CancelledItem => format!("Auction cancelled: {0}", item_name),
+            MailKind::CancellationRefund => format!("Auction cancelled: {0}", item_name),
+        }
+    }
+}
+
+// The mail with the currency or the item attached, delivered to the player
+// once it's claimed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Mail {
+    player_id: Uuid,
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    kind: String,
+    trade_id: Uuid,
+    subject: String,
+    currency: Money,
+    item_id: Option<Uuid>,
+    item_name: Option<String>,
+    quantity: i32,
+    claimed_at: Option<DateTime<Utc>>,
+    currency_code: Option<String>,
+}
+
+impl Mail {
+    fn new(player_id: Uuid, kind: MailKind, trade: &Trade) -> Self {
+        Self {
+            player_id,
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            kind: kind.as_str().to_string(),
+            trade_id: trade.id(),
+            subject: kind.subject(trade.item_name()),
+            currency: Money::ZERO,
+            item_id: None,
+            item_name: None,
+            quantity: 0,
+            claimed_at: None,
+            currency_code: None,
+        }
+    }
+
+    pub fn with_currency(player_id: Uuid, kind: MailKind, trade: &Trade, amount: Money) -> Self {
+        Self {
+            currency: amount,
+            currency_code: Some(trade.currency().to_string()),
+            ..Self::new(player_id, kind, trade)
+        }
+    }
+
+    pub fn with_item(player_id: Uuid, kind: MailKind, trade: &Trade, item: &LineItem) -> Self {
+        Self {
+            subject: kind.subject(item.item_name()),
+            item_id: Some(item.item_id()),
+            item_name: Some(item.item_name().to_string()),
+            quantity: item.quantity(),
+            ..Self::new(player_id, kind, trade)
+        }
+    }
+
+    pub fn player_id(&self) -> Uuid {
+        self.player_id
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn trade_id(&self) -> Uuid {
+        self.trade_id
+    }
+
+    pub fn currency(&self) -> Money {
+        self.currency
+    }
+
+    pub fn currency_code(&self) -> &str {
+        self.currency_code.as_deref().unwrap_or(DEFAULT_CURRENCY)
+    }
+
+    pub fn is_claimed(&self) -> bool {
+        self.claimed_at.is_some()
+    }
+
+    pub fn set_claimed_at(&mut self, claimed_at: DateTime<Utc>) {
+        self.claimed_at = Some(claimed_at);
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "player_id" => self.player_id,
+            "id" => self.id,
+            "created_at" => self.created_at,
+            "kind" => self.kind,
+            "trade_id" => self.trade_id,
+            "subject" => self.subject,
+            "currency" => self.currency,
+            "item_id" => self.item_id,
+            "item_name" => self.item_name,
+            "quantity" => self.quantity,
+            "claimed_at" => self.claimed_at,
+            "currency_code" => self.currency_code
+        )
+    }
+}
+
+// Implemented by hand, because the derive macro can't decode optional timestamps.
+impl TryFromRow for Mail {
+    fn try_from_row(row: Row) -> cdrs_tokio::Result<Self> {
+        Ok(Self {
+            player_id: row.get_r_by_name("player_id")?,
+            id: row.get_r_by_name("id")?,
+            created_at: row.get_r_by_name("created_at")?,
+            kind: row.get_r_by_name("kind")?,
+            trade_id: row.get_r_by_name("trade_id")?,
+            subject: row.get_r_by_name("subject")?,
+            currency: row.get_r_by_name("currency")?,
+            item_id: row.get_by_name("item_id")?,
+            item_name: row.get_by_name("item_name")?,
+            quantity: row.get_r_by_name("quantity")?,
+            claimed_at: row.get_by_name("claimed_at")?,
+            currency_code: row.get_by_name("currency_code")?,
+        })
+    }
+}
+
+impl From<&Mail> for MailDetail {
+    fn from(instance: &Mail) -> Self {
+        Self {
+            id: instance.id.to_string(),
+            kind: instance.kind.to_owned(),
+            trade_id: instance.trade_id.to_string(),
+            subject: instance.subject.to_owned(),
+            currency: instance.currency.into(),
+            item_id: instance.item_id.map(|item_id| item_id.to_string()),
+            item_name: instance.item_name.to_owned(),
+            quantity: instance.quantity,
+            created_at: instance.created_at.timestamp(),
+            claimed_at: instance.claimed_at.map(|claimed_at| claimed_at.timestamp()),
+            currency_code: instance.currency_code().to_string(),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::trade::Trade;
+use crate::proto::MarketPrice as MarketPriceDetail;
+
+lazy_static! {
+    pub static ref MARKET_SUMMARY_TABLE: &'static str = "trading_post.realm_market_summary";
+    pub static ref MARKET_SUMMARY_ALL_COLUMNS: &'static [&'static str] = &[
+        "realm_id",
+        "item_id",
+        "min_unit_bid_price",
+        "min_unit_buyout_price",
+        "active_listings",
+        "updated_at",
+    ];
+}
+
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug, PartialEq)]
+pub struct MarketSummary {
+    realm_id: String,
+    item_id: Uuid,
+    min_unit_bid_price: Option<i64>,
+    min_unit_buyout_price: Option<i64>,
+    active_listings: i32,
+    updated_at: DateTime<Utc>,
+}
+
+impl MarketSummary {
+    // Builds the summary from the active trades of the item in the realm.
+    pub fn new(realm_id: &str, item_id: Uuid, trades: &[Trade]) -> Self {
+        Self {
+            realm_id: realm_id.to_string(),
+            item_id,
+            min_unit_bid_price: trades
+                .iter()
+                .map(|trade| trade.unit_bid_price())
+                .min()
+                .map(i64::from),
+            min_unit_buyout_price: trades
+                .iter()
+                .filter_map(|trade| trade.unit_buyout_price())
+                .min()
+                .map(i64::from),
+            active_listings: trades.len() as i32,
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn item_id(&self) -> Uuid {
+        self.item_id
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "realm_id" => self.realm_id,
+            "item_id" => self.item_id,
+            "min_unit_bid_price" => self.min_unit_bid_price,
+            "min_unit_buyout_price" => self.min_unit_buyout_price,
+            "active_listings" => self.active_listings,
+            "updated_at" => self.updated_at
+        )
+    }
+}
+
+impl From<&MarketSummary> for MarketPriceDetail {
+    fn from(instance: &MarketSummary) -> Self {
+        Self {
+            item_id: instance.item_id.to_string(),
+            min_unit_bid_price: instance.min_unit_bid_price,
+            min_unit_buyout_price: instance.min_unit_buyout_price,
+            active_listings: instance.active_listings,
+        }
+    }
+}
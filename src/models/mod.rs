@@ -1 +1,19 @@
-pub mod trade;
\ No newline at end of file
+pub mod barter_offer;
+pub mod inventory_change;
+pub mod lease;
+pub mod ledger;
+pub mod legacy;
+pub mod mail;
+pub mod market_summary;
+pub mod notification;
+pub mod outbox;
+pub mod realm_visibility;
+pub mod sale;
+pub mod sale_history;
+pub mod saved_search;
+pub mod trade;
+pub mod trade_change;
+pub mod trade_flag;
+pub mod watchlist;
+pub mod webhook_dead_letter;
+pub mod webhook_retry;
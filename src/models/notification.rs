@@ -0,0 +1,89 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::proto::Notification as NotificationDetail;
+
+lazy_static! {
+    pub static ref NOTIFICATION_TABLE: &'static str = "trading_post.notification";
+    pub static ref NOTIFICATION_ALL_COLUMNS: &'static [&'static str] = &[
+        "player_id",
+        "created_at",
+        "id",
+        "kind",
+        "trade_id",
+        "message",
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotificationKind {
+    // A new trade matches one of the player's saved searches.
+    SavedSearchMatch,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::SavedSearchMatch => "saved_search_match",
+        }
+    }
+}
+
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug)]
+pub struct Notification {
+    player_id: Uuid,
+    created_at: DateTime<Utc>,
+    id: Uuid,
+    kind: String,
+    trade_id: Uuid,
+    message: String,
+}
+
+impl Notification {
+    pub fn new(player_id: Uuid, kind: NotificationKind, trade_id: Uuid, message: String) -> Self {
+        Self {
+            player_id,
+            created_at: Utc::now(),
+            id: Uuid::new_v4(),
+            kind: kind.as_str().to_string(),
+            trade_id,
+            message,
+        }
+    }
+
+    // Takes the id and time from the event the notification was created for,
+    // so that the redelivered event overwrites the same notification.
+    pub fn with_origin(mut self, id: Uuid, created_at: DateTime<Utc>) -> Self {
+        self.id = id;
+        self.created_at = created_at;
+        self
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "player_id" => self.player_id,
+            "created_at" => self.created_at,
+            "id" => self.id,
+            "kind" => self.kind,
+            "trade_id" => self.trade_id,
+            "message" => self.message
+        )
+    }
+}
+
+impl From<&Notification> for NotificationDetail {
+    fn from(instance: &Notification) -> Self {
+        Self {
+            id: instance.id.to_string(),
+            kind: instance.kind.to_owned(),
+            trade_id: instance.trade_id.to_string(),
+            message: instance.message.to_owned(),
+            created_at: instance.created_at.timestamp(),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::sale_history::get_bucket;
+
+lazy_static! {
+    pub static ref OUTBOX_TABLE: &'static str = "trading_post.outbox";
+    pub static ref OUTBOX_ALL_COLUMNS: &'static [&'static str] = &[
+        "bucket",
+        "created_at",
+        "id",
+        "kind",
+        "aggregate_id",
+        "payload",
+        "sequence",
+    ];
+    pub static ref OUTBOX_RELAY_TABLE: &'static str = "trading_post.outbox_relay";
+    pub static ref OUTBOX_RELAY_ALL_COLUMNS: &'static [&'static str] =
+        &["name", "sequence", "bucket"];
+}
+
+// The domain event waiting to be published to other services. Entries are
+// partitioned by day and removed once published.
+#[derive(Serialize, Deserialize, IntoCdrsValue, TryFromRow, Debug, Clone)]
+pub struct OutboxEntry {
+    bucket: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    id: Uuid,
+    kind: String,
+    aggregate_id: Uuid,
+    payload: String,
+    sequence: Option<i64>,
+}
+
+impl OutboxEntry {
+    pub fn new(kind: &str, aggregate_id: Uuid, payload: String) -> Self {
+        let created_at = Utc::now();
+
+        Self {
+            bucket: get_bucket(created_at),
+            created_at,
+            id: Uuid::new_v4(),
+            kind: kind.to_string(),
+            aggregate_id,
+            payload,
+            sequence: None,
+        }
+    }
+
+    pub fn bucket(&self) -> DateTime<Utc> {
+        self.bucket
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn aggregate_id(&self) -> Uuid {
+        self.aggregate_id
+    }
+
+    pub fn payload(&self) -> &str {
+        &self.payload
+    }
+
+    pub fn sequence(&self) -> Option<i64> {
+        self.sequence
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "bucket" => self.bucket,
+            "created_at" => self.created_at,
+            "id" => self.id,
+            "kind" => self.kind,
+            "aggregate_id" => self.aggregate_id,
+            "payload" => self.payload,
+            "sequence" => self.sequence
+        )
+    }
+}
+
+// The progress of the relay: the last assigned sequence number and
+// the oldest bucket that might have unpublished entries.
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug, Clone)]
+pub struct OutboxRelayState {
+    name: String,
+    sequence: i64,
+    bucket: DateTime<Utc>,
+}
+
+impl OutboxRelayState {
+    pub fn new(name: &str, bucket: DateTime<Utc>) -> Self {
+        Self {
+            name: name.to_string(),
+            sequence: 0,
+            bucket: get_bucket(bucket),
+        }
+    }
+
+    pub fn sequence(&self) -> i64 {
+        self.sequence
+    }
+
+    pub fn bucket(&self) -> DateTime<Utc> {
+        self.bucket
+    }
+
+    pub fn next_sequence(&mut self) -> i64 {
+        self.sequence += 1;
+        self.sequence
+    }
+
+    pub fn set_bucket(&mut self, bucket: DateTime<Utc>) {
+        self.bucket = bucket;
+    }
+
+    pub fn to_query_values(&self) -> QueryValues {
+        query_values!(
+            "name" => self.name.to_owned(),
+            "sequence" => self.sequence,
+            "bucket" => self.bucket
+        )
+    }
+}
@@ -0,0 +1,43 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+lazy_static! {
+    pub static ref REALM_VISIBILITY_TABLE: &'static str = "trading_post.realm_visibility";
+    pub static ref REALM_VISIBILITY_ALL_COLUMNS: &'static [&'static str] =
+        &["realm_id", "visible_realm_id", "created_at"];
+}
+
+// Opens the listings of the other realm to players of the realm. Links are
+// one-way, so each side of a merge or an event is opened separately.
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug)]
+pub struct RealmLink {
+    realm_id: String,
+    visible_realm_id: String,
+    created_at: DateTime<Utc>,
+}
+
+impl RealmLink {
+    pub fn new(realm_id: &str, visible_realm_id: &str) -> Self {
+        Self {
+            realm_id: realm_id.to_string(),
+            visible_realm_id: visible_realm_id.to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn visible_realm_id(&self) -> &str {
+        &self.visible_realm_id
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "realm_id" => self.realm_id,
+            "visible_realm_id" => self.visible_realm_id,
+            "created_at" => self.created_at
+        )
+    }
+}
@@ -0,0 +1,109 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::core::money::Money;
+use crate::models::sale_history::get_bucket;
+use crate::models::trade::DEFAULT_CURRENCY;
+
+lazy_static! {
+    pub static ref SALE_TABLE: &'static str = "trading_post.sale";
+    pub static ref SALE_ALL_COLUMNS: &'static [&'static str] = &[
+        "bucket", "sold_at", "trade_id", "item_id", "seller", "buyer", "price", "quantity",
+        "currency",
+    ];
+}
+
+// The completed sale between two players. Unlike the sale history, it keeps
+// private, bundle and barter sales in any currency.
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug, Clone)]
+pub struct SaleEntry {
+    bucket: DateTime<Utc>,
+    sold_at: DateTime<Utc>,
+    trade_id: Uuid,
+    item_id: Uuid,
+    seller: Uuid,
+    buyer: Uuid,
+    price: i64,
+    quantity: i32,
+    currency: String,
+}
+
+impl SaleEntry {
+    pub fn new(
+        trade_id: Uuid,
+        item_id: Uuid,
+        seller: Uuid,
+        buyer: Uuid,
+        price: Money,
+        quantity: i32,
+        sold_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            bucket: get_bucket(sold_at),
+            sold_at,
+            trade_id,
+            item_id,
+            seller,
+            buyer,
+            price: price.into(),
+            quantity,
+            currency: DEFAULT_CURRENCY.to_string(),
+        }
+    }
+
+    pub fn with_currency(mut self, currency: &str) -> Self {
+        self.currency = currency.to_string();
+        self
+    }
+
+    pub fn sold_at(&self) -> DateTime<Utc> {
+        self.sold_at
+    }
+
+    pub fn trade_id(&self) -> Uuid {
+        self.trade_id
+    }
+
+    pub fn item_id(&self) -> Uuid {
+        self.item_id
+    }
+
+    pub fn seller(&self) -> Uuid {
+        self.seller
+    }
+
+    pub fn buyer(&self) -> Uuid {
+        self.buyer
+    }
+
+    pub fn price(&self) -> Money {
+        Money::new(self.price)
+    }
+
+    pub fn quantity(&self) -> i32 {
+        self.quantity
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "bucket" => self.bucket,
+            "sold_at" => self.sold_at,
+            "trade_id" => self.trade_id,
+            "item_id" => self.item_id,
+            "seller" => self.seller,
+            "buyer" => self.buyer,
+            "price" => self.price,
+            "quantity" => self.quantity,
+            "currency" => self.currency
+        )
+    }
+}
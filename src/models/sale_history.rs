@@ -0,0 +1,82 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::core::money::Money;
+
+lazy_static! {
+    pub static ref SALE_HISTORY_TABLE: &'static str = "trading_post.realm_sale_history";
+    pub static ref SALE_HISTORY_ALL_COLUMNS: &'static [&'static str] =
+        &["realm_id", "item_id", "bucket", "sold_at", "trade_id", "price", "quantity"];
+}
+
+// Sales are partitioned by realm, item and day, so that the history for a period
+// is read from a few partitions at most.
+pub fn get_bucket(moment: DateTime<Utc>) -> DateTime<Utc> {
+    moment
+        .duration_trunc(TimeDelta::days(1))
+        .expect("truncate to the day")
+}
+
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug, Clone)]
+pub struct SaleHistoryEntry {
+    realm_id: String,
+    item_id: Uuid,
+    bucket: DateTime<Utc>,
+    sold_at: DateTime<Utc>,
+    trade_id: Uuid,
+    price: i64,
+    quantity: Option<i32>,
+}
+
+impl SaleHistoryEntry {
+    pub fn new(
+        realm_id: &str,
+        item_id: Uuid,
+        trade_id: Uuid,
+        price: Money,
+        quantity: i32,
+        sold_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            realm_id: realm_id.to_string(),
+            item_id,
+            bucket: get_bucket(sold_at),
+            sold_at,
+            trade_id,
+            price: price.into(),
+            quantity: Some(quantity),
+        }
+    }
+
+    pub fn sold_at(&self) -> DateTime<Utc> {
+        self.sold_at
+    }
+
+    pub fn quantity(&self) -> i32 {
+        self.quantity.unwrap_or(1)
+    }
+
+    pub fn unit_price(&self) -> Money {
+        // Sales are recorded with a positive quantity, so the division can't fail
+        Money::new(self.price)
+            .checked_div(i64::from(self.quantity().max(1)))
+            .unwrap_or_default()
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "realm_id" => self.realm_id,
+            "item_id" => self.item_id,
+            "bucket" => self.bucket,
+            "sold_at" => self.sold_at,
+            "trade_id" => self.trade_id,
+            "price" => self.price,
+            "quantity" => self.quantity
+        )
+    }
+}
@@ -0,0 +1,230 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::core::money::Money;
+use crate::core::realm::DEFAULT_REALM;
+use crate::models::trade::{get_currency, Trade};
+use crate::proto::{FilterParams, SavedSearch as SavedSearchDetail};
+
+lazy_static! {
+    pub static ref SAVED_SEARCH_TABLE: &'static str = "trading_post.saved_search";
+    pub static ref SAVED_SEARCH_ALL_COLUMNS: &'static [&'static str] = &[
+        "player_id",
+        "id",
+        "name",
+        "item_name",
+        "min_price",
+        "max_price",
+        "min_buyout_price",
+        "max_buyout_price",
+        "created_at",
+        "currency",
+        "realm_id",
+    ];
+}
+
+// The filter parameters saved by the player to be alerted about new
+// matching trades.
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug, Clone)]
+pub struct SavedSearch {
+    player_id: Uuid,
+    id: Uuid,
+    name: String,
+    item_name: Option<String>,
+    min_price: Option<i64>,
+    max_price: Option<i64>,
+    min_buyout_price: Option<i64>,
+    max_buyout_price: Option<i64>,
+    created_at: DateTime<Utc>,
+    currency: Option<String>,
+    realm_id: Option<String>,
+}
+
+impl SavedSearch {
+    pub fn new(realm_id: &str, player_id: Uuid, name: &str, params: &FilterParams) -> Self {
+        Self {
+            player_id,
+            id: Uuid::new_v4(),
+            name: String::new(),
+            item_name: None,
+            min_price: None,
+            max_price: None,
+            min_buyout_price: None,
+            max_buyout_price: None,
+            created_at: Utc::now(),
+            currency: None,
+            realm_id: Some(realm_id.to_string()),
+        }
+        .with_params(name, params)
+    }
+
+    pub fn with_params(mut self, name: &str, params: &FilterParams) -> Self {
+        self.name = name.to_string();
+        self.item_name = params.item_name.to_owned();
+        self.min_price = params.min_price;
+        self.max_price = params.max_price;
+        self.min_buyout_price = params.min_buyout_price;
+        self.max_buyout_price = params.max_buyout_price;
+        self.currency = params.currency.as_deref().map(get_currency);
+        self
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn player_id(&self) -> Uuid {
+        self.player_id
+    }
+
+    pub fn realm_id(&self) -> &str {
+        // Searches created before realms were introduced
+        self.realm_id.as_deref().unwrap_or(DEFAULT_REALM)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Checks the trade against the saved filters, the same way as
+    // it's done by the ListTrades filters.
+    pub fn matches(&self, trade: &Trade) -> bool {
+        let item_name_matches = self.item_name.as_ref().is_none_or(|item_name| {
+            trade
+                .item_name()
+                .to_lowercase()
+                .contains(&item_name.to_lowercase())
+        });
+        let in_range = |value: Money, min: Option<i64>, max: Option<i64>| {
+            min.is_none_or(|min| value >= Money::new(min))
+                && max.is_none_or(|max| value <= Money::new(max))
+        };
+
+        let currency_matches = self
+            .currency
+            .as_ref()
+            .is_none_or(|currency| trade.currency() == currency);
+
+        self.realm_id() == trade.realm_id()
+            && item_name_matches
+            && currency_matches
+            && in_range(trade.bid_price(), self.min_price, self.max_price)
+            && in_range(
+                trade.buyout_price(),
+                self.min_buyout_price,
+                self.max_buyout_price,
+            )
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "player_id" => self.player_id,
+            "id" => self.id,
+            "name" => self.name,
+            "item_name" => self.item_name,
+            "min_price" => self.min_price,
+            "max_price" => self.max_price,
+            "min_buyout_price" => self.min_buyout_price,
+            "max_buyout_price" => self.max_buyout_price,
+            "created_at" => self.created_at,
+            "currency" => self.currency,
+            "realm_id" => self.realm_id
+        )
+    }
+}
+
+impl From<&SavedSearch> for SavedSearchDetail {
+    fn from(instance: &SavedSearch) -> Self {
+        Self {
+            id: instance.id.to_string(),
+            name: instance.name.to_owned(),
+            filter_params: Some(FilterParams {
+                item_name: instance.item_name.to_owned(),
+                min_price: instance.min_price,
+                max_price: instance.max_price,
+                min_buyout_price: instance.min_buyout_price,
+                max_buyout_price: instance.max_buyout_price,
+                currency: instance.currency.to_owned(),
+            }),
+            created_at: instance.created_at.timestamp(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::core::realm::DEFAULT_REALM;
+    use crate::models::saved_search::SavedSearch;
+    use crate::models::trade::Trade;
+    use crate::proto::FilterParams;
+
+    fn create_trade(item_name: &str, bid_price: i64, buyout_price: i64) -> Trade {
+        Trade::builder()
+            .item_name(item_name)
+            .prices(bid_price, buyout_price)
+            .build()
+    }
+
+    fn create_saved_search(params: FilterParams) -> SavedSearch {
+        SavedSearch::new(DEFAULT_REALM, Uuid::new_v4(), "search", &params)
+    }
+
+    #[test]
+    fn test_matches_item_name_case_insensitive() {
+        let saved_search = create_saved_search(FilterParams {
+            item_name: Some("sword".to_string()),
+            ..Default::default()
+        });
+
+        assert!(saved_search.matches(&create_trade("Epic Sword", 100, 200)));
+        assert!(!saved_search.matches(&create_trade("Epic Shield", 100, 200)));
+    }
+
+    #[test]
+    fn test_matches_price_ranges_inclusive() {
+        let saved_search = create_saved_search(FilterParams {
+            item_name: Some("sword".to_string()),
+            min_price: Some(100),
+            max_price: Some(200),
+            max_buyout_price: Some(500),
+            ..Default::default()
+        });
+
+        assert!(saved_search.matches(&create_trade("Sword", 100, 500)));
+        assert!(saved_search.matches(&create_trade("Sword", 200, 300)));
+        assert!(!saved_search.matches(&create_trade("Sword", 99, 300)));
+        assert!(!saved_search.matches(&create_trade("Sword", 201, 300)));
+        assert!(!saved_search.matches(&create_trade("Sword", 150, 501)));
+    }
+
+    #[test]
+    fn test_matches_currency() {
+        let saved_search = create_saved_search(FilterParams {
+            item_name: Some("sword".to_string()),
+            currency: Some("Tokens".to_string()),
+            ..Default::default()
+        });
+        let tokens_trade = Trade::builder().currency("tokens").build();
+
+        assert!(saved_search.matches(&tokens_trade));
+        assert!(!saved_search.matches(&create_trade("Sword", 100, 200)));
+    }
+
+    #[test]
+    fn test_matches_only_trades_of_same_realm() {
+        let saved_search = create_saved_search(FilterParams {
+            item_name: Some("sword".to_string()),
+            ..Default::default()
+        });
+
+        assert!(saved_search.matches(&create_trade("Sword", 100, 200)));
+        assert!(!saved_search.matches(&create_trade("Sword", 100, 200).with_realm("eu-1")));
+    }
+}
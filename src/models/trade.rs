@@ -3,16 +3,74 @@ use cdrs_tokio::query_values;
 use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
 use chrono::{DateTime, Days, Utc};
 use lazy_static::lazy_static;
-use serde::Serialize;
+use log::error;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::time::Duration;
 use uuid::Uuid;
 
-use crate::proto::{CreateTradeRequest, Trade as TradeDetail};
+use crate::core::error::{Error, Result};
+use crate::core::money::Money;
+use crate::core::realm::DEFAULT_REALM;
+use crate::proto::{CreateTradeRequest, LineItem as LineItemDetail, Trade as TradeDetail};
+
+// The currency used when the request doesn't specify any. Trades created
+// before currencies were introduced are priced in it as well.
+pub const DEFAULT_CURRENCY: &str = "gold";
+
+// The auction house used when the request doesn't specify any. Trades
+// created before houses were introduced are listed in it as well.
+pub const DEFAULT_HOUSE: &str = "default";
+
+// Returns the currency code from the request, or the default one when it's empty.
+pub fn get_currency(value: &str) -> String {
+    match value.trim() {
+        "" => DEFAULT_CURRENCY.to_string(),
+        currency => currency.to_lowercase(),
+    }
+}
+
+// Formats the price for the players. The default currency is shown in coins,
+// other currencies only know their plain amounts.
+pub fn format_price(price: Money, currency: &str) -> String {
+    match currency {
+        DEFAULT_CURRENCY => price.to_coins(),
+        _ => format!("{0} {1}", price, currency),
+    }
+}
+
+// The separator between the item names of a bundle. The names are stored
+// together, so that the bundle can be found by any of the contained items.
+const BUNDLE_ITEM_NAME_SEPARATOR: &str = ", ";
+
+fn encode_line_items(line_items: &[LineItem]) -> Option<String> {
+    match line_items.is_empty() {
+        true => None,
+        false => Some(serde_json::to_string(line_items).expect("serialize line items")),
+    }
+}
+
+// Fails on the corrupt value, so that nobody sells or gets nothing instead
+// of the stored items.
+pub fn decode_line_items(value: &str) -> Result<Vec<LineItem>> {
+    serde_json::from_str(value).map_err(|err| {
+        error!("Can't decode the line items {0}: {1}", value, err);
+        Error::CorruptData("line items".to_string())
+    })
+}
+
+// Returns the auction house from the request, or the default one when it's empty.
+pub fn get_house(value: &str) -> String {
+    match value.trim() {
+        "" => DEFAULT_HOUSE.to_string(),
+        house => house.to_lowercase(),
+    }
+}
 
 lazy_static! {
-    pub static ref TRADE_TABLE: &'static str = "trading_post.trade";
+    pub static ref TRADE_TABLE: &'static str = "trading_post.realm_trade";
     pub static ref TRADE_ALL_COLUMNS: &'static [&'static str] = &[
+        "realm_id",
         "id",
         "item_id",
         "item_name",
@@ -25,6 +83,18 @@ lazy_static! {
         "bought_by_username",
         "expired_at",
         "is_deleted",
+        "item_category",
+        "status",
+        "quantity",
+        "currency",
+        "house",
+        "deposit",
+        "commission_percent",
+        "starts_at",
+        "line_items",
+        "wanted_items",
+        "reserved_for",
+        "pending_change",
     ];
     pub static ref EMPTY_UUID: Uuid =
         Uuid::parse_str("00000000-0000-0000-0000-000000000000").unwrap();
@@ -32,6 +102,7 @@ lazy_static! {
 
 #[derive(Serialize, IntoCdrsValue, TryFromRow, Debug)]
 pub struct Trade {
+    realm_id: String,
     id: Uuid,
     item_id: Uuid,
     item_name: String,
@@ -44,31 +115,309 @@ pub struct Trade {
     bought_by_username: String,
     expired_at: DateTime<Utc>,
     is_deleted: bool,
+    item_category: Option<String>,
+    status: Option<String>,
+    quantity: Option<i32>,
+    currency: Option<String>,
+    house: Option<String>,
+    deposit: Option<i64>,
+    commission_percent: Option<i64>,
+    // The POSIX timestamp in seconds, when the scheduled trade goes on sale.
+    starts_at: Option<i64>,
+    // The JSON encoded items of the bundle trade. Not set for trades of
+    // a single item.
+    line_items: Option<String>,
+    // The JSON encoded items the seller wants in return. Set only for
+    // barter trades.
+    wanted_items: Option<String>,
+    // The only player allowed to buy the private trade.
+    reserved_for: Option<Uuid>,
+    // The JSON encoded rows that follow the last change of the trade, until
+    // they're written. Set only while the follow-up of the change is pending.
+    pending_change: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LineItem {
+    item_id: Uuid,
+    item_name: String,
+    quantity: i32,
+}
+
+impl LineItem {
+    pub fn item_id(&self) -> Uuid {
+        self.item_id
+    }
+
+    pub fn item_name(&self) -> &str {
+        &self.item_name
+    }
+
+    pub fn quantity(&self) -> i32 {
+        self.quantity
+    }
+}
+
+impl From<LineItemDetail> for LineItem {
+    fn from(request: LineItemDetail) -> Self {
+        Self {
+            item_id: Uuid::from_str(&request.item_id).unwrap(),
+            item_name: request.item_name,
+            quantity: request.quantity.max(1),
+        }
+    }
+}
+
+impl From<&LineItem> for LineItemDetail {
+    fn from(instance: &LineItem) -> Self {
+        Self {
+            item_id: instance.item_id.to_string(),
+            item_name: instance.item_name.clone(),
+            quantity: instance.quantity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TradeStatus {
+    // Waits for the start time, hidden from other players until then.
+    Scheduled,
+    Active,
+    Sold,
+    Cancelled,
+    // Stored once the expired trade was closed. Until then, derived from
+    // the expiration time of an active trade.
+    Expired,
+}
+
+impl TradeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradeStatus::Scheduled => "scheduled",
+            TradeStatus::Active => "active",
+            TradeStatus::Sold => "sold",
+            TradeStatus::Cancelled => "cancelled",
+            TradeStatus::Expired => "expired",
+        }
+    }
 }
 
 impl Trade {
+    pub fn with_realm(mut self, realm_id: &str) -> Self {
+        self.realm_id = realm_id.to_string();
+        self
+    }
+
+    pub fn realm_id(&self) -> &str {
+        &self.realm_id
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
     pub fn item_id(&self) -> Uuid {
         self.item_id
     }
 
+    pub fn item_name(&self) -> &str {
+        &self.item_name
+    }
+
     pub fn created_by(&self) -> Uuid {
         self.created_by
     }
 
-    pub fn bid_price(&self) -> i64 {
-        self.bid_price
+    pub fn bid_price(&self) -> Money {
+        Money::new(self.bid_price)
+    }
+
+    pub fn buyout_price(&self) -> Money {
+        Money::new(self.buyout_price)
+    }
+
+    pub fn quantity(&self) -> i32 {
+        // Trades created before quantities were introduced hold a single item
+        self.quantity.unwrap_or(1)
+    }
+
+    pub fn is_bundle(&self) -> bool {
+        self.line_items.is_some()
+    }
+
+    // Returns the items delivered to the buyer. A trade of a single item
+    // has exactly one line item.
+    pub fn line_items(&self) -> Result<Vec<LineItem>> {
+        match &self.line_items {
+            Some(line_items) => decode_line_items(line_items),
+            None => Ok(vec![LineItem {
+                item_id: self.item_id,
+                item_name: self.item_name.clone(),
+                quantity: self.quantity(),
+            }]),
+        }
+    }
+
+    // Barter trades are sold only for the items accepted by the seller.
+    pub fn is_barter(&self) -> bool {
+        self.wanted_items.is_some()
+    }
+
+    pub fn wanted_items(&self) -> Result<Vec<LineItem>> {
+        match &self.wanted_items {
+            Some(wanted_items) => decode_line_items(wanted_items),
+            None => Ok(vec![]),
+        }
+    }
+
+    // The buyout price for a single item, or the bid price when buyout wasn't set.
+    pub fn unit_price(&self) -> Money {
+        self.unit_buyout_price()
+            .unwrap_or_else(|| self.unit_bid_price())
     }
 
-    pub fn buyout_price(&self) -> i64 {
-        self.buyout_price
+    pub fn unit_bid_price(&self) -> Money {
+        self.get_unit_price(self.bid_price())
+    }
+
+    pub fn unit_buyout_price(&self) -> Option<Money> {
+        match self.buyout_price().is_positive() {
+            true => Some(self.get_unit_price(self.buyout_price())),
+            false => None,
+        }
+    }
+
+    fn get_unit_price(&self, price: Money) -> Money {
+        // The quantity is always positive, so the division can't fail
+        price
+            .checked_div(i64::from(self.quantity().max(1)))
+            .unwrap_or_default()
     }
 
     pub fn bought_by(&self) -> Uuid {
         self.bought_by
     }
 
+    pub fn expired_at(&self) -> DateTime<Utc> {
+        self.expired_at
+    }
+
+    // Trades created without the lifetime are stored with the expiration time
+    // before the creation time and stay on sale until they're sold.
+    pub fn expires(&self) -> bool {
+        self.expired_at > self.created_at
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires() && self.expired_at <= Utc::now()
+    }
+
+    pub fn status(&self) -> TradeStatus {
+        match self.status.as_deref() {
+            Some("scheduled") => TradeStatus::Scheduled,
+            Some("sold") => TradeStatus::Sold,
+            Some("cancelled") => TradeStatus::Cancelled,
+            Some("expired") => TradeStatus::Expired,
+            Some(_) => TradeStatus::Active,
+            // Trades created before statuses were introduced
+            None if !self.is_deleted => TradeStatus::Active,
+            None if self.bought_by != *EMPTY_UUID => TradeStatus::Sold,
+            None => TradeStatus::Cancelled,
+        }
+    }
+
+    // Returns the status exactly as stored. Changes of the trade are applied
+    // only while it keeps the status they were checked against.
+    pub fn stored_status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    // Returns the status of the trade as seen by players, taking the
+    // expiration time into account.
+    pub fn current_status(&self) -> TradeStatus {
+        match self.status() {
+            TradeStatus::Scheduled if self.is_pending() => TradeStatus::Scheduled,
+            TradeStatus::Active | TradeStatus::Scheduled if self.is_expired() => {
+                TradeStatus::Expired
+            }
+            TradeStatus::Scheduled => TradeStatus::Active,
+            status => status,
+        }
+    }
+
+    pub fn starts_at(&self) -> Option<DateTime<Utc>> {
+        self.starts_at
+            .and_then(|starts_at| DateTime::from_timestamp(starts_at, 0))
+    }
+
+    // Checks whether the scheduled trade didn't go on sale yet. The trade
+    // becomes active as soon as the start time passes, even before the
+    // scheduler updates its status.
+    pub fn is_pending(&self) -> bool {
+        self.status() == TradeStatus::Scheduled
+            && self
+                .starts_at()
+                .is_some_and(|starts_at| starts_at > Utc::now())
+    }
+
+    pub fn reserved_for(&self) -> Option<Uuid> {
+        self.reserved_for
+    }
+
+    // Private trades are priced for a single buyer, so their prices don't
+    // tell the market price of the item.
+    pub fn is_private(&self) -> bool {
+        self.reserved_for.is_some()
+    }
+
+    pub fn pending_change(&self) -> Option<&str> {
+        self.pending_change.as_deref()
+    }
+
+    pub fn clear_pending_change(&mut self) {
+        self.pending_change = None;
+    }
+
+    // Scheduled and reserved trades are listed only for their sellers. Reserved
+    // trades are found by their buyers among the private offers.
+    pub fn is_listed_for(&self, user_id: Option<Uuid>) -> bool {
+        Some(self.created_by) == user_id || (!self.is_pending() && self.reserved_for.is_none())
+    }
+
+    pub fn item_category(&self) -> &str {
+        self.item_category.as_deref().unwrap_or_default()
+    }
+
+    pub fn currency(&self) -> &str {
+        self.currency.as_deref().unwrap_or(DEFAULT_CURRENCY)
+    }
+
+    pub fn house(&self) -> &str {
+        self.house.as_deref().unwrap_or(DEFAULT_HOUSE)
+    }
+
+    // Keeps the fees of the house at the moment of listing, so that changed
+    // settings don't affect trades that were already listed.
+    pub fn with_fees(mut self, deposit: Money, commission_percent: i64) -> Self {
+        self.deposit = Some(deposit.into());
+        self.commission_percent = Some(commission_percent);
+        self
+    }
+
+    pub fn deposit(&self) -> Money {
+        Money::new(self.deposit.unwrap_or_default())
+    }
+
+    // Returns the amount sent to the seller for the sold item: the price
+    // without the commission of the house, plus the returned deposit.
+    pub fn get_proceeds(&self, amount: Money) -> Result<Money> {
+        let commission = amount.percent(self.commission_percent.unwrap_or_default())?;
+        amount.checked_sub(commission)?.checked_add(self.deposit())
+    }
+
     pub fn into_query_values(self) -> QueryValues {
         query_values!(
+            "realm_id" => self.realm_id,
             "id" => self.id,
             "item_id" => self.item_id,
             "item_name" => self.item_name,
@@ -80,7 +429,19 @@ impl Trade {
             "bought_by" => self.bought_by,
             "bought_by_username" => self.bought_by_username,
             "expired_at" => self.expired_at,
-            "is_deleted" => self.is_deleted
+            "is_deleted" => self.is_deleted,
+            "item_category" => self.item_category,
+            "status" => self.status,
+            "quantity" => self.quantity,
+            "currency" => self.currency,
+            "house" => self.house,
+            "deposit" => self.deposit,
+            "commission_percent" => self.commission_percent,
+            "starts_at" => self.starts_at,
+            "line_items" => self.line_items,
+            "wanted_items" => self.wanted_items,
+            "reserved_for" => self.reserved_for,
+            "pending_change" => self.pending_change
         )
     }
 }
@@ -88,16 +449,50 @@ impl Trade {
 impl From<CreateTradeRequest> for Trade {
     fn from(request: CreateTradeRequest) -> Self {
         let created_at = Utc::now();
+        // Scheduled trades expire after the given time since their start
+        let starts_at = request
+            .starts_at
+            .and_then(|starts_at| DateTime::from_timestamp(starts_at, 0))
+            .filter(|starts_at| *starts_at > created_at);
         let expired_at = match request.expire_in {
             // Set a date before the created_at date to indicate that expiration wasn't set
             0 => created_at - Days::new(1),
-            _ => created_at + Duration::from_secs(request.expire_in as u64),
+            _ => starts_at.unwrap_or(created_at) + Duration::from_secs(request.expire_in as u64),
+        };
+        let status = match starts_at {
+            Some(_) => TradeStatus::Scheduled,
+            None => TradeStatus::Active,
+        };
+        let line_items: Vec<LineItem> = request.items.into_iter().map(LineItem::from).collect();
+        let wanted_items: Vec<LineItem> = request
+            .wanted_items
+            .into_iter()
+            .map(LineItem::from)
+            .collect();
+        // The bundle is stored in the partition of its first item and sold
+        // as a single unit
+        let (item_id, item_name, quantity) = match line_items.first() {
+            Some(first) => (
+                first.item_id,
+                line_items
+                    .iter()
+                    .map(|line_item| line_item.item_name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(BUNDLE_ITEM_NAME_SEPARATOR),
+                1,
+            ),
+            None => (
+                Uuid::from_str(&request.item_id).unwrap(),
+                request.item_name,
+                request.quantity.max(1),
+            ),
         };
 
         Self {
+            realm_id: DEFAULT_REALM.to_string(),
             id: Uuid::new_v4(),
-            item_id: Uuid::from_str(&request.item_id).unwrap(),
-            item_name: request.item_name,
+            item_id,
+            item_name,
             bid_price: request.bid_price,
             buyout_price: request.buyout_price,
             created_by: Uuid::from_str(&request.created_by).unwrap(),
@@ -107,6 +502,23 @@ impl From<CreateTradeRequest> for Trade {
             bought_by_username: String::new(),
             expired_at,
             is_deleted: false,
+            item_category: match request.item_category.is_empty() {
+                true => None,
+                false => Some(request.item_category),
+            },
+            status: Some(status.as_str().to_string()),
+            quantity: Some(quantity),
+            currency: Some(get_currency(&request.currency)),
+            house: Some(get_house(&request.house)),
+            deposit: None,
+            commission_percent: None,
+            starts_at: starts_at.map(|starts_at| starts_at.timestamp()),
+            line_items: encode_line_items(&line_items),
+            wanted_items: encode_line_items(&wanted_items),
+            reserved_for: request
+                .reserved_for
+                .map(|reserved_for| Uuid::from_str(&reserved_for).unwrap()),
+            pending_change: None,
         }
     }
 }
@@ -131,6 +543,146 @@ impl From<&Trade> for TradeDetail {
             bought_by: instance.bought_by.to_string(),
             bought_by_username: instance.bought_by_username.to_string(),
             expired_at,
+            item_category: instance.item_category().to_string(),
+            quantity: instance.quantity(),
+            currency: instance.currency().to_string(),
+            realm_id: instance.realm_id.to_owned(),
+            house: instance.house().to_string(),
+            deposit: instance.deposit().into(),
+            starts_at: instance.starts_at,
+            // The corrupt items are listed as none, the sale fails on them
+            items: match instance.is_bundle() {
+                true => instance
+                    .line_items()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(LineItemDetail::from)
+                    .collect(),
+                false => vec![],
+            },
+            wanted_items: instance
+                .wanted_items()
+                .unwrap_or_default()
+                .iter()
+                .map(LineItemDetail::from)
+                .collect(),
+            reserved_for: instance
+                .reserved_for
+                .map(|reserved_for| reserved_for.to_string()),
         }
     }
 }
+
+#[cfg(test)]
+impl Trade {
+    pub fn builder() -> TradeBuilder {
+        TradeBuilder::new()
+    }
+
+    pub fn with_bid(mut self, user_id: Uuid, username: &str, amount: i64) -> Self {
+        self.bid_price = amount;
+        self.bought_by = user_id;
+        self.bought_by_username = username.to_string();
+        self
+    }
+
+    pub fn with_status(mut self, status: TradeStatus) -> Self {
+        self.status = Some(status.as_str().to_string());
+        self
+    }
+
+    pub fn with_encoded_line_items(mut self, line_items: &str) -> Self {
+        self.line_items = Some(line_items.to_string());
+        self
+    }
+
+    pub fn with_lifetime(mut self, created_at: DateTime<Utc>, expired_at: DateTime<Utc>) -> Self {
+        self.created_at = created_at;
+        self.expired_at = expired_at;
+        self
+    }
+}
+
+// Builds the trades used by tests. Starts with a sword listed by a random
+// seller for 100 with the buyout for 1000.
+#[cfg(test)]
+pub struct TradeBuilder {
+    request: CreateTradeRequest,
+}
+
+#[cfg(test)]
+impl TradeBuilder {
+    fn new() -> Self {
+        Self {
+            request: CreateTradeRequest {
+                item_id: Uuid::new_v4().to_string(),
+                item_name: "Sword".to_string(),
+                bid_price: 100,
+                buyout_price: 1000,
+                created_by: Uuid::new_v4().to_string(),
+                created_by_username: "seller".to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn item_id(mut self, item_id: Uuid) -> Self {
+        self.request.item_id = item_id.to_string();
+        self
+    }
+
+    pub fn item_name(mut self, item_name: &str) -> Self {
+        self.request.item_name = item_name.to_string();
+        self
+    }
+
+    pub fn prices(mut self, bid_price: i64, buyout_price: i64) -> Self {
+        self.request.bid_price = bid_price;
+        self.request.buyout_price = buyout_price;
+        self
+    }
+
+    pub fn quantity(mut self, quantity: i32) -> Self {
+        self.request.quantity = quantity;
+        self
+    }
+
+    pub fn currency(mut self, currency: &str) -> Self {
+        self.request.currency = currency.to_string();
+        self
+    }
+
+    pub fn item_category(mut self, item_category: &str) -> Self {
+        self.request.item_category = item_category.to_string();
+        self
+    }
+
+    pub fn house(mut self, house: &str) -> Self {
+        self.request.house = house.to_string();
+        self
+    }
+
+    pub fn created_by(mut self, player_id: Uuid) -> Self {
+        self.request.created_by = player_id.to_string();
+        self
+    }
+
+    pub fn reserved_for(mut self, player_id: Option<Uuid>) -> Self {
+        self.request.reserved_for = player_id.map(|player_id| player_id.to_string());
+        self
+    }
+
+    pub fn expire_in(mut self, seconds: i64) -> Self {
+        self.request.expire_in = seconds;
+        self
+    }
+
+    pub fn starts_at(mut self, timestamp: i64) -> Self {
+        self.request.starts_at = Some(timestamp);
+        self
+    }
+
+    pub fn build(self) -> Trade {
+        Trade::from(self.request)
+    }
+}
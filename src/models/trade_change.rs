@@ -0,0 +1,224 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use log::error;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::core::error::{Error, Result};
+use crate::models::inventory_change::InventoryChange;
+use crate::models::ledger::LedgerEntry;
+use crate::models::mail::Mail;
+use crate::models::outbox::OutboxEntry;
+use crate::models::sale_history::get_bucket;
+use crate::models::trade::Trade;
+
+lazy_static! {
+    pub static ref TRADE_CHANGE_TABLE: &'static str = "trading_post.trade_change";
+    pub static ref TRADE_CHANGE_ALL_COLUMNS: &'static [&'static str] = &[
+        "bucket",
+        "created_at",
+        "id",
+        "realm_id",
+        "item_id",
+        "trade_id",
+        "created_by",
+    ];
+}
+
+// Points to the trade, whose change may have a follow-up left to write.
+// Stored before the trade is updated and removed once the follow-up is
+// written or the update is rejected. Entries are partitioned by day.
+#[derive(Serialize, Deserialize, IntoCdrsValue, TryFromRow, Debug, Clone)]
+pub struct TradeChangeEntry {
+    bucket: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    id: Uuid,
+    realm_id: String,
+    item_id: Uuid,
+    trade_id: Uuid,
+    created_by: Uuid,
+}
+
+impl TradeChangeEntry {
+    fn new(trade: &Trade) -> Self {
+        let created_at = Utc::now();
+
+        Self {
+            bucket: get_bucket(created_at),
+            created_at,
+            id: Uuid::new_v4(),
+            realm_id: trade.realm_id().to_string(),
+            item_id: trade.item_id(),
+            trade_id: trade.id(),
+            created_by: trade.created_by(),
+        }
+    }
+
+    pub fn bucket(&self) -> DateTime<Utc> {
+        self.bucket
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn realm_id(&self) -> &str {
+        &self.realm_id
+    }
+
+    pub fn item_id(&self) -> Uuid {
+        self.item_id
+    }
+
+    pub fn trade_id(&self) -> Uuid {
+        self.trade_id
+    }
+
+    pub fn created_by(&self) -> Uuid {
+        self.created_by
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "bucket" => self.bucket,
+            "created_at" => self.created_at,
+            "id" => self.id,
+            "realm_id" => self.realm_id,
+            "item_id" => self.item_id,
+            "trade_id" => self.trade_id,
+            "created_by" => self.created_by
+        )
+    }
+}
+
+// The rows written once the change of the trade is applied. The change is
+// stored in the trade by the update itself, with the ids of all rows, so
+// that writing it again after a failure doesn't duplicate them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TradeChange {
+    entry: TradeChangeEntry,
+    outbox: Vec<OutboxEntry>,
+    mail: Vec<Mail>,
+    ledger: Vec<LedgerEntry>,
+    inventory: Vec<InventoryChange>,
+}
+
+impl TradeChange {
+    pub fn new(trade: &Trade) -> Self {
+        Self {
+            entry: TradeChangeEntry::new(trade),
+            outbox: vec![],
+            mail: vec![],
+            ledger: vec![],
+            inventory: vec![],
+        }
+    }
+
+    pub fn with_event(mut self, entry: OutboxEntry) -> Self {
+        self.outbox.push(entry);
+        self
+    }
+
+    pub fn with_mail(mut self, mail: Vec<Mail>) -> Self {
+        self.mail.extend(mail);
+        self
+    }
+
+    pub fn with_charge(mut self, entry: LedgerEntry) -> Self {
+        self.ledger.push(entry);
+        self
+    }
+
+    pub fn with_inventory_change(mut self, change: InventoryChange) -> Self {
+        self.inventory.push(change);
+        self
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.entry.id()
+    }
+
+    pub fn entry(&self) -> &TradeChangeEntry {
+        &self.entry
+    }
+
+    pub fn outbox(&self) -> &[OutboxEntry] {
+        &self.outbox
+    }
+
+    pub fn mail(&self) -> &[Mail] {
+        &self.mail
+    }
+
+    pub fn ledger(&self) -> &[LedgerEntry] {
+        &self.ledger
+    }
+
+    pub fn inventory(&self) -> &[InventoryChange] {
+        &self.inventory
+    }
+
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("serialize trade change")
+    }
+}
+
+// Fails on the corrupt value, so that the follow-up isn't written partially.
+pub fn decode_trade_change(value: &str) -> Result<TradeChange> {
+    serde_json::from_str(value).map_err(|err| {
+        error!("Can't decode the trade change {0}: {1}", value, err);
+        Error::CorruptData("trade change".to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::core::money::Money;
+    use crate::models::ledger::{LedgerEntry, LedgerReason};
+    use crate::models::mail::{Mail, MailKind};
+    use crate::models::outbox::OutboxEntry;
+    use crate::models::trade::Trade;
+    use crate::models::trade_change::{decode_trade_change, TradeChange};
+
+    #[test]
+    fn test_decoded_change_keeps_row_ids() {
+        let trade = Trade::builder().build();
+        let mail = Mail::with_currency(
+            trade.created_by(),
+            MailKind::SaleProceeds,
+            &trade,
+            Money::new(1000),
+        );
+        let change = TradeChange::new(&trade)
+            .with_event(OutboxEntry::new(
+                "trade.bought_out",
+                trade.id(),
+                "{}".to_string(),
+            ))
+            .with_mail(vec![mail])
+            .with_charge(LedgerEntry::new(
+                trade.created_by(),
+                trade.id(),
+                Money::new(-50),
+                trade.currency(),
+                LedgerReason::CancellationPenalty,
+            ));
+
+        let decoded = decode_trade_change(&change.encode()).unwrap();
+
+        assert_eq!(decoded.id(), change.id());
+        assert_eq!(decoded.outbox()[0].id(), change.outbox()[0].id());
+        assert_eq!(decoded.mail()[0].id(), change.mail()[0].id());
+        assert_eq!(decoded.mail()[0].currency(), Money::new(1000));
+        assert_eq!(decoded.encode(), change.encode());
+        assert!(decode_trade_change("{").is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::core::money::Money;
+use crate::proto::TradeFlag as TradeFlagDetail;
+
+lazy_static! {
+    pub static ref TRADE_FLAG_TABLE: &'static str = "trading_post.trade_flag";
+    pub static ref TRADE_FLAG_ALL_COLUMNS: &'static [&'static str] = &[
+        "trade_id",
+        "kind",
+        "item_id",
+        "seller",
+        "buyer",
+        "price",
+        "details",
+        "created_at",
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlagKind {
+    // The same seller and buyer trade with each other over and over.
+    RepeatedPair,
+    // The item was sold far above its median price.
+    PriceOutlier,
+    // The item was sold and then sold back between the same players.
+    RoundTrip,
+}
+
+impl FlagKind {
+    pub const ALL: [FlagKind; 3] = [
+        FlagKind::RepeatedPair,
+        FlagKind::PriceOutlier,
+        FlagKind::RoundTrip,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FlagKind::RepeatedPair => "repeated_pair",
+            FlagKind::PriceOutlier => "price_outlier",
+            FlagKind::RoundTrip => "round_trip",
+        }
+    }
+}
+
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug, Clone, PartialEq)]
+pub struct TradeFlag {
+    trade_id: Uuid,
+    kind: String,
+    item_id: Uuid,
+    seller: Uuid,
+    buyer: Uuid,
+    price: i64,
+    details: String,
+    created_at: DateTime<Utc>,
+}
+
+impl TradeFlag {
+    pub fn new(
+        trade_id: Uuid,
+        kind: FlagKind,
+        item_id: Uuid,
+        seller: Uuid,
+        buyer: Uuid,
+        price: Money,
+        details: String,
+    ) -> Self {
+        Self {
+            trade_id,
+            kind: kind.as_str().to_string(),
+            item_id,
+            seller,
+            buyer,
+            price: price.into(),
+            details,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn trade_id(&self) -> Uuid {
+        self.trade_id
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "trade_id" => self.trade_id,
+            "kind" => self.kind,
+            "item_id" => self.item_id,
+            "seller" => self.seller,
+            "buyer" => self.buyer,
+            "price" => self.price,
+            "details" => self.details,
+            "created_at" => self.created_at
+        )
+    }
+}
+
+impl From<&TradeFlag> for TradeFlagDetail {
+    fn from(instance: &TradeFlag) -> Self {
+        Self {
+            trade_id: instance.trade_id.to_string(),
+            kind: instance.kind.clone(),
+            item_id: instance.item_id.to_string(),
+            seller: instance.seller.to_string(),
+            buyer: instance.buyer.to_string(),
+            price: instance.price,
+            details: instance.details.clone(),
+            created_at: instance.created_at.timestamp(),
+        }
+    }
+}
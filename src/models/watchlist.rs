@@ -0,0 +1,130 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::core::realm::DEFAULT_REALM;
+use crate::models::trade::Trade;
+use crate::proto::{
+    Trade as TradeDetail, WatchedItem as WatchedItemDetail, WatchedTrade as WatchedTradeDetail,
+};
+
+lazy_static! {
+    pub static ref WATCHLIST_TABLE: &'static str = "trading_post.watchlist";
+    pub static ref WATCHLIST_ALL_COLUMNS: &'static [&'static str] =
+        &["player_id", "trade_id", "item_id", "created_at", "realm_id"];
+    pub static ref WATCHED_ITEM_TABLE: &'static str = "trading_post.watched_item";
+    pub static ref WATCHED_ITEM_ALL_COLUMNS: &'static [&'static str] =
+        &["player_id", "realm_id", "item_id", "created_at"];
+}
+
+// The trade followed by the player. The realm and the item id are stored along
+// with the trade id, so that the trade can be read by its primary key.
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug)]
+pub struct WatchlistEntry {
+    player_id: Uuid,
+    trade_id: Uuid,
+    item_id: Uuid,
+    created_at: DateTime<Utc>,
+    realm_id: Option<String>,
+}
+
+impl WatchlistEntry {
+    pub fn new(player_id: Uuid, trade: &Trade) -> Self {
+        Self {
+            player_id,
+            trade_id: trade.id(),
+            item_id: trade.item_id(),
+            created_at: Utc::now(),
+            realm_id: Some(trade.realm_id().to_string()),
+        }
+    }
+
+    pub fn realm_id(&self) -> &str {
+        // Entries created before realms were introduced
+        self.realm_id.as_deref().unwrap_or(DEFAULT_REALM)
+    }
+
+    pub fn trade_id(&self) -> Uuid {
+        self.trade_id
+    }
+
+    pub fn item_id(&self) -> Uuid {
+        self.item_id
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "player_id" => self.player_id,
+            "trade_id" => self.trade_id,
+            "item_id" => self.item_id,
+            "created_at" => self.created_at,
+            "realm_id" => self.realm_id
+        )
+    }
+}
+
+impl From<(&WatchlistEntry, &Trade)> for WatchedTradeDetail {
+    fn from((entry, trade): (&WatchlistEntry, &Trade)) -> Self {
+        Self {
+            trade: Some(TradeDetail::from(trade)),
+            status: trade.current_status().as_str().to_string(),
+            watched_at: entry.created_at().timestamp(),
+        }
+    }
+}
+
+// The item followed by the player. Every new trade of the item shows up
+// in the watchlist without watching the trade itself.
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug)]
+pub struct WatchedItem {
+    player_id: Uuid,
+    realm_id: String,
+    item_id: Uuid,
+    created_at: DateTime<Utc>,
+}
+
+impl WatchedItem {
+    pub fn new(player_id: Uuid, realm_id: &str, item_id: Uuid) -> Self {
+        Self {
+            player_id,
+            realm_id: realm_id.to_string(),
+            item_id,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn item_id(&self) -> Uuid {
+        self.item_id
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "player_id" => self.player_id,
+            "realm_id" => self.realm_id,
+            "item_id" => self.item_id,
+            "created_at" => self.created_at
+        )
+    }
+}
+
+impl From<(&WatchedItem, &Vec<Trade>)> for WatchedItemDetail {
+    fn from((item, trades): (&WatchedItem, &Vec<Trade>)) -> Self {
+        Self {
+            item_id: item.item_id().to_string(),
+            trades: trades.iter().map(TradeDetail::from).collect(),
+            watched_at: item.created_at().timestamp(),
+        }
+    }
+}
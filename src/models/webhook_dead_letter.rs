@@ -0,0 +1,71 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+lazy_static! {
+    pub static ref WEBHOOK_DEAD_LETTER_TABLE: &'static str = "trading_post.webhook_dead_letter";
+    pub static ref WEBHOOK_DEAD_LETTER_ALL_COLUMNS: &'static [&'static str] = &[
+        "endpoint",
+        "failed_at",
+        "id",
+        "kind",
+        "trade_id",
+        "payload",
+        "attempts",
+        "last_error",
+    ];
+}
+
+// The webhook payload that the endpoint didn't accept after all attempts.
+// Kept for the manual investigation and redelivery.
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug)]
+pub struct WebhookDeadLetter {
+    endpoint: String,
+    failed_at: DateTime<Utc>,
+    id: Uuid,
+    kind: String,
+    trade_id: Uuid,
+    payload: String,
+    attempts: i32,
+    last_error: String,
+}
+
+impl WebhookDeadLetter {
+    pub fn new(
+        endpoint: &str,
+        id: Uuid,
+        kind: &str,
+        trade_id: Uuid,
+        payload: String,
+        attempts: i32,
+        last_error: String,
+    ) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            failed_at: Utc::now(),
+            id,
+            kind: kind.to_string(),
+            trade_id,
+            payload,
+            attempts,
+            last_error,
+        }
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "endpoint" => self.endpoint,
+            "failed_at" => self.failed_at,
+            "id" => self.id,
+            "kind" => self.kind,
+            "trade_id" => self.trade_id,
+            "payload" => self.payload,
+            "attempts" => self.attempts,
+            "last_error" => self.last_error
+        )
+    }
+}
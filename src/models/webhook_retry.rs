@@ -0,0 +1,104 @@
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use cdrs_tokio_helpers_derive::{IntoCdrsValue, TryFromRow};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::webhook_dead_letter::WebhookDeadLetter;
+
+lazy_static! {
+    pub static ref WEBHOOK_RETRY_TABLE: &'static str = "trading_post.webhook_retry";
+    pub static ref WEBHOOK_RETRY_ALL_COLUMNS: &'static [&'static str] = &[
+        "endpoint",
+        "id",
+        "kind",
+        "trade_id",
+        "payload",
+        "attempts",
+        "last_error",
+        "next_attempt_at",
+    ];
+}
+
+// The webhook payload that the endpoint didn't accept yet, waiting for
+// the next attempt. Created before the first attempt fails.
+#[derive(Serialize, IntoCdrsValue, TryFromRow, Debug)]
+pub struct WebhookRetry {
+    endpoint: String,
+    id: Uuid,
+    kind: String,
+    trade_id: Uuid,
+    payload: String,
+    attempts: i32,
+    last_error: String,
+    next_attempt_at: DateTime<Utc>,
+}
+
+impl WebhookRetry {
+    pub fn new(endpoint: &str, id: Uuid, kind: &str, trade_id: Uuid, payload: String) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            id,
+            kind: kind.to_string(),
+            trade_id,
+            payload,
+            attempts: 0,
+            last_error: String::new(),
+            next_attempt_at: Utc::now(),
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn payload(&self) -> &str {
+        &self.payload
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts as u32
+    }
+
+    pub fn last_error(&self) -> &str {
+        &self.last_error
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_attempt_at <= now
+    }
+
+    // Records another failed attempt.
+    pub fn fail(&mut self, error: String, next_attempt_at: DateTime<Utc>) {
+        self.attempts += 1;
+        self.last_error = error;
+        self.next_attempt_at = next_attempt_at;
+    }
+
+    pub fn into_dead_letter(self) -> WebhookDeadLetter {
+        WebhookDeadLetter::new(
+            &self.endpoint,
+            self.id,
+            &self.kind,
+            self.trade_id,
+            self.payload,
+            self.attempts,
+            self.last_error,
+        )
+    }
+
+    pub fn into_query_values(self) -> QueryValues {
+        query_values!(
+            "endpoint" => self.endpoint,
+            "id" => self.id,
+            "kind" => self.kind,
+            "trade_id" => self.trade_id,
+            "payload" => self.payload,
+            "attempts" => self.attempts,
+            "last_error" => self.last_error,
+            "next_attempt_at" => self.next_attempt_at
+        )
+    }
+}
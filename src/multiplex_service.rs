@@ -1,14 +1,22 @@
 use axum::{
-    http::header::CONTENT_TYPE,
-    http::Request,
+    http::header::{HeaderName, AUTHORIZATION, CONTENT_TYPE},
+    http::{HeaderValue, Method, Request, Uri},
     response::{IntoResponse, Response},
 };
 use futures::{future::BoxFuture, ready};
 use std::{
     convert::Infallible,
     task::{Context, Poll},
+    time::Duration,
 };
 use tower::Service;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::cli::CliOptions;
+use crate::core::realm::REALM_METADATA_KEY;
+
+// How long browsers may cache the CORS preflight responses.
+const CORS_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
 
 pub struct MultiplexService<A, B> {
     rest: A,
@@ -108,6 +116,8 @@ where
     }
 }
 
+// Covers the native gRPC and gRPC-Web content types. The gRPC-Web requests
+// are translated by the grpc service itself.
 fn is_grpc_request<B>(req: &Request<B>) -> bool {
     req.headers()
         .get(CONTENT_TYPE)
@@ -115,3 +125,115 @@ fn is_grpc_request<B>(req: &Request<B>) -> bool {
         .filter(|content_type| content_type.starts_with(b"application/grpc"))
         .is_some()
 }
+
+fn split_origins(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(|origin| origin.trim())
+        .filter(|origin| !origin.is_empty())
+}
+
+// Accepts `*` or origins in the form sent by browsers, like
+// `https://game.example.com:8443`, so that typos stop the startup instead
+// of silently blocking the browser clients.
+pub fn validate_origins(value: String) -> Result<(), String> {
+    for origin in split_origins(&value).filter(|origin| *origin != "*") {
+        let is_valid = match origin.parse::<Uri>() {
+            Ok(uri) => match (uri.scheme_str(), uri.authority()) {
+                (Some(scheme @ ("http" | "https")), Some(authority)) => {
+                    origin == format!("{0}://{1}", scheme, authority)
+                }
+                _ => false,
+            },
+            Err(_) => false,
+        };
+
+        if !is_valid {
+            return Err(format!(
+                "{0} is not a valid origin, use the scheme and the host without a path, like https://game.example.com",
+                origin
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Browsers aren't allowed to call the API, unless the origins are given.
+pub fn create_cors_layer(opts: &CliOptions) -> CorsLayer {
+    let origins = split_origins(&opts.cors_allowed_origins).collect::<Vec<&str>>();
+    let allow_origin = match origins.contains(&"*") {
+        true => AllowOrigin::any(),
+        false => AllowOrigin::list(
+            origins
+                .iter()
+                .map(|origin| HeaderValue::from_str(origin).expect("parse validated origin")),
+        ),
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers([
+            CONTENT_TYPE,
+            AUTHORIZATION,
+            HeaderName::from_static("x-grpc-web"),
+            HeaderName::from_static("x-user-agent"),
+            HeaderName::from_static("grpc-timeout"),
+            HeaderName::from_static(REALM_METADATA_KEY),
+        ])
+        .expose_headers([
+            HeaderName::from_static("grpc-status"),
+            HeaderName::from_static("grpc-message"),
+            HeaderName::from_static("grpc-status-details-bin"),
+        ])
+        .max_age(CORS_MAX_AGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::CONTENT_TYPE, Request};
+
+    use crate::multiplex_service::{is_grpc_request, validate_origins};
+
+    fn create_request(content_type: &str) -> Request<()> {
+        Request::builder()
+            .header(CONTENT_TYPE, content_type)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_is_grpc_request_for_native_and_web_clients() {
+        assert!(is_grpc_request(&create_request("application/grpc")));
+        assert!(is_grpc_request(&create_request("application/grpc+proto")));
+        assert!(is_grpc_request(&create_request("application/grpc-web")));
+        assert!(is_grpc_request(&create_request(
+            "application/grpc-web-text; charset=utf-8"
+        )));
+    }
+
+    #[test]
+    fn test_is_grpc_request_for_rest_clients() {
+        assert!(!is_grpc_request(&create_request("application/json")));
+        assert!(!is_grpc_request(&Request::new(())));
+    }
+
+    #[test]
+    fn test_validate_origins() {
+        assert!(validate_origins("".to_string()).is_ok());
+        assert!(validate_origins("*".to_string()).is_ok());
+        assert!(
+            validate_origins("https://game.example.com, http://localhost:3000".to_string()).is_ok()
+        );
+        assert!(validate_origins("game.example.com".to_string()).is_err());
+        assert!(validate_origins("https://game.example.com/".to_string()).is_err());
+        assert!(validate_origins("ftp://game.example.com".to_string()).is_err());
+    }
+}
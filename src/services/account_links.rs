@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::fs;
+use std::str::FromStr;
+
+use log::warn;
+use uuid::Uuid;
+
+// Supplies groups of accounts that belong to the same person, e.g. the ones
+// registered by the same owner or used from the same IP hash.
+pub trait AccountLinks: Send + Sync {
+    // Returns the kind of the link between both accounts, if there is any.
+    fn find_link(&self, first: Uuid, second: Uuid) -> Option<&str>;
+}
+
+#[derive(Default)]
+pub struct StaticAccountLinks {
+    // The link kind of each group and the groups of each account.
+    groups: Vec<String>,
+    memberships: HashMap<Uuid, Vec<usize>>,
+}
+
+impl StaticAccountLinks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_group(mut self, kind: &str, accounts: &[Uuid]) -> Self {
+        let group = self.groups.len();
+        self.groups.push(kind.to_string());
+
+        for account in accounts {
+            self.memberships.entry(*account).or_default().push(group);
+        }
+
+        self
+    }
+
+    // Loads the link groups from a file, where each line is a link kind
+    // followed by comma-separated account UUIDs:
+    //
+    // same_owner 5b2c...,0e41...
+    // same_ip_hash 5b2c...,9a7f...,11d3...
+    pub fn from_file(path: &str) -> Self {
+        let content = fs::read_to_string(path).expect("read the account links file");
+
+        content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .fold(Self::new(), |links, line| {
+                let (kind, accounts) = line.split_once(' ').unwrap_or((line, ""));
+                let accounts = accounts
+                    .split(',')
+                    .filter_map(|account| match Uuid::from_str(account.trim()) {
+                        Ok(account) => Some(account),
+                        Err(_) => {
+                            warn!("Skipped invalid account `{0}` in `{1}`", account, path);
+                            None
+                        }
+                    })
+                    .collect::<Vec<Uuid>>();
+
+                links.with_group(kind, &accounts)
+            })
+    }
+}
+
+impl AccountLinks for StaticAccountLinks {
+    fn find_link(&self, first: Uuid, second: Uuid) -> Option<&str> {
+        let first_groups = self.memberships.get(&first)?;
+        let second_groups = self.memberships.get(&second)?;
+
+        first_groups
+            .iter()
+            .find(|group| second_groups.contains(group))
+            .map(|group| self.groups[*group].as_str())
+    }
+}
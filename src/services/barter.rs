@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cdrs_tokio::query_values;
+use chrono::Utc;
+use log::{error, info};
+use uuid::Uuid;
+
+use crate::core::error::Result;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::barter_offer::{
+    BarterOffer, BarterOfferStatus, BARTER_OFFER_ALL_COLUMNS, BARTER_OFFER_TABLE,
+};
+use crate::models::inventory_change::{
+    InventoryChange, INVENTORY_CHANGE_ALL_COLUMNS, INVENTORY_CHANGE_TABLE,
+};
+use crate::models::trade::{LineItem, Trade};
+use crate::services::inventory::{InventoryHook, Reservation};
+use crate::services::lease::{Lease, LEASE_INTERVALS};
+
+// Returns whether the offered items include every wanted item in at least
+// the wanted quantity. Extra items are allowed.
+pub fn covers_wanted_items(wanted: &[LineItem], offered: &[LineItem]) -> bool {
+    let sum_quantities = |items: &[LineItem]| {
+        items.iter().fold(HashMap::new(), |mut quantities, item| {
+            *quantities.entry(item.item_id()).or_insert(0i64) += i64::from(item.quantity());
+            quantities
+        })
+    };
+    let offered = sum_quantities(offered);
+
+    sum_quantities(wanted)
+        .iter()
+        .all(|(item_id, quantity)| offered.get(item_id).unwrap_or(&0) >= quantity)
+}
+
+fn get_resolve_query<'a>(offer: &'a BarterOffer) -> QueryBuilder<'a> {
+    QueryBuilder::new(&BARTER_OFFER_TABLE)
+        .query_type(QueryType::Update)
+        .columns(&["status", "resolved_at"])
+        .filter_by(Filter::new(
+            "realm_id",
+            Operator::Eq,
+            Some(offer.realm_id().into()),
+        ))
+        .filter_by(Filter::new(
+            "trade_id",
+            Operator::Eq,
+            Some(offer.trade_id().into()),
+        ))
+        .filter_by(Filter::new("id", Operator::Eq, Some(offer.id().into())))
+}
+
+// Returns the resolution of the offer, that is applied only to the pending
+// offer. Concurrent accepts and rejects of the offer read it as pending, so
+// only one of them is applied.
+fn get_pending_resolve_query(offer: &BarterOffer) -> QueryBuilder<'_> {
+    get_resolve_query(offer).only_if_null("resolved_at")
+}
+
+fn get_change_query(query_type: QueryType) -> QueryBuilder<'static> {
+    QueryBuilder::new(&INVENTORY_CHANGE_TABLE).query_type(query_type)
+}
+
+// Stores barter offers and keeps the offered items reserved in the player
+// inventory until the offer is resolved.
+pub struct BarterOffers {
+    db: CassandraSession,
+    inventory: Arc<dyn InventoryHook>,
+}
+
+impl BarterOffers {
+    pub fn new(db: CassandraSession, inventory: Arc<dyn InventoryHook>) -> Self {
+        Self { db, inventory }
+    }
+
+    // The items are reserved before the offer is stored, so that the seller
+    // never sees an offer without the items behind it.
+    pub async fn offer(&self, offer: BarterOffer) -> Result<Uuid> {
+        let offer_id = offer.id();
+        let reservation = Reservation {
+            id: offer_id,
+            player_id: offer.offered_by(),
+            items: offer.items()?,
+        };
+        self.inventory.reserve(&reservation).await?;
+
+        let query = QueryBuilder::new(&BARTER_OFFER_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&BARTER_OFFER_ALL_COLUMNS)
+            .build();
+        if let Err(err) = query.try_insert(&self.db, &offer.into_query_values()).await {
+            if let Err(err) = self.inventory.release(offer_id).await {
+                error!(
+                    "Can't release the items of the unsaved barter offer {0}: {1}",
+                    offer_id, err
+                );
+            }
+            return Err(err);
+        }
+
+        Ok(offer_id)
+    }
+
+    pub async fn get(&self, realm_id: &str, trade_id: Uuid, offer_id: Uuid) -> Result<BarterOffer> {
+        let query = QueryBuilder::new(&BARTER_OFFER_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&BARTER_OFFER_ALL_COLUMNS)
+            .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+            .filter_by(Filter::new("trade_id", Operator::Eq, Some(trade_id.into())))
+            .filter_by(Filter::new("id", Operator::Eq, Some(offer_id.into())))
+            .build();
+
+        query.get_instance::<BarterOffer>(&self.db).await
+    }
+
+    pub async fn list(&self, realm_id: &str, trade_id: Uuid) -> Result<Vec<BarterOffer>> {
+        let query = QueryBuilder::new(&BARTER_OFFER_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&BARTER_OFFER_ALL_COLUMNS)
+            .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+            .filter_by(Filter::new("trade_id", Operator::Eq, Some(trade_id.into())))
+            .build();
+
+        query.get_entries::<BarterOffer>(&self.db).await
+    }
+
+    // Returns whether the offer was accepted by this call. The trade must be
+    // sold only after that, and the acceptance withdrawn when it can't be.
+    pub async fn accept(&self, offer: &BarterOffer) -> Result<bool> {
+        let query = get_pending_resolve_query(offer).build();
+
+        query
+            .update_if(
+                &self.db,
+                &query_values!(
+                    "status" => BarterOfferStatus::Accepted.as_str(),
+                    "resolved_at" => Utc::now().timestamp()
+                ),
+            )
+            .await
+    }
+
+    // Rejects the accepted offer, when the trade was closed by someone else
+    // before it was sold for the offer.
+    pub async fn withdraw_acceptance(&self, offer: &BarterOffer) -> Result<()> {
+        let query = get_resolve_query(offer)
+            .only_if(Filter::new(
+                "status",
+                Operator::Eq,
+                Some(BarterOfferStatus::Accepted.as_str().into()),
+            ))
+            .build();
+        let applied = query
+            .update_if(
+                &self.db,
+                &query_values!(
+                    "status" => BarterOfferStatus::Rejected.as_str(),
+                    "resolved_at" => Utc::now().timestamp()
+                ),
+            )
+            .await?;
+
+        if applied {
+            self.change_inventory(InventoryChange::release(offer.id()))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Gives the offered items of the accepted offer to the seller. The failed
+    // transfer stays recorded and is retried later.
+    pub async fn deliver(&self, offer: &BarterOffer, seller: Uuid) -> Result<()> {
+        self.apply(offer.id(), Some(seller)).await
+    }
+
+    // Returns the offered items back to the player. Only one of the concurrent
+    // calls resolves the offer, so the items are released once.
+    pub async fn reject(&self, offer: &BarterOffer) -> Result<bool> {
+        let query = get_pending_resolve_query(offer).build();
+        let applied = query
+            .update_if(
+                &self.db,
+                &query_values!(
+                    "status" => BarterOfferStatus::Rejected.as_str(),
+                    "resolved_at" => Utc::now().timestamp()
+                ),
+            )
+            .await?;
+
+        if applied {
+            self.change_inventory(InventoryChange::release(offer.id()))
+                .await?;
+        }
+
+        Ok(applied)
+    }
+
+    // Rejects the offers left after the trade was closed.
+    pub async fn reject_pending(&self, trade: &Trade) {
+        let offers = match self.list(trade.realm_id(), trade.id()).await {
+            Ok(offers) => offers,
+            Err(err) => {
+                error!(
+                    "Can't read the offers of the trade {0}: {1}",
+                    trade.id(),
+                    err
+                );
+                return;
+            }
+        };
+
+        for offer in offers.iter().filter(|offer| offer.is_pending()) {
+            if let Err(err) = self.reject(offer).await {
+                error!("Can't reject the barter offer {0}: {1}", offer.id(), err);
+            }
+        }
+    }
+
+    // Records the change before sending it to the inventory hook, so that
+    // it's retried when the hook fails.
+    async fn change_inventory(&self, change: InventoryChange) -> Result<()> {
+        let (reservation_id, recipient) = (change.id(), change.recipient());
+        let query = get_change_query(QueryType::Insert)
+            .columns(&INVENTORY_CHANGE_ALL_COLUMNS)
+            .build();
+        query
+            .try_insert(&self.db, &change.into_query_values())
+            .await?;
+
+        self.apply(reservation_id, recipient).await
+    }
+
+    // Sends the recorded change to the inventory hook and forgets it once
+    // the hook applied it.
+    async fn apply(&self, reservation_id: Uuid, recipient: Option<Uuid>) -> Result<()> {
+        match recipient {
+            Some(recipient) => self.inventory.transfer(reservation_id, recipient).await?,
+            None => self.inventory.release(reservation_id).await?,
+        }
+
+        let query = get_change_query(QueryType::Delete)
+            .filter_by(Filter::new("id", Operator::Eq, Some(reservation_id.into())))
+            .build();
+        query.delete(&self.db).await?;
+
+        Ok(())
+    }
+
+    // Retries the inventory changes, that the hook didn't apply yet.
+    pub async fn run(self, interval: Duration) {
+        let lease = Lease::new(
+            self.db.clone(),
+            "inventory_changes",
+            interval * LEASE_INTERVALS,
+        );
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match lease.acquire().await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(err) => {
+                    error!("Can't acquire the inventory changes lease: {0}", err);
+                    continue;
+                }
+            }
+
+            match self.retry_changes().await {
+                Ok(0) => {}
+                Ok(applied) => info!("{0} inventory changes applied on retry", applied),
+                Err(err) => error!("Can't read the inventory changes: {0}", err),
+            }
+        }
+    }
+
+    async fn retry_changes(&self) -> Result<usize> {
+        let query = get_change_query(QueryType::Select)
+            .columns(&INVENTORY_CHANGE_ALL_COLUMNS)
+            .build();
+        let changes = query.get_entries::<InventoryChange>(&self.db).await?;
+
+        let mut applied = 0;
+        for change in changes {
+            match self.apply(change.id(), change.recipient()).await {
+                Ok(()) => applied += 1,
+                Err(err) => error!(
+                    "Can't apply the inventory change of the reservation {0}: {1}",
+                    change.id(),
+                    err
+                ),
+            }
+        }
+
+        Ok(applied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::models::barter_offer::BarterOffer;
+    use crate::models::trade::{LineItem, Trade};
+    use crate::proto::LineItem as LineItemDetail;
+    use crate::services::barter::{covers_wanted_items, get_pending_resolve_query};
+
+    fn create_item(item_id: Uuid, quantity: i32) -> LineItem {
+        LineItem::from(LineItemDetail {
+            item_id: item_id.to_string(),
+            item_name: "Ore".to_string(),
+            quantity,
+        })
+    }
+
+    #[test]
+    fn test_offer_covers_wanted_items() {
+        let (ore, gem) = (Uuid::new_v4(), Uuid::new_v4());
+        let wanted = vec![create_item(ore, 10), create_item(gem, 1)];
+
+        assert!(covers_wanted_items(
+            &wanted,
+            &[
+                create_item(gem, 1),
+                create_item(ore, 4),
+                create_item(ore, 6)
+            ]
+        ));
+        assert!(covers_wanted_items(
+            &wanted,
+            &[
+                create_item(ore, 20),
+                create_item(gem, 1),
+                create_item(Uuid::new_v4(), 1)
+            ]
+        ));
+        assert!(!covers_wanted_items(
+            &wanted,
+            &[create_item(ore, 9), create_item(gem, 1)]
+        ));
+        assert!(!covers_wanted_items(&wanted, &[create_item(ore, 10)]));
+    }
+
+    #[test]
+    fn test_offer_is_resolved_only_while_pending() {
+        // Accepting and rejecting the offer use the same condition, so only
+        // one of the concurrent answers moves the reserved items
+        let trade = Trade::builder().build();
+        let offer = BarterOffer::new(&trade, Uuid::new_v4(), "buyer", &[]);
+        let query = get_pending_resolve_query(&offer).build();
+
+        assert!(query.get_raw_cql().ends_with("IF resolved_at = null"));
+    }
+}
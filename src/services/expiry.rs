@@ -0,0 +1,156 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use cdrs_tokio::query_values;
+use log::{error, info};
+
+use crate::core::error::Result;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::trade::{Trade, TradeStatus, EMPTY_UUID, TRADE_ALL_COLUMNS, TRADE_TABLE};
+use crate::models::trade_change::TradeChange;
+use crate::services::barter::BarterOffers;
+use crate::services::inventory::InventoryHook;
+use crate::services::lease::{Lease, LEASE_INTERVALS};
+use crate::services::mailbox::get_expiry_mail;
+use crate::services::market_summary::MarketSummaries;
+use crate::services::outbox::get_outbox_entry;
+use crate::services::price_history::PriceHistory;
+use crate::services::trade_changes::TradeChanges;
+use crate::services::trade_events::{TradeEvent, TradeEventBus, TradeEventKind};
+use crate::services::trade_updates::get_trade_update;
+
+// Closes the trades that reached their expiration time and notifies
+// the subscribers about it. The scan reads every open trade, so only
+// a single replica runs it at a time.
+pub struct ExpiryWatcher {
+    db: CassandraSession,
+    events: TradeEventBus,
+    market_summaries: MarketSummaries,
+    price_history: PriceHistory,
+    barter_offers: BarterOffers,
+    trade_changes: TradeChanges,
+}
+
+impl ExpiryWatcher {
+    pub fn new(
+        db: CassandraSession,
+        events: TradeEventBus,
+        inventory: Arc<dyn InventoryHook>,
+    ) -> Self {
+        Self {
+            market_summaries: MarketSummaries::new(db.clone()),
+            price_history: PriceHistory::new(db.clone()),
+            barter_offers: BarterOffers::new(db.clone(), inventory),
+            trade_changes: TradeChanges::new(db.clone()),
+            db,
+            events,
+        }
+    }
+
+    pub async fn run(self, interval: Duration) {
+        let lease = Lease::new(self.db.clone(), "expiry", interval * LEASE_INTERVALS);
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match lease.acquire().await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(err) => {
+                    error!("Can't acquire the expiry lease: {0}", err);
+                    continue;
+                }
+            }
+
+            match self.scan().await {
+                Ok(0) => {}
+                Ok(expired) => info!("Expiry scan finished, {0} trades closed", expired),
+                Err(err) => error!("Expiry scan failed: {0}", err),
+            }
+        }
+    }
+
+    pub async fn scan(&self) -> Result<usize> {
+        let query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
+            .allow_filtering(true)
+            .build();
+        let trades = query
+            .get_entries::<Trade>(&self.db)
+            .await?
+            .into_iter()
+            .filter(|trade| trade.is_expired())
+            .collect::<Vec<Trade>>();
+
+        let mut expired = 0;
+        for mut trade in trades {
+            if let Err(err) = self.trade_changes.finish_pending(&mut trade).await {
+                error!(
+                    "Can't finish the last change of the trade {0}: {1}",
+                    trade.id(),
+                    err
+                );
+                continue;
+            }
+
+            let event = TradeEvent::new(TradeEventKind::Expired, &trade);
+            match self.close(&trade, &event).await {
+                Ok(true) => {}
+                // The trade was sold or cancelled after it was read
+                Ok(false) => continue,
+                Err(err) => {
+                    error!("Can't close the expired trade {0}: {1}", trade.id(), err);
+                    continue;
+                }
+            }
+
+            // The top bidder wins the expired trade for the last bid
+            if trade.bought_by() != *EMPTY_UUID {
+                self.price_history
+                    .record_sale(&trade, trade.bid_price(), trade.expired_at())
+                    .await;
+            }
+            self.events.publish(event);
+            self.market_summaries
+                .refresh(trade.realm_id(), trade.item_id())
+                .await;
+            if trade.is_barter() {
+                self.barter_offers.reject_pending(&trade).await;
+            }
+            expired += 1;
+        }
+
+        Ok(expired)
+    }
+
+    // Returns whether the trade was closed by this call.
+    async fn close(&self, trade: &Trade, event: &TradeEvent) -> Result<bool> {
+        let change = TradeChange::new(trade)
+            .with_event(get_outbox_entry(event))
+            .with_mail(get_expiry_mail(trade)?);
+        let pending_change = self.trade_changes.prepare(&change).await?;
+
+        let update_query =
+            get_trade_update(trade, &["is_deleted", "status", "pending_change"]).build();
+        let update_query_values = query_values!(
+            "is_deleted" => true,
+            "status" => TradeStatus::Expired.as_str(),
+            "pending_change" => pending_change.to_owned()
+        );
+        if !update_query
+            .update_if(&self.db, &update_query_values)
+            .await?
+        {
+            self.trade_changes.discard(&change).await;
+            return Ok(false);
+        }
+
+        self.trade_changes.finish(&change, &pending_change).await;
+        Ok(true)
+    }
+}
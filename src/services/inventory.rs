@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hyper::client::HttpConnector;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Method, Request, Uri};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::cli::CliOptions;
+use crate::core::error::{Error, Result};
+use crate::models::trade::LineItem;
+
+// The time given to the game server to move the items.
+const INVENTORY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InventoryHookKind {
+    // Reservations are kept in memory. Used for local runs and tests.
+    Local,
+    Webhook,
+}
+
+impl FromStr for InventoryHookKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "local" => Ok(InventoryHookKind::Local),
+            "webhook" => Ok(InventoryHookKind::Webhook),
+            _ => Err(format!(
+                "{0} is not a valid inventory hook, use `local` or `webhook`",
+                value
+            )),
+        }
+    }
+}
+
+// The items taken out of the player inventory for the barter offer.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Reservation {
+    pub id: Uuid,
+    pub player_id: Uuid,
+    pub items: Vec<LineItem>,
+}
+
+// Moves items between player inventories on the game server. Every call
+// can be repeated with the same reservation id, so the game server must
+// apply each of them only once.
+#[tonic::async_trait]
+pub trait InventoryHook: Send + Sync {
+    // Takes the items out of the player inventory. Fails when the player
+    // doesn't have them.
+    async fn reserve(&self, reservation: &Reservation) -> Result<()>;
+
+    // Returns the reserved items back to the player.
+    async fn release(&self, reservation_id: Uuid) -> Result<()>;
+
+    // Gives the reserved items to another player.
+    async fn transfer(&self, reservation_id: Uuid, recipient: Uuid) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReservationState {
+    Reserved,
+    Released,
+    Transferred(Uuid),
+}
+
+// Keeps the reservations in memory and treats every player as having
+// the reserved items. Follows the rules expected from the game server:
+// the repeated call is accepted, but the items released back to the player
+// can't be transferred and the transferred items can't be released.
+#[derive(Default)]
+pub struct LocalInventory {
+    reservations: Mutex<HashMap<Uuid, (Reservation, ReservationState)>>,
+}
+
+impl LocalInventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn change_state(&self, reservation_id: Uuid, new_state: ReservationState) -> Result<()> {
+        let mut reservations = self.reservations.lock().expect("lock local inventory");
+        let state = match reservations.get_mut(&reservation_id) {
+            Some((_, state)) => state,
+            None => {
+                return Err(Error::DeliveryFailed(format!(
+                    "The reservation {0} doesn't exist.",
+                    reservation_id
+                )))
+            }
+        };
+
+        if *state != ReservationState::Reserved && *state != new_state {
+            return Err(Error::DeliveryFailed(format!(
+                "The reservation {0} was already {1}.",
+                reservation_id,
+                match state {
+                    ReservationState::Released => "released",
+                    _ => "transferred",
+                }
+            )));
+        }
+
+        *state = new_state;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl LocalInventory {
+    pub fn get_reservation(&self, reservation_id: Uuid) -> Option<Reservation> {
+        let reservations = self.reservations.lock().expect("lock local inventory");
+        reservations
+            .get(&reservation_id)
+            .filter(|(_, state)| *state == ReservationState::Reserved)
+            .map(|(reservation, _)| reservation.clone())
+    }
+}
+
+#[tonic::async_trait]
+impl InventoryHook for LocalInventory {
+    async fn reserve(&self, reservation: &Reservation) -> Result<()> {
+        let mut reservations = self.reservations.lock().expect("lock local inventory");
+        reservations
+            .entry(reservation.id)
+            .or_insert_with(|| (reservation.clone(), ReservationState::Reserved));
+        Ok(())
+    }
+
+    async fn release(&self, reservation_id: Uuid) -> Result<()> {
+        self.change_state(reservation_id, ReservationState::Released)
+    }
+
+    async fn transfer(&self, reservation_id: Uuid, recipient: Uuid) -> Result<()> {
+        self.change_state(reservation_id, ReservationState::Transferred(recipient))
+    }
+}
+
+#[derive(Serialize)]
+struct TransferPayload {
+    id: Uuid,
+    recipient: Uuid,
+}
+
+#[derive(Serialize)]
+struct ReleasePayload {
+    id: Uuid,
+}
+
+// Sends the inventory changes as JSON in POST requests to the `reserve`,
+// `release` and `transfer` paths of the game server. Any response except
+// 2xx is treated as a failed change.
+pub struct WebhookInventory {
+    client: Client<HttpConnector>,
+    url: String,
+}
+
+impl WebhookInventory {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn post<T: Serialize + Sync>(&self, path: &str, payload: &T) -> Result<()> {
+        let uri: Uri = format!("{0}/{1}", self.url, path)
+            .parse()
+            .expect("parse inventory webhook url");
+        let body = serde_json::to_vec(payload).expect("serialize inventory change");
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .expect("build inventory request");
+
+        let response = tokio::time::timeout(INVENTORY_TIMEOUT, self.client.request(request))
+            .await
+            .map_err(|_| Error::DeliveryFailed("The inventory hook timed out.".to_string()))?
+            .map_err(|err| {
+                Error::DeliveryFailed(format!("The inventory hook is unavailable: {0}", err))
+            })?;
+
+        if !response.status().is_success() {
+            return Err(Error::DeliveryFailed(format!(
+                "The inventory hook responded with {0}.",
+                response.status()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl InventoryHook for WebhookInventory {
+    async fn reserve(&self, reservation: &Reservation) -> Result<()> {
+        self.post("reserve", reservation).await
+    }
+
+    async fn release(&self, reservation_id: Uuid) -> Result<()> {
+        self.post("release", &ReleasePayload { id: reservation_id })
+            .await
+    }
+
+    async fn transfer(&self, reservation_id: Uuid, recipient: Uuid) -> Result<()> {
+        let payload = TransferPayload {
+            id: reservation_id,
+            recipient,
+        };
+        self.post("transfer", &payload).await
+    }
+}
+
+pub fn create_inventory_hook(opts: &CliOptions) -> Arc<dyn InventoryHook> {
+    match opts.inventory_hook {
+        InventoryHookKind::Local => Arc::new(LocalInventory::new()),
+        InventoryHookKind::Webhook => {
+            // The local hook must be chosen explicitly, so the missing url
+            // stops the startup instead of keeping items in memory
+            let url = opts.inventory_webhook_url.as_ref().expect(
+                "--inventory-webhook-url is required, use `--inventory-hook local` only for local runs",
+            );
+            Arc::new(WebhookInventory::new(url))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::services::inventory::{InventoryHook, LocalInventory, Reservation};
+
+    async fn reserve(inventory: &LocalInventory) -> Reservation {
+        let reservation = Reservation {
+            id: Uuid::new_v4(),
+            player_id: Uuid::new_v4(),
+            items: vec![],
+        };
+        inventory.reserve(&reservation).await.unwrap();
+        reservation
+    }
+
+    #[tokio::test]
+    async fn test_local_inventory_transfers_reservation_once() {
+        // The accepted offer, that is delivered again by the retry
+        let inventory = LocalInventory::new();
+        let reservation = reserve(&inventory).await;
+        let seller = Uuid::new_v4();
+        assert_eq!(
+            inventory.get_reservation(reservation.id),
+            Some(reservation.clone())
+        );
+
+        inventory.transfer(reservation.id, seller).await.unwrap();
+        assert_eq!(inventory.get_reservation(reservation.id), None);
+        assert!(inventory.transfer(reservation.id, seller).await.is_ok());
+        assert!(inventory
+            .transfer(reservation.id, Uuid::new_v4())
+            .await
+            .is_err());
+        assert!(inventory.release(reservation.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_local_inventory_keeps_released_reservation() {
+        // The offer rejected by the seller or after the trade expired
+        let inventory = LocalInventory::new();
+        let reservation = reserve(&inventory).await;
+
+        inventory.release(reservation.id).await.unwrap();
+        assert_eq!(inventory.get_reservation(reservation.id), None);
+        assert!(inventory.release(reservation.id).await.is_ok());
+        assert!(inventory
+            .transfer(reservation.id, Uuid::new_v4())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_local_inventory_rejects_unknown_reservation() {
+        let inventory = LocalInventory::new();
+
+        assert!(inventory.release(Uuid::new_v4()).await.is_err());
+        assert!(inventory
+            .transfer(Uuid::new_v4(), Uuid::new_v4())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use cdrs_tokio::query::QueryValues;
+use cdrs_tokio::query_values;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::core::error::Result;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::lease::{LEASE_ALL_COLUMNS, LEASE_TABLE};
+
+// The amount of job intervals, after which another replica takes over
+// the job from the replica that stopped running it.
+pub const LEASE_INTERVALS: u32 = 3;
+
+// Lets a single replica run the background job at a time. The holder renews
+// the lease on every run, other replicas take it over once the holder stops
+// renewing it.
+pub struct Lease {
+    db: CassandraSession,
+    name: String,
+    holder: Uuid,
+    duration: Duration,
+    // When the lease was last acquired or renewed by this replica.
+    renewed_at: Mutex<Option<Instant>>,
+}
+
+impl Lease {
+    pub fn new(db: CassandraSession, name: &str, duration: Duration) -> Self {
+        Self {
+            db,
+            name: name.to_string(),
+            holder: Uuid::new_v4(),
+            duration,
+            renewed_at: Mutex::new(None),
+        }
+    }
+
+    // Returns whether the replica holds the lease for the next run.
+    pub async fn acquire(&self) -> Result<bool> {
+        let now = Utc::now().timestamp();
+        let query_values = self.get_query_values(now);
+
+        let create_query = QueryBuilder::new(&LEASE_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&LEASE_ALL_COLUMNS)
+            .if_not_exists()
+            .build();
+        if create_query.update_if(&self.db, &query_values).await? || self.renew().await? {
+            self.set_renewed(true);
+            return Ok(true);
+        }
+
+        let take_over_query = self
+            .get_update_query()
+            .only_if(Filter::new("expires_at", Operator::Lte, Some(now.into())))
+            .build();
+        let acquired = take_over_query.update_if(&self.db, &query_values).await?;
+        self.set_renewed(acquired);
+        Ok(acquired)
+    }
+
+    // Returns whether the replica still holds the lease. Called by the runs
+    // that can take longer than the lease, the lease is renewed once half of
+    // it has passed. The lease that was taken over isn't taken back, so
+    // the run must stop once it's lost.
+    pub async fn keep(&self) -> Result<bool> {
+        let renewed_at = *self.renewed_at.lock().unwrap();
+        match renewed_at {
+            Some(renewed_at) if renewed_at.elapsed() < self.duration / 2 => return Ok(true),
+            Some(_) => {}
+            None => return Ok(false),
+        }
+
+        let renewed = self.renew().await?;
+        self.set_renewed(renewed);
+        Ok(renewed)
+    }
+
+    async fn renew(&self) -> Result<bool> {
+        let renew_query = self
+            .get_update_query()
+            .only_if(Filter::new(
+                "holder",
+                Operator::Eq,
+                Some(self.holder.into()),
+            ))
+            .build();
+
+        renew_query
+            .update_if(&self.db, &self.get_query_values(Utc::now().timestamp()))
+            .await
+    }
+
+    fn set_renewed(&self, renewed: bool) {
+        *self.renewed_at.lock().unwrap() = renewed.then(Instant::now);
+    }
+
+    fn get_query_values(&self, now: i64) -> QueryValues {
+        query_values!(
+            "name" => self.name.to_owned(),
+            "holder" => self.holder,
+            "expires_at" => now + self.duration.as_secs() as i64
+        )
+    }
+
+    fn get_update_query(&self) -> QueryBuilder<'_> {
+        QueryBuilder::new(&LEASE_TABLE)
+            .query_type(QueryType::Update)
+            .columns(&["holder", "expires_at"])
+            .filter_by(Filter::new(
+                "name",
+                Operator::Eq,
+                Some(self.name.as_str().into()),
+            ))
+    }
+}
@@ -0,0 +1,450 @@
+use cdrs_tokio::query_values;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::core::error::Result;
+use crate::core::money::Money;
+use crate::core::orm::batch::Batch;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::mail::{Mail, MailKind, MAIL_ALL_COLUMNS, MAIL_TABLE};
+use crate::models::trade::{Trade, EMPTY_UUID};
+use crate::services::settlement::Settlement;
+
+// Adds the mail to the batch with the state change of the trade, so that
+// the mail is sent only when the change is applied.
+pub fn record_mail(mut batch: Batch, mail: Vec<Mail>) -> Result<Batch> {
+    let query = QueryBuilder::new(&MAIL_TABLE)
+        .query_type(QueryType::Insert)
+        .columns(&MAIL_ALL_COLUMNS)
+        .build();
+
+    for entry in mail {
+        batch = batch.add(&query, &entry.into_query_values())?;
+    }
+
+    Ok(batch)
+}
+
+// Sends every item of the trade in a separate mail. The mail is recorded in
+// the same batch, so the bundle is delivered either completely or not at all.
+// Fails when the items of the bundle can't be decoded.
+fn get_item_mail(player_id: Uuid, kind: MailKind, trade: &Trade) -> Result<Vec<Mail>> {
+    Ok(trade
+        .line_items()?
+        .iter()
+        .map(|item| Mail::with_item(player_id, kind, trade, item))
+        .collect())
+}
+
+// Returns the bid back to the top bidder, when the bid was beaten.
+pub fn get_outbid_mail(trade: &Trade) -> Vec<Mail> {
+    if trade.bought_by() == *EMPTY_UUID {
+        return vec![];
+    }
+
+    vec![Mail::with_currency(
+        trade.bought_by(),
+        MailKind::OutbidRefund,
+        trade,
+        trade.bid_price(),
+    )]
+}
+
+// Sends the proceeds to the seller and the item to the buyer. The previous
+// top bidder gets the bid back.
+pub fn get_buyout_mail(trade: &Trade, buyer: Uuid, amount: Money) -> Result<Vec<Mail>> {
+    let mut mail = get_outbid_mail(trade);
+    mail.push(Mail::with_currency(
+        trade.created_by(),
+        MailKind::SaleProceeds,
+        trade,
+        trade.get_proceeds(amount)?,
+    ));
+    mail.extend(get_item_mail(buyer, MailKind::WonItem, trade)?);
+    Ok(mail)
+}
+
+// The top bidder wins the expired trade. Without bids the item goes back
+// to the seller.
+pub fn get_expiry_mail(trade: &Trade) -> Result<Vec<Mail>> {
+    if trade.bought_by() == *EMPTY_UUID {
+        return get_item_mail(trade.created_by(), MailKind::ExpiredItem, trade);
+    }
+
+    let mut mail = vec![Mail::with_currency(
+        trade.created_by(),
+        MailKind::SaleProceeds,
+        trade,
+        trade.get_proceeds(trade.bid_price())?,
+    )];
+    mail.extend(get_item_mail(trade.bought_by(), MailKind::WonItem, trade)?);
+    Ok(mail)
+}
+
+// Sends the items of the barter trade to the player whose offer was accepted.
+// The offered items are moved to the seller by the inventory hook.
+pub fn get_barter_mail(trade: &Trade, buyer: Uuid) -> Result<Vec<Mail>> {
+    get_item_mail(buyer, MailKind::WonItem, trade)
+}
+
+// Returns the item to the seller and the refund to the top bidder, if any.
+pub fn get_cancellation_mail(trade: &Trade, refund: Option<(Uuid, Money)>) -> Result<Vec<Mail>> {
+    let mut mail = get_item_mail(trade.created_by(), MailKind::CancelledItem, trade)?;
+
+    if let Some((bidder, amount)) = refund {
+        mail.push(Mail::with_currency(
+            bidder,
+            MailKind::CancellationRefund,
+            trade,
+            amount,
+        ));
+    }
+
+    Ok(mail)
+}
+
+pub struct Mailbox {
+    db: CassandraSession,
+    settlement: Settlement,
+}
+
+impl Mailbox {
+    pub fn new(db: CassandraSession) -> Self {
+        Self {
+            settlement: Settlement::new(db.clone()),
+            db,
+        }
+    }
+
+    pub async fn get(&self, player_id: Uuid, id: Uuid) -> Result<Mail> {
+        let query = QueryBuilder::new(&MAIL_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&MAIL_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "player_id",
+                Operator::Eq,
+                Some(player_id.into()),
+            ))
+            .filter_by(Filter::new("id", Operator::Eq, Some(id.into())))
+            .build();
+
+        query.get_instance::<Mail>(&self.db).await
+    }
+
+    // Returns the mail of the player, starting from the most recent one.
+    pub async fn get_mail(&self, player_id: Uuid) -> Result<Vec<Mail>> {
+        let query = QueryBuilder::new(&MAIL_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&MAIL_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "player_id",
+                Operator::Eq,
+                Some(player_id.into()),
+            ))
+            .build();
+        let mut mail = query.get_entries::<Mail>(&self.db).await?;
+        mail.sort_by_key(|entry| std::cmp::Reverse(entry.created_at()));
+
+        Ok(mail)
+    }
+
+    // Returns the mail and whether it was claimed by this call.
+    pub async fn claim(&self, player_id: Uuid, id: Uuid) -> Result<(Mail, bool)> {
+        let mail = self.get(player_id, id).await?;
+        self.deliver(mail).await
+    }
+
+    // Returns the mail claimed by this call.
+    pub async fn claim_all(&self, player_id: Uuid) -> Result<Vec<Mail>> {
+        let mut claimed = vec![];
+
+        for mail in self.get_mail(player_id).await? {
+            if mail.is_claimed() {
+                continue;
+            }
+
+            if let (mail, true) = self.deliver(mail).await? {
+                claimed.push(mail);
+            }
+        }
+
+        Ok(claimed)
+    }
+
+    // The currency is credited before the mail is marked as claimed, so that
+    // a failed claim can be safely repeated. Only one of the concurrent claims
+    // marks the mail as claimed.
+    async fn deliver(&self, mut mail: Mail) -> Result<(Mail, bool)> {
+        if mail.is_claimed() {
+            return Ok((mail, false));
+        }
+
+        if mail.currency().is_positive() {
+            self.settlement.claim(&mail).await?;
+        }
+
+        let claimed_at = Utc::now();
+        let query = QueryBuilder::new(&MAIL_TABLE)
+            .query_type(QueryType::Update)
+            .columns(&["claimed_at"])
+            .filter_by(Filter::new(
+                "player_id",
+                Operator::Eq,
+                Some(mail.player_id().into()),
+            ))
+            .filter_by(Filter::new("id", Operator::Eq, Some(mail.id().into())))
+            .only_if_null("claimed_at")
+            .build();
+        let applied = query
+            .update_if(&self.db, &query_values!("claimed_at" => claimed_at))
+            .await?;
+
+        if !applied {
+            let mail = self.get(mail.player_id(), mail.id()).await?;
+            return Ok((mail, false));
+        }
+
+        mail.set_claimed_at(claimed_at);
+        Ok((mail, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::core::money::Money;
+    use crate::models::mail::{Mail, MailKind};
+    use crate::models::trade::Trade;
+    use crate::proto::{CreateTradeRequest, LineItem, Mail as MailDetail};
+    use crate::services::mailbox::{
+        get_buyout_mail, get_cancellation_mail, get_expiry_mail, get_outbid_mail,
+    };
+
+    // Returns the recipient, the kind, the currency and the item of each mail.
+    fn get_attachments(mail: &[Mail]) -> Vec<(Uuid, String, i64, Option<String>)> {
+        mail.iter()
+            .map(|entry| {
+                let detail = MailDetail::from(entry);
+                (
+                    entry.player_id(),
+                    detail.kind,
+                    detail.currency,
+                    detail.item_id,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_outbid_mail_refunds_top_bidder() {
+        let bidder = Uuid::new_v4();
+        let trade = Trade::builder().build().with_bid(bidder, "bidder", 150);
+
+        assert_eq!(
+            get_attachments(&get_outbid_mail(&trade)),
+            vec![(
+                bidder,
+                MailKind::OutbidRefund.as_str().to_string(),
+                150,
+                None
+            )]
+        );
+        assert!(get_outbid_mail(&Trade::builder().build()).is_empty());
+    }
+
+    #[test]
+    fn test_buyout_mail_pays_seller_and_delivers_item() {
+        let (seller, bidder, buyer) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let trade = Trade::builder()
+            .created_by(seller)
+            .build()
+            .with_bid(bidder, "bidder", 150);
+        let item_id = Some(trade.item_id().to_string());
+
+        assert_eq!(
+            get_attachments(&get_buyout_mail(&trade, buyer, Money::new(1000)).unwrap()),
+            vec![
+                (
+                    bidder,
+                    MailKind::OutbidRefund.as_str().to_string(),
+                    150,
+                    None
+                ),
+                (
+                    seller,
+                    MailKind::SaleProceeds.as_str().to_string(),
+                    1000,
+                    None
+                ),
+                (buyer, MailKind::WonItem.as_str().to_string(), 0, item_id),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sale_proceeds_include_house_fees() {
+        let (seller, buyer) = (Uuid::new_v4(), Uuid::new_v4());
+        let trade = Trade::builder()
+            .created_by(seller)
+            .build()
+            .with_fees(Money::new(50), 15);
+        let item_id = Some(trade.item_id().to_string());
+
+        assert_eq!(
+            get_attachments(&get_buyout_mail(&trade, buyer, Money::new(1000)).unwrap()),
+            vec![
+                (
+                    seller,
+                    MailKind::SaleProceeds.as_str().to_string(),
+                    900,
+                    None
+                ),
+                (buyer, MailKind::WonItem.as_str().to_string(), 0, item_id),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_buyout_mail_delivers_every_bundle_item() {
+        let (seller, buyer) = (Uuid::new_v4(), Uuid::new_v4());
+        let (helmet, chest) = (Uuid::new_v4(), Uuid::new_v4());
+        let trade = Trade::from(CreateTradeRequest {
+            bid_price: 100,
+            buyout_price: 1000,
+            created_by: seller.to_string(),
+            created_by_username: "seller".to_string(),
+            items: vec![
+                LineItem {
+                    item_id: helmet.to_string(),
+                    item_name: "Iron Helmet".to_string(),
+                    quantity: 1,
+                },
+                LineItem {
+                    item_id: chest.to_string(),
+                    item_name: "Iron Chestplate".to_string(),
+                    quantity: 0,
+                },
+            ],
+            ..Default::default()
+        });
+
+        assert!(trade.is_bundle());
+        assert_eq!(trade.item_id(), helmet);
+        assert_eq!(trade.item_name(), "Iron Helmet, Iron Chestplate");
+        assert_eq!(
+            get_attachments(&get_buyout_mail(&trade, buyer, Money::new(1000)).unwrap()),
+            vec![
+                (
+                    seller,
+                    MailKind::SaleProceeds.as_str().to_string(),
+                    1000,
+                    None
+                ),
+                (
+                    buyer,
+                    MailKind::WonItem.as_str().to_string(),
+                    0,
+                    Some(helmet.to_string())
+                ),
+                (
+                    buyer,
+                    MailKind::WonItem.as_str().to_string(),
+                    0,
+                    Some(chest.to_string())
+                ),
+            ]
+        );
+        assert!(trade
+            .line_items()
+            .unwrap()
+            .iter()
+            .all(|item| item.quantity() == 1));
+    }
+
+    #[test]
+    fn test_expiry_mail_returns_item_without_bids() {
+        let seller = Uuid::new_v4();
+        let trade = Trade::builder().created_by(seller).build();
+        let item_id = Some(trade.item_id().to_string());
+
+        assert_eq!(
+            get_attachments(&get_expiry_mail(&trade).unwrap()),
+            vec![(
+                seller,
+                MailKind::ExpiredItem.as_str().to_string(),
+                0,
+                item_id
+            )]
+        );
+    }
+
+    #[test]
+    fn test_expiry_mail_delivers_item_to_top_bidder() {
+        let (seller, bidder) = (Uuid::new_v4(), Uuid::new_v4());
+        let trade = Trade::builder()
+            .created_by(seller)
+            .build()
+            .with_bid(bidder, "bidder", 150);
+        let item_id = Some(trade.item_id().to_string());
+
+        assert_eq!(
+            get_attachments(&get_expiry_mail(&trade).unwrap()),
+            vec![
+                (
+                    seller,
+                    MailKind::SaleProceeds.as_str().to_string(),
+                    150,
+                    None
+                ),
+                (bidder, MailKind::WonItem.as_str().to_string(), 0, item_id),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_corrupt_bundle_fails_the_sale() {
+        let (seller, buyer) = (Uuid::new_v4(), Uuid::new_v4());
+        let trade = Trade::builder()
+            .created_by(seller)
+            .build()
+            .with_bid(buyer, "buyer", 150)
+            .with_encoded_line_items("[{\"item_id\":");
+
+        assert!(get_buyout_mail(&trade, buyer, Money::new(1000)).is_err());
+        assert!(get_expiry_mail(&trade).is_err());
+        assert!(get_cancellation_mail(&trade, None).is_err());
+    }
+
+    #[test]
+    fn test_cancellation_mail_refunds_bidder() {
+        let (seller, bidder) = (Uuid::new_v4(), Uuid::new_v4());
+        let trade = Trade::builder()
+            .created_by(seller)
+            .build()
+            .with_bid(bidder, "bidder", 150);
+        let item_id = Some(trade.item_id().to_string());
+
+        assert_eq!(
+            get_attachments(
+                &get_cancellation_mail(&trade, Some((bidder, Money::new(150)))).unwrap()
+            ),
+            vec![
+                (
+                    seller,
+                    MailKind::CancelledItem.as_str().to_string(),
+                    0,
+                    item_id
+                ),
+                (
+                    bidder,
+                    MailKind::CancellationRefund.as_str().to_string(),
+                    150,
+                    None
+                ),
+            ]
+        );
+    }
+}
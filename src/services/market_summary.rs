@@ -0,0 +1,184 @@
+use futures::future::try_join_all;
+use log::error;
+use uuid::Uuid;
+
+use crate::core::error::Result;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::market_summary::{
+    MarketSummary, MARKET_SUMMARY_ALL_COLUMNS, MARKET_SUMMARY_TABLE,
+};
+use crate::models::trade::{Trade, DEFAULT_CURRENCY, TRADE_ALL_COLUMNS, TRADE_TABLE};
+
+// Bundles are priced for all of their items together, barter trades have
+// no prices and private trades are priced for a single buyer, so they don't
+// tell the price of the item.
+fn is_summarized(trade: &Trade) -> bool {
+    !trade.is_expired()
+        && !trade.is_pending()
+        && !trade.is_bundle()
+        && !trade.is_barter()
+        && !trade.is_private()
+        && trade.currency() == DEFAULT_CURRENCY
+}
+
+fn summarize(realm_id: &str, item_id: Uuid, trades: Vec<Trade>) -> MarketSummary {
+    let trades = trades
+        .into_iter()
+        .filter(is_summarized)
+        .collect::<Vec<Trade>>();
+
+    MarketSummary::new(realm_id, item_id, &trades)
+}
+
+// Maintains the per-realm and per-item summary of active trades, so that prices for
+// many items can be read without scanning the trades. Only the trades
+// in the default currency are taken into account.
+pub struct MarketSummaries {
+    db: CassandraSession,
+}
+
+impl MarketSummaries {
+    pub fn new(db: CassandraSession) -> Self {
+        Self { db }
+    }
+
+    // Recalculates the summary of the item. Must be called after each change
+    // of the item trades.
+    pub async fn refresh(&self, realm_id: &str, item_id: Uuid) {
+        if let Err(err) = self.try_refresh(realm_id, item_id).await {
+            error!(
+                "Can't refresh the market summary of {0} in the {1} realm: {2}",
+                item_id, realm_id, err
+            );
+        }
+    }
+
+    async fn try_refresh(&self, realm_id: &str, item_id: Uuid) -> Result<()> {
+        let read_query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+            .filter_by(Filter::new("item_id", Operator::Eq, Some(item_id.into())))
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
+            .allow_filtering(true)
+            .build();
+        let trades = read_query.get_entries::<Trade>(&self.db).await?;
+
+        let summary = summarize(realm_id, item_id, trades);
+        let insert_query = QueryBuilder::new(&MARKET_SUMMARY_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&MARKET_SUMMARY_ALL_COLUMNS)
+            .build();
+        insert_query
+            .insert(&self.db, &summary.into_query_values())
+            .await;
+
+        Ok(())
+    }
+
+    // Returns the summaries of the requested items. Items without
+    // any trades yet are omitted.
+    pub async fn get_many(&self, realm_id: &str, item_ids: &[Uuid]) -> Result<Vec<MarketSummary>> {
+        let summaries =
+            try_join_all(item_ids.iter().map(|item_id| self.get(realm_id, *item_id))).await?;
+
+        Ok(summaries.into_iter().flatten().collect())
+    }
+
+    async fn get(&self, realm_id: &str, item_id: Uuid) -> Result<Option<MarketSummary>> {
+        let query = QueryBuilder::new(&MARKET_SUMMARY_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&MARKET_SUMMARY_ALL_COLUMNS)
+            .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+            .filter_by(Filter::new("item_id", Operator::Eq, Some(item_id.into())))
+            .limit(1)
+            .build();
+        let summaries = query.get_entries::<MarketSummary>(&self.db).await?;
+
+        Ok(summaries.into_iter().next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, Utc};
+    use uuid::Uuid;
+
+    use crate::models::trade::Trade;
+    use crate::proto::MarketPrice;
+    use crate::services::market_summary::{is_summarized, summarize};
+
+    fn create_item_trade(
+        item_id: Uuid,
+        bid_price: i64,
+        buyout_price: i64,
+        quantity: i32,
+        currency: &str,
+    ) -> Trade {
+        Trade::builder()
+            .item_id(item_id)
+            .item_name("Arrow")
+            .prices(bid_price, buyout_price)
+            .quantity(quantity)
+            .currency(currency)
+            .expire_in(3600)
+            .build()
+    }
+
+    #[test]
+    fn test_private_trades_are_not_summarized() {
+        assert!(is_summarized(&Trade::builder().build()));
+        assert!(!is_summarized(
+            &Trade::builder().reserved_for(Some(Uuid::new_v4())).build()
+        ));
+    }
+
+    #[test]
+    fn test_summary_takes_lowest_unit_prices() {
+        let item_id = Uuid::new_v4();
+        let trades = vec![
+            create_item_trade(item_id, 300, 900, 3, "gold"),
+            create_item_trade(item_id, 150, 0, 1, "gold"),
+            create_item_trade(item_id, 500, 2000, 10, "gold"),
+        ];
+
+        let price = MarketPrice::from(&summarize("eu-1", item_id, trades));
+        assert_eq!(price.item_id, item_id.to_string());
+        // 50 per arrow in the stack of 10, the highest total bid
+        assert_eq!(price.min_unit_bid_price, Some(50));
+        // The trade without the buyout price is skipped
+        assert_eq!(price.min_unit_buyout_price, Some(200));
+        assert_eq!(price.active_listings, 3);
+    }
+
+    #[test]
+    fn test_summary_skips_expired_trades_and_other_currencies() {
+        let item_id = Uuid::new_v4();
+        let expired = create_item_trade(item_id, 10, 20, 1, "gold").with_lifetime(
+            Utc::now() - TimeDelta::hours(2),
+            Utc::now() - TimeDelta::hours(1),
+        );
+        let trades = vec![
+            create_item_trade(item_id, 300, 900, 1, "gold"),
+            expired,
+            create_item_trade(item_id, 5, 5, 1, "gems"),
+        ];
+
+        let price = MarketPrice::from(&summarize("eu-1", item_id, trades));
+        assert_eq!(price.min_unit_bid_price, Some(300));
+        assert_eq!(price.min_unit_buyout_price, Some(900));
+        assert_eq!(price.active_listings, 1);
+    }
+
+    #[test]
+    fn test_summary_without_trades_has_no_prices() {
+        let item_id = Uuid::new_v4();
+
+        let price = MarketPrice::from(&summarize("eu-1", item_id, vec![]));
+        assert_eq!(price.min_unit_bid_price, None);
+        assert_eq!(price.min_unit_buyout_price, None);
+        assert_eq!(price.active_listings, 0);
+    }
+}
@@ -0,0 +1,23 @@
+pub mod account_links;
+pub mod barter;
+pub mod expiry;
+pub mod inventory;
+pub mod lease;
+pub mod mailbox;
+pub mod market_summary;
+pub mod notifications;
+pub mod outbox;
+pub mod price_history;
+pub mod price_suggestion;
+pub mod rate_limiter;
+pub mod realm_backfill;
+pub mod realm_visibility;
+pub mod saved_searches;
+pub mod scheduler;
+pub mod settlement;
+pub mod trade_changes;
+pub mod trade_events;
+pub mod trade_updates;
+pub mod wash_trading;
+pub mod watchlist;
+pub mod webhooks;
@@ -0,0 +1,49 @@
+use uuid::Uuid;
+
+use crate::core::error::Result;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::core::pagination::PaginationParams;
+use crate::models::notification::{Notification, NOTIFICATION_ALL_COLUMNS, NOTIFICATION_TABLE};
+
+// Stores the notifications for players, so that clients can fetch them later.
+pub struct Notifications {
+    db: CassandraSession,
+}
+
+impl Notifications {
+    pub fn new(db: CassandraSession) -> Self {
+        Self { db }
+    }
+
+    pub async fn send(&self, notification: Notification) -> Result<()> {
+        let query = QueryBuilder::new(&NOTIFICATION_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&NOTIFICATION_ALL_COLUMNS)
+            .build();
+        let query_values = notification.into_query_values();
+        query.try_insert(&self.db, &query_values).await
+    }
+
+    // Returns the player's notifications, starting from the most recent one.
+    pub async fn get_notifications(
+        &self,
+        player_id: Uuid,
+        pagination_params: &PaginationParams,
+    ) -> Result<Vec<Notification>> {
+        let query = QueryBuilder::new(&NOTIFICATION_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&NOTIFICATION_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "player_id",
+                Operator::Eq,
+                Some(player_id.into()),
+            ))
+            .build();
+
+        query
+            .get_paginated_entries::<Notification>(&self.db, pagination_params)
+            .await
+    }
+}
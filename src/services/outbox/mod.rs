@@ -0,0 +1,93 @@
+pub mod relay;
+pub mod sinks;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::cli::CliOptions;
+use crate::core::error::Result;
+use crate::core::orm::batch::Batch;
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::outbox::{OutboxEntry, OUTBOX_ALL_COLUMNS, OUTBOX_TABLE};
+use crate::services::outbox::relay::OutboxRelay;
+use crate::services::outbox::sinks::{
+    FanOutSink, JsonLinesSink, OutboxSink, OutboxSinkKind, WebhookSink,
+};
+use crate::services::saved_searches::SavedSearchAlerts;
+use crate::services::trade_events::TradeEvent;
+use crate::services::wash_trading::SaleLog;
+use crate::services::webhooks::create_game_server_webhooks;
+
+// The event in the shape delivered to sinks. The sequence number grows
+// with every published event and stays the same for redeliveries.
+#[derive(Serialize, Debug, Clone)]
+pub struct OutboxEvent {
+    pub sequence: i64,
+    pub id: Uuid,
+    pub kind: String,
+    pub aggregate_id: Uuid,
+    pub payload: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OutboxEvent {
+    pub fn new(sequence: i64, entry: &OutboxEntry) -> Self {
+        let payload = serde_json::from_str(entry.payload())
+            .unwrap_or_else(|_| serde_json::Value::String(entry.payload().to_string()));
+
+        Self {
+            sequence,
+            id: entry.id(),
+            kind: entry.kind().to_string(),
+            aggregate_id: entry.aggregate_id(),
+            payload,
+            created_at: entry.created_at(),
+        }
+    }
+}
+
+pub fn get_outbox_entry(event: &TradeEvent) -> OutboxEntry {
+    let payload = serde_json::to_string(event).expect("serialize trade event");
+    OutboxEntry::new(
+        &format!("trade.{0}", event.kind().as_str()),
+        event.trade_id(),
+        payload,
+    )
+}
+
+// Adds the event to the batch with the state change, so that the event
+// is stored only when the change is applied.
+pub fn record_event(batch: Batch, event: &TradeEvent) -> Result<Batch> {
+    let entry = get_outbox_entry(event);
+    let query = QueryBuilder::new(&OUTBOX_TABLE)
+        .query_type(QueryType::Insert)
+        .columns(&OUTBOX_ALL_COLUMNS)
+        .build();
+
+    batch.add(&query, &entry.into_query_values())
+}
+
+pub fn create_outbox_relay(opts: &CliOptions, db: CassandraSession) -> OutboxRelay {
+    let mut sinks: Vec<Box<dyn OutboxSink>> = vec![Box::new(SavedSearchAlerts::new(db.clone()))];
+    match opts.outbox_sink {
+        OutboxSinkKind::None => {}
+        OutboxSinkKind::File => sinks.push(Box::new(JsonLinesSink::new(&opts.outbox_file))),
+        OutboxSinkKind::Webhook => {
+            let url = opts
+                .outbox_webhook_url
+                .as_ref()
+                .expect("--outbox-webhook-url is required for the webhook sink");
+            sinks.push(Box::new(WebhookSink::new(url)));
+        }
+    }
+    if opts.wash_trading_scan_interval > 0 {
+        sinks.push(Box::new(SaleLog::new(db.clone())));
+    }
+    if let Some(webhooks) = create_game_server_webhooks(opts, db.clone()) {
+        sinks.push(Box::new(webhooks));
+    }
+
+    OutboxRelay::new(db, Box::new(FanOutSink::new(sinks)))
+}
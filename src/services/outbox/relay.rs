@@ -0,0 +1,263 @@
+use std::time::Duration;
+
+use cdrs_tokio::query_values;
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{error, info, warn};
+
+use crate::core::error::{Error, Result};
+use crate::core::orm::batch::Batch;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::outbox::{
+    OutboxEntry, OutboxRelayState, OUTBOX_ALL_COLUMNS, OUTBOX_RELAY_ALL_COLUMNS,
+    OUTBOX_RELAY_TABLE, OUTBOX_TABLE,
+};
+use crate::models::sale_history::get_bucket;
+use crate::services::lease::{Lease, LEASE_INTERVALS};
+use crate::services::outbox::sinks::OutboxSink;
+use crate::services::outbox::OutboxEvent;
+use crate::services::trade_changes::TradeChanges;
+
+const RELAY_NAME: &str = "default";
+
+// How far back the relay looks for entries on the first run.
+const INITIAL_LOOKBACK: TimeDelta = TimeDelta::days(7);
+
+// The time given to writers with a lagging clock to append entries to
+// the previous day bucket, before the relay stops reading it.
+const BUCKET_GRACE_PERIOD: TimeDelta = TimeDelta::hours(1);
+
+// Publishes the events stored in the outbox to the sink in the order they
+// were recorded. An event is removed only after the sink accepted it, so
+// that every event is delivered at least once. Only the replica holding
+// the lease runs it, so the events are published by a single instance.
+// Before publishing, it writes the follow-ups of trade changes, that their
+// requests failed to write, so that their events are published as well.
+pub struct OutboxRelay {
+    db: CassandraSession,
+    sink: Box<dyn OutboxSink>,
+    trade_changes: TradeChanges,
+}
+
+impl OutboxRelay {
+    pub fn new(db: CassandraSession, sink: Box<dyn OutboxSink>) -> Self {
+        Self {
+            trade_changes: TradeChanges::new(db.clone()),
+            db,
+            sink,
+        }
+    }
+
+    pub async fn run(self, interval: Duration) {
+        let lease = Lease::new(self.db.clone(), "outbox_relay", interval * LEASE_INTERVALS);
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match lease.acquire().await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(err) => {
+                    error!("Can't acquire the outbox relay lease: {0}", err);
+                    continue;
+                }
+            }
+
+            match self.trade_changes.recover().await {
+                Ok(0) => {}
+                Ok(recovered) => info!("Outbox relay recovered {0} trade changes", recovered),
+                Err(err) => error!("Can't recover the trade changes: {0}", err),
+            }
+
+            match self.relay(&lease).await {
+                Ok(0) => {}
+                Ok(published) => info!("Outbox relay published {0} events", published),
+                Err(err) => error!("Outbox relay failed: {0}", err),
+            }
+        }
+    }
+
+    // Stops at the first failed event, so that the next run starts from it
+    // and the order of events is kept. Stops as well once the lease is lost,
+    // so that the replica that took it over doesn't publish the same events.
+    pub async fn relay(&self, lease: &Lease) -> Result<usize> {
+        let now = Utc::now();
+        let mut state = self.get_state(now).await?;
+        let mut bucket = state.bucket();
+        let mut published = 0;
+
+        while bucket <= get_bucket(now) {
+            for entry in self.get_entries(bucket).await? {
+                if !lease.keep().await? {
+                    warn!("Outbox relay lost the lease after {0} events", published);
+                    return Ok(published);
+                }
+
+                let sequence = match entry.sequence() {
+                    Some(sequence) => sequence,
+                    None => self.assign_sequence(&mut state, &entry).await?,
+                };
+
+                self.sink
+                    .publish(&OutboxEvent::new(sequence, &entry))
+                    .await?;
+                self.remove(&entry).await?;
+                published += 1;
+            }
+
+            let next_bucket = bucket + TimeDelta::days(1);
+            if state.bucket() == bucket && next_bucket + BUCKET_GRACE_PERIOD <= now {
+                state.set_bucket(next_bucket);
+                self.save_state(&state).await?;
+            }
+            bucket = next_bucket;
+        }
+
+        Ok(published)
+    }
+
+    // The state is created on the first run. Only one of the concurrent
+    // runs creates it, the others read the created state.
+    async fn get_state(&self, now: DateTime<Utc>) -> Result<OutboxRelayState> {
+        let query = QueryBuilder::new(&OUTBOX_RELAY_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&OUTBOX_RELAY_ALL_COLUMNS)
+            .filter_by(Filter::new("name", Operator::Eq, Some(RELAY_NAME.into())))
+            .build();
+        if let Some(state) = query
+            .get_entries::<OutboxRelayState>(&self.db)
+            .await?
+            .into_iter()
+            .next()
+        {
+            return Ok(state);
+        }
+
+        let state = OutboxRelayState::new(RELAY_NAME, now - INITIAL_LOOKBACK);
+        let create_query = QueryBuilder::new(&OUTBOX_RELAY_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&OUTBOX_RELAY_ALL_COLUMNS)
+            .if_not_exists()
+            .build();
+        if create_query
+            .update_if(&self.db, &state.to_query_values())
+            .await?
+        {
+            return Ok(state);
+        }
+
+        query.get_instance::<OutboxRelayState>(&self.db).await
+    }
+
+    async fn get_entries(&self, bucket: DateTime<Utc>) -> Result<Vec<OutboxEntry>> {
+        let query = QueryBuilder::new(&OUTBOX_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&OUTBOX_ALL_COLUMNS)
+            .filter_by(Filter::new("bucket", Operator::Eq, Some(bucket.into())))
+            .build();
+
+        query.get_entries::<OutboxEntry>(&self.db).await
+    }
+
+    // Claims the next sequence number in the relay state first, so that
+    // the number is never given to different events, and stores it in
+    // the entry only when the entry has no number yet, so that the event
+    // doesn't get different numbers. Both updates are conditional, because
+    // the replica that lost the lease may still be running. The number
+    // claimed for the entry numbered by another run is skipped.
+    async fn assign_sequence(
+        &self,
+        state: &mut OutboxRelayState,
+        entry: &OutboxEntry,
+    ) -> Result<i64> {
+        let state_query = QueryBuilder::new(&OUTBOX_RELAY_TABLE)
+            .query_type(QueryType::Update)
+            .columns(&["sequence"])
+            .filter_by(Filter::new("name", Operator::Eq, Some(RELAY_NAME.into())))
+            .only_if(Filter::new(
+                "sequence",
+                Operator::Eq,
+                Some(state.sequence().into()),
+            ))
+            .build();
+        let sequence = state.sequence() + 1;
+        if !state_query
+            .update_if(&self.db, &query_values!("sequence" => sequence))
+            .await?
+        {
+            return Err(Error::Conflict(String::from(
+                "The outbox relay state was changed by another replica.",
+            )));
+        }
+        state.next_sequence();
+
+        let entry_query = QueryBuilder::new(&OUTBOX_TABLE)
+            .query_type(QueryType::Update)
+            .columns(&["sequence"])
+            .filter_by(Filter::new(
+                "bucket",
+                Operator::Eq,
+                Some(entry.bucket().into()),
+            ))
+            .filter_by(Filter::new(
+                "created_at",
+                Operator::Eq,
+                Some(entry.created_at().into()),
+            ))
+            .filter_by(Filter::new("id", Operator::Eq, Some(entry.id().into())))
+            // The kind is set for every entry, so the entry removed by another
+            // run isn't written back
+            .only_if(Filter::new("kind", Operator::Eq, Some(entry.kind().into())))
+            .only_if_null("sequence")
+            .build();
+        if !entry_query
+            .update_if(&self.db, &query_values!("sequence" => sequence))
+            .await?
+        {
+            return Err(Error::Conflict(format!(
+                "The outbox entry {0} was numbered by another replica.",
+                entry.id()
+            )));
+        }
+
+        Ok(sequence)
+    }
+
+    // Saves only the bucket, so that the sequence number claimed by another
+    // run isn't overwritten.
+    async fn save_state(&self, state: &OutboxRelayState) -> Result<()> {
+        let query = QueryBuilder::new(&OUTBOX_RELAY_TABLE)
+            .query_type(QueryType::Update)
+            .columns(&["bucket"])
+            .filter_by(Filter::new("name", Operator::Eq, Some(RELAY_NAME.into())))
+            .build();
+        Batch::new()
+            .add(&query, &query_values!("bucket" => state.bucket()))?
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn remove(&self, entry: &OutboxEntry) -> Result<()> {
+        let query = QueryBuilder::new(&OUTBOX_TABLE)
+            .query_type(QueryType::Delete)
+            .filter_by(Filter::new(
+                "bucket",
+                Operator::Eq,
+                Some(entry.bucket().into()),
+            ))
+            .filter_by(Filter::new(
+                "created_at",
+                Operator::Eq,
+                Some(entry.created_at().into()),
+            ))
+            .filter_by(Filter::new("id", Operator::Eq, Some(entry.id().into())))
+            .build();
+        query.delete(&self.db).await?;
+
+        Ok(())
+    }
+}
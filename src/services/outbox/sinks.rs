@@ -0,0 +1,237 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use hyper::client::HttpConnector;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Method, Request, Uri};
+
+use crate::core::error::{Error, Result};
+use crate::services::outbox::OutboxEvent;
+
+// The time given to the webhook to accept an event.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutboxSinkKind {
+    // Events are published only to the saved search alerts and the game
+    // server webhooks, if any.
+    None,
+    File,
+    Webhook,
+}
+
+impl FromStr for OutboxSinkKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "none" => Ok(OutboxSinkKind::None),
+            "file" => Ok(OutboxSinkKind::File),
+            "webhook" => Ok(OutboxSinkKind::Webhook),
+            _ => Err(format!(
+                "{0} is not a valid sink, use `none`, `file` or `webhook`",
+                value
+            )),
+        }
+    }
+}
+
+// The destination of published events. The same event can be published
+// more than once, so consumers must deduplicate them by the sequence number.
+#[tonic::async_trait]
+pub trait OutboxSink: Send + Sync {
+    async fn publish(&self, event: &OutboxEvent) -> Result<()>;
+}
+
+// Publishes every event to all sinks in order. When one of them fails,
+// the event is published again to all of them on the next run.
+pub struct FanOutSink {
+    sinks: Vec<Box<dyn OutboxSink>>,
+}
+
+impl FanOutSink {
+    pub fn new(sinks: Vec<Box<dyn OutboxSink>>) -> Self {
+        Self { sinks }
+    }
+}
+
+#[tonic::async_trait]
+impl OutboxSink for FanOutSink {
+    async fn publish(&self, event: &OutboxEvent) -> Result<()> {
+        for sink in &self.sinks {
+            sink.publish(event).await?;
+        }
+
+        Ok(())
+    }
+}
+
+// Appends events to the file, one JSON document per line.
+pub struct JsonLinesSink {
+    path: PathBuf,
+}
+
+impl JsonLinesSink {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: PathBuf::from(path),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl OutboxSink for JsonLinesSink {
+    async fn publish(&self, event: &OutboxEvent) -> Result<()> {
+        let line = serde_json::to_string(event).expect("serialize outbox event");
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{0}", line))
+            .map_err(|err| {
+                Error::DeliveryFailed(format!("Can't write to {0}: {1}", self.path.display(), err))
+            })
+    }
+}
+
+// Sends events as JSON in POST requests. Any response except 2xx is
+// treated as a failed delivery.
+pub struct WebhookSink {
+    client: Client<HttpConnector>,
+    url: Uri,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: Client::new(),
+            url: url.parse().expect("parse webhook url"),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl OutboxSink for WebhookSink {
+    async fn publish(&self, event: &OutboxEvent) -> Result<()> {
+        let body = serde_json::to_vec(event).expect("serialize outbox event");
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(self.url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .expect("build webhook request");
+
+        let response = tokio::time::timeout(WEBHOOK_TIMEOUT, self.client.request(request))
+            .await
+            .map_err(|_| Error::DeliveryFailed("The webhook timed out.".to_string()))?
+            .map_err(|err| {
+                Error::DeliveryFailed(format!("The webhook is unavailable: {0}", err))
+            })?;
+
+        if !response.status().is_success() {
+            return Err(Error::DeliveryFailed(format!(
+                "The webhook responded with {0}.",
+                response.status()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+
+    use chrono::Utc;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Response, Server, StatusCode};
+    use uuid::Uuid;
+
+    use crate::services::outbox::sinks::{FanOutSink, JsonLinesSink, OutboxSink, WebhookSink};
+    use crate::services::outbox::OutboxEvent;
+
+    fn create_event(sequence: i64) -> OutboxEvent {
+        OutboxEvent {
+            sequence,
+            id: Uuid::new_v4(),
+            kind: "trade.listed".to_string(),
+            aggregate_id: Uuid::new_v4(),
+            payload: serde_json::json!({"price": 100}),
+            created_at: Utc::now(),
+        }
+    }
+
+    fn start_webhook(status: StatusCode) -> SocketAddr {
+        let make_service = make_service_fn(move |_| async move {
+            Ok::<_, Infallible>(service_fn(move |_| async move {
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .status(status)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+            }))
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let address = server.local_addr();
+        tokio::spawn(server);
+        address
+    }
+
+    #[tokio::test]
+    async fn test_json_lines_sink_appends_events() {
+        let path = std::env::temp_dir().join(format!("outbox-{0}.jsonl", Uuid::new_v4()));
+        let sink = JsonLinesSink::new(path.to_str().unwrap());
+
+        sink.publish(&create_event(1)).await.unwrap();
+        sink.publish(&create_event(2)).await.unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let sequences = content
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["sequence"].clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sequences, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_sink_fails_when_any_sink_fails() {
+        let path = std::env::temp_dir().join(format!("outbox-{0}.jsonl", Uuid::new_v4()));
+        let address = start_webhook(StatusCode::SERVICE_UNAVAILABLE);
+        let sink = FanOutSink::new(vec![
+            Box::new(JsonLinesSink::new(path.to_str().unwrap())),
+            Box::new(WebhookSink::new(&format!("http://{0}/events", address))),
+        ]);
+
+        assert!(sink.publish(&create_event(1)).await.is_err());
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(content.lines().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_sink_accepts_success_response() {
+        let address = start_webhook(StatusCode::NO_CONTENT);
+        let sink = WebhookSink::new(&format!("http://{0}/events", address));
+
+        assert!(sink.publish(&create_event(1)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_sink_fails_on_error_response() {
+        let address = start_webhook(StatusCode::SERVICE_UNAVAILABLE);
+        let sink = WebhookSink::new(&format!("http://{0}/events", address));
+
+        assert!(sink.publish(&create_event(1)).await.is_err());
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use uuid::Uuid;
+
+use crate::core::error::Result;
+use crate::core::money::Money;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::sale_history::{
+    get_bucket, SaleHistoryEntry, SALE_HISTORY_ALL_COLUMNS, SALE_HISTORY_TABLE,
+};
+use crate::models::trade::{Trade, DEFAULT_CURRENCY};
+use crate::proto::Candle as CandleDetail;
+
+pub struct PriceHistory {
+    db: CassandraSession,
+}
+
+impl PriceHistory {
+    pub fn new(db: CassandraSession) -> Self {
+        Self { db }
+    }
+
+    // Prices in different currencies aren't comparable, so the history is kept
+    // in the default currency only. Bundles are excluded, as their price covers
+    // several items, and private trades, as their price is agreed with a single
+    // buyer.
+    pub async fn record_sale(&self, trade: &Trade, price: Money, sold_at: DateTime<Utc>) {
+        if trade.currency() != DEFAULT_CURRENCY || trade.is_bundle() || trade.is_private() {
+            return;
+        }
+
+        let entry = SaleHistoryEntry::new(
+            trade.realm_id(),
+            trade.item_id(),
+            trade.id(),
+            price,
+            trade.quantity(),
+            sold_at,
+        );
+        let query = QueryBuilder::new(&SALE_HISTORY_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&SALE_HISTORY_ALL_COLUMNS)
+            .build();
+        query.insert(&self.db, &entry.into_query_values()).await;
+    }
+
+    // Returns sales of the item in the realm within the [from, to) period,
+    // ordered by time.
+    pub async fn get_sales(
+        &self,
+        realm_id: &str,
+        item_id: Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<SaleHistoryEntry>> {
+        let mut sales = vec![];
+        let mut bucket = get_bucket(from);
+
+        while bucket < to {
+            let query = QueryBuilder::new(&SALE_HISTORY_TABLE)
+                .query_type(QueryType::Select)
+                .columns(&SALE_HISTORY_ALL_COLUMNS)
+                .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+                .filter_by(Filter::new("item_id", Operator::Eq, Some(item_id.into())))
+                .filter_by(Filter::new("bucket", Operator::Eq, Some(bucket.into())))
+                .build();
+            let entries = query.get_entries::<SaleHistoryEntry>(&self.db).await?;
+
+            sales.extend(
+                entries
+                    .into_iter()
+                    .filter(|entry| entry.sold_at() >= from && entry.sold_at() < to),
+            );
+            bucket += TimeDelta::days(1);
+        }
+
+        Ok(sales)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Candle {
+    open_time: i64,
+    open: Money,
+    high: Money,
+    low: Money,
+    close: Money,
+    volume: i64,
+}
+
+// Groups sales into candles of the `interval` seconds, aligned to the `from`
+// timestamp. Intervals without sales are omitted.
+pub fn build_candles(sales: &[SaleHistoryEntry], from: i64, interval: i64) -> Vec<Candle> {
+    let mut sales = sales.iter().collect::<Vec<&SaleHistoryEntry>>();
+    sales.sort_by_key(|sale| sale.sold_at());
+
+    let mut candles: BTreeMap<i64, Candle> = BTreeMap::new();
+    for sale in sales {
+        let index = (sale.sold_at().timestamp() - from) / interval;
+        let price = sale.unit_price();
+        let quantity = i64::from(sale.quantity());
+
+        candles
+            .entry(index)
+            .and_modify(|candle| {
+                candle.high = candle.high.max(price);
+                candle.low = candle.low.min(price);
+                candle.close = price;
+                candle.volume += quantity;
+            })
+            .or_insert(Candle {
+                open_time: from + index * interval,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume: quantity,
+            });
+    }
+
+    candles.into_values().collect()
+}
+
+pub fn median(mut prices: Vec<Money>) -> Option<Money> {
+    if prices.is_empty() {
+        return None;
+    }
+
+    prices.sort_unstable();
+    let middle = prices.len() / 2;
+    match prices.len() % 2 {
+        0 => {
+            let (low, high) = (prices[middle - 1], prices[middle]);
+            high.checked_sub(low)
+                .and_then(|spread| spread.checked_div(2))
+                .and_then(|half| low.checked_add(half))
+                .ok()
+        }
+        _ => Some(prices[middle]),
+    }
+}
+
+impl From<&Candle> for CandleDetail {
+    fn from(instance: &Candle) -> Self {
+        Self {
+            open_time: instance.open_time,
+            open: instance.open.into(),
+            high: instance.high.into(),
+            low: instance.low.into(),
+            close: instance.close.into(),
+            volume: instance.volume,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use uuid::Uuid;
+
+    use crate::core::money::Money;
+    use crate::core::realm::DEFAULT_REALM;
+    use crate::models::sale_history::SaleHistoryEntry;
+    use crate::services::price_history::{build_candles, median, Candle};
+
+    fn create_sale(timestamp: i64, price: i64) -> SaleHistoryEntry {
+        SaleHistoryEntry::new(
+            DEFAULT_REALM,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Money::new(price),
+            1,
+            DateTime::from_timestamp(timestamp, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_build_candles_aggregates_sales_within_interval() {
+        let sales = vec![
+            create_sale(1_000, 100),
+            create_sale(1_030, 150),
+            create_sale(1_010, 80),
+            create_sale(1_050, 120),
+        ];
+
+        assert_eq!(
+            build_candles(&sales, 1_000, 60),
+            vec![Candle {
+                open_time: 1_000,
+                open: Money::new(100),
+                high: Money::new(150),
+                low: Money::new(80),
+                close: Money::new(120),
+                volume: 4,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_build_candles_skips_intervals_without_sales() {
+        let sales = vec![create_sale(1_000, 100), create_sale(1_200, 200)];
+
+        assert_eq!(
+            build_candles(&sales, 1_000, 60),
+            vec![
+                Candle {
+                    open_time: 1_000,
+                    open: Money::new(100),
+                    high: Money::new(100),
+                    low: Money::new(100),
+                    close: Money::new(100),
+                    volume: 1,
+                },
+                Candle {
+                    open_time: 1_180,
+                    open: Money::new(200),
+                    high: Money::new(200),
+                    low: Money::new(200),
+                    close: Money::new(200),
+                    volume: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_candles_uses_unit_prices() {
+        let sale = SaleHistoryEntry::new(
+            DEFAULT_REALM,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Money::new(500),
+            5,
+            DateTime::from_timestamp(1_000, 0).unwrap(),
+        );
+
+        assert_eq!(
+            build_candles(&[sale], 1_000, 60),
+            vec![Candle {
+                open_time: 1_000,
+                open: Money::new(100),
+                high: Money::new(100),
+                low: Money::new(100),
+                close: Money::new(100),
+                volume: 5,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_build_candles_without_sales() {
+        assert!(build_candles(&[], 1_000, 60).is_empty());
+    }
+
+    #[test]
+    fn test_median() {
+        let prices = |prices: &[i64]| prices.iter().copied().map(Money::new).collect();
+
+        assert_eq!(median(vec![]), None);
+        assert_eq!(median(prices(&[300, 100, 200])), Some(Money::new(200)));
+        assert_eq!(median(prices(&[400, 100, 200, 300])), Some(Money::new(250)));
+        assert_eq!(
+            median(prices(&[i64::MAX, i64::MAX - 2])),
+            Some(Money::new(i64::MAX - 1))
+        );
+    }
+}
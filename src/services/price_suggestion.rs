@@ -0,0 +1,216 @@
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use uuid::Uuid;
+
+use crate::core::cache::TtlCache;
+use crate::core::error::Result;
+use crate::core::money::Money;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::trade::{Trade, TRADE_ALL_COLUMNS, TRADE_TABLE};
+use crate::proto::SuggestPriceResponse;
+use crate::services::price_history::{median, PriceHistory};
+
+// How long the market data of the item is reused between suggestions.
+const MARKET_SNAPSHOT_TTL: Duration = Duration::from_secs(30);
+// How many days of sales are considered recent.
+const RECENT_SALES_DAYS: i64 = 7;
+// The recommended bid price, as a percent of the recommended buyout price.
+const BID_PRICE_PERCENT: i64 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarketSnapshot {
+    lowest_unit_price: Option<Money>,
+    median_unit_price: Option<Money>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PriceSuggestion {
+    lowest_unit_price: Option<Money>,
+    median_unit_price: Option<Money>,
+    bid_price: Option<Money>,
+    buyout_price: Option<Money>,
+}
+
+// Undercuts the cheapest active trade, but never goes above the median
+// price of the recent sales, so that the item sells quickly.
+pub fn suggest(snapshot: &MarketSnapshot, quantity: i64) -> PriceSuggestion {
+    let undercut_price = snapshot.lowest_unit_price.map(|unit_price| {
+        unit_price
+            .checked_sub(Money::ONE)
+            .map_or(Money::ONE, |price| price.max(Money::ONE))
+    });
+    let unit_price = match (undercut_price, snapshot.median_unit_price) {
+        (Some(undercut_price), Some(median_price)) => Some(undercut_price.min(median_price)),
+        (undercut_price, median_price) => undercut_price.or(median_price),
+    };
+    // Nothing is suggested when the price of the whole stack doesn't fit
+    let buyout_price =
+        unit_price.and_then(|unit_price| unit_price.max(Money::ONE).checked_mul(quantity).ok());
+    let bid_price = buyout_price
+        .and_then(|buyout_price| buyout_price.percent(BID_PRICE_PERCENT).ok())
+        .map(|bid_price| bid_price.max(Money::ONE));
+
+    PriceSuggestion {
+        lowest_unit_price: snapshot.lowest_unit_price,
+        median_unit_price: snapshot.median_unit_price,
+        bid_price,
+        buyout_price,
+    }
+}
+
+pub struct PriceAdvisor {
+    db: CassandraSession,
+    price_history: PriceHistory,
+    snapshots: TtlCache<(String, Uuid), MarketSnapshot>,
+}
+
+impl PriceAdvisor {
+    pub fn new(db: CassandraSession) -> Self {
+        Self {
+            price_history: PriceHistory::new(db.clone()),
+            db,
+            snapshots: TtlCache::new(MARKET_SNAPSHOT_TTL),
+        }
+    }
+
+    pub async fn suggest(
+        &self,
+        realm_id: &str,
+        item_id: Uuid,
+        quantity: i64,
+    ) -> Result<PriceSuggestion> {
+        let key = (realm_id.to_string(), item_id);
+        let snapshot = match self.snapshots.get(&key) {
+            Some(snapshot) => snapshot,
+            None => {
+                let snapshot = self.load_snapshot(realm_id, item_id).await?;
+                self.snapshots.insert(key, snapshot);
+                snapshot
+            }
+        };
+
+        Ok(suggest(&snapshot, quantity))
+    }
+
+    async fn load_snapshot(&self, realm_id: &str, item_id: Uuid) -> Result<MarketSnapshot> {
+        let query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+            .filter_by(Filter::new("item_id", Operator::Eq, Some(item_id.into())))
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
+            .allow_filtering(true)
+            .build();
+        let lowest_unit_price = query
+            .get_entries::<Trade>(&self.db)
+            .await?
+            .iter()
+            .filter(|trade| {
+                !trade.is_expired()
+                    && !trade.is_bundle()
+                    && !trade.is_barter()
+                    && !trade.is_private()
+            })
+            .map(|trade| trade.unit_price())
+            .min();
+
+        let now = Utc::now();
+        let sales = self
+            .price_history
+            .get_sales(
+                realm_id,
+                item_id,
+                now - TimeDelta::days(RECENT_SALES_DAYS),
+                now,
+            )
+            .await?;
+        let median_unit_price = median(sales.iter().map(|sale| sale.unit_price()).collect());
+
+        Ok(MarketSnapshot {
+            lowest_unit_price,
+            median_unit_price,
+        })
+    }
+}
+
+impl From<&PriceSuggestion> for SuggestPriceResponse {
+    fn from(instance: &PriceSuggestion) -> Self {
+        Self {
+            lowest_unit_price: instance.lowest_unit_price.map(i64::from),
+            median_unit_price: instance.median_unit_price.map(i64::from),
+            bid_price: instance.bid_price.map(i64::from),
+            buyout_price: instance.buyout_price.map(i64::from),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::money::Money;
+    use crate::services::price_suggestion::{suggest, MarketSnapshot, PriceSuggestion};
+
+    #[test]
+    fn test_suggest_undercuts_lowest_price() {
+        let snapshot = MarketSnapshot {
+            lowest_unit_price: Some(Money::new(100)),
+            median_unit_price: Some(Money::new(120)),
+        };
+
+        assert_eq!(
+            suggest(&snapshot, 10),
+            PriceSuggestion {
+                lowest_unit_price: Some(Money::new(100)),
+                median_unit_price: Some(Money::new(120)),
+                bid_price: Some(Money::new(792)),
+                buyout_price: Some(Money::new(990)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_suggest_caps_price_by_median() {
+        let snapshot = MarketSnapshot {
+            lowest_unit_price: Some(Money::new(1000)),
+            median_unit_price: Some(Money::new(100)),
+        };
+
+        assert_eq!(suggest(&snapshot, 1).buyout_price, Some(Money::new(100)));
+    }
+
+    #[test]
+    fn test_suggest_uses_median_without_active_trades() {
+        let snapshot = MarketSnapshot {
+            lowest_unit_price: None,
+            median_unit_price: Some(Money::new(50)),
+        };
+
+        assert_eq!(suggest(&snapshot, 2).buyout_price, Some(Money::new(100)));
+    }
+
+    #[test]
+    fn test_suggest_without_market_data() {
+        let snapshot = MarketSnapshot {
+            lowest_unit_price: None,
+            median_unit_price: None,
+        };
+
+        let suggestion = suggest(&snapshot, 1);
+        assert_eq!(suggestion.bid_price, None);
+        assert_eq!(suggestion.buyout_price, None);
+    }
+
+    #[test]
+    fn test_suggest_skips_prices_out_of_range() {
+        let snapshot = MarketSnapshot {
+            lowest_unit_price: None,
+            median_unit_price: Some(Money::new(i64::MAX / 2)),
+        };
+
+        let suggestion = suggest(&snapshot, 3);
+        assert_eq!(suggestion.bid_price, None);
+        assert_eq!(suggestion.buyout_price, None);
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+// An in-process sliding window limiter, that allows up to `limit` accepted
+// actions per player within the `window` duration.
+pub struct RateLimiter {
+    limit: usize,
+    window: Duration,
+    state: Mutex<LimiterState>,
+}
+
+struct LimiterState {
+    hits: HashMap<Uuid, VecDeque<Instant>>,
+    // Players without hits in the current window are dropped at most once
+    // per window, so that the limiter doesn't grow with every player seen.
+    pruned_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            state: Mutex::new(LimiterState {
+                hits: HashMap::new(),
+                pruned_at: Instant::now(),
+            }),
+        }
+    }
+
+    // Counts the action of the player in advance, or returns the duration
+    // after which the next action will be accepted, if the player already
+    // reached the limit. The check and the count happen at once, so that
+    // concurrent actions can't pass the same check.
+    pub fn reserve(&self, player_id: Uuid) -> Result<Reservation<'_>, Duration> {
+        self.reserve_at(player_id, Instant::now())
+    }
+
+    fn reserve_at(&self, player_id: Uuid, now: Instant) -> Result<Reservation<'_>, Duration> {
+        if self.limit == 0 {
+            return Ok(Reservation {
+                limiter: self,
+                player_id,
+                hit: None,
+            });
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if now.duration_since(state.pruned_at) >= self.window {
+            state.hits.retain(|_, player_hits| {
+                player_hits
+                    .back()
+                    .map(|last_hit| now.duration_since(*last_hit) < self.window)
+                    .unwrap_or(false)
+            });
+            state.pruned_at = now;
+        }
+
+        let player_hits = state.hits.entry(player_id).or_default();
+        self.drop_expired_hits(player_hits, now);
+        if player_hits.len() >= self.limit {
+            let first_hit = player_hits.front().unwrap();
+            return Err(self.window - now.duration_since(*first_hit));
+        }
+        player_hits.push_back(now);
+
+        Ok(Reservation {
+            limiter: self,
+            player_id,
+            hit: Some(now),
+        })
+    }
+
+    fn release(&self, player_id: Uuid, hit: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(player_hits) = state.hits.get_mut(&player_id) {
+            if let Some(position) = player_hits.iter().position(|other| *other == hit) {
+                player_hits.remove(position);
+            }
+        }
+    }
+
+    fn drop_expired_hits(&self, player_hits: &mut VecDeque<Instant>, now: Instant) {
+        while let Some(first_hit) = player_hits.front() {
+            match now.duration_since(*first_hit) >= self.window {
+                true => player_hits.pop_front(),
+                false => break,
+            };
+        }
+    }
+}
+
+// The action counted by the limiter before it's done. The action is given
+// back when the reservation is dropped without being confirmed, e.g. when
+// the request fails after the check, so that only accepted actions count.
+pub struct Reservation<'a> {
+    limiter: &'a RateLimiter,
+    player_id: Uuid,
+    hit: Option<Instant>,
+}
+
+impl Reservation<'_> {
+    pub fn confirm(mut self) {
+        self.hit = None;
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if let Some(hit) = self.hit.take() {
+            self.limiter.release(self.player_id, hit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use uuid::Uuid;
+
+    use crate::services::rate_limiter::RateLimiter;
+
+    #[test]
+    fn test_hits_within_limit_are_accepted() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let player_id = Uuid::new_v4();
+        let now = Instant::now();
+
+        limiter.reserve_at(player_id, now).unwrap().confirm();
+        assert!(limiter.reserve_at(player_id, now).is_ok());
+    }
+
+    #[test]
+    fn test_hit_over_limit_returns_retry_after() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let player_id = Uuid::new_v4();
+        let now = Instant::now();
+
+        limiter.reserve_at(player_id, now).unwrap().confirm();
+        limiter
+            .reserve_at(player_id, now + Duration::from_secs(10))
+            .unwrap()
+            .confirm();
+
+        assert_eq!(
+            limiter
+                .reserve_at(player_id, now + Duration::from_secs(20))
+                .err(),
+            Some(Duration::from_secs(40))
+        );
+    }
+
+    #[test]
+    fn test_hits_are_accepted_after_window_passed() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+        let player_id = Uuid::new_v4();
+        let now = Instant::now();
+
+        limiter.reserve_at(player_id, now).unwrap().confirm();
+
+        assert!(limiter
+            .reserve_at(player_id, now + Duration::from_secs(60))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_players_are_limited_independently() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+        let now = Instant::now();
+
+        limiter.reserve_at(Uuid::new_v4(), now).unwrap().confirm();
+
+        assert!(limiter.reserve_at(Uuid::new_v4(), now).is_ok());
+    }
+
+    #[test]
+    fn test_zero_limit_disables_limiter() {
+        let limiter = RateLimiter::new(0, Duration::from_secs(60));
+        let player_id = Uuid::new_v4();
+        let now = Instant::now();
+
+        for _ in 0..100 {
+            limiter.reserve_at(player_id, now).unwrap().confirm();
+        }
+    }
+
+    #[test]
+    fn test_rejected_actions_are_not_counted() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+        let player_id = Uuid::new_v4();
+        let now = Instant::now();
+
+        // The reservations are dropped without being confirmed
+        for _ in 0..10 {
+            assert!(limiter.reserve_at(player_id, now).is_ok());
+        }
+        limiter.reserve_at(player_id, now).unwrap().confirm();
+
+        assert!(limiter.reserve_at(player_id, now).is_err());
+    }
+
+    #[test]
+    fn test_concurrent_actions_count_before_confirmed() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+        let player_id = Uuid::new_v4();
+        let now = Instant::now();
+
+        let reservation = limiter.reserve_at(player_id, now).unwrap();
+        assert!(limiter.reserve_at(player_id, now).is_err());
+
+        drop(reservation);
+        assert!(limiter.reserve_at(player_id, now).is_ok());
+    }
+
+    #[test]
+    fn test_idle_players_are_pruned_once_per_window() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+        let now = Instant::now();
+
+        let record_at = |now: Instant| {
+            limiter.reserve_at(Uuid::new_v4(), now).unwrap().confirm();
+        };
+
+        record_at(now);
+        record_at(now + Duration::from_secs(30));
+        assert_eq!(limiter.state.lock().unwrap().hits.len(), 2);
+
+        record_at(now + Duration::from_secs(80));
+        assert_eq!(limiter.state.lock().unwrap().hits.len(), 2);
+        record_at(now + Duration::from_secs(100));
+        assert_eq!(limiter.state.lock().unwrap().hits.len(), 3);
+    }
+}
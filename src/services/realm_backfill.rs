@@ -0,0 +1,102 @@
+use cdrs_tokio::frame::TryFromRow;
+use cdrs_tokio::query::QueryValues;
+use log::info;
+use serde::Serialize;
+
+use crate::core::error::Result;
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::core::realm::DEFAULT_REALM;
+use crate::models::legacy::{
+    LegacyMarketSummary, LegacySaleHistoryEntry, LegacyTrade, LEGACY_MARKET_SUMMARY_ALL_COLUMNS,
+    LEGACY_MARKET_SUMMARY_TABLE, LEGACY_SALE_HISTORY_ALL_COLUMNS, LEGACY_SALE_HISTORY_TABLE,
+    LEGACY_TRADE_ALL_COLUMNS, LEGACY_TRADE_TABLE,
+};
+use crate::models::market_summary::MARKET_SUMMARY_TABLE;
+use crate::models::sale_history::SALE_HISTORY_TABLE;
+use crate::models::trade::TRADE_TABLE;
+
+// Copies the rows stored before realms were introduced into the realm
+// tables of the default realm. The rows already present in the realm
+// tables are kept, so the backfill can be repeated after a failure.
+pub struct RealmBackfill {
+    db: CassandraSession,
+}
+
+impl RealmBackfill {
+    pub fn new(db: CassandraSession) -> Self {
+        Self { db }
+    }
+
+    // Returns the amount of copied rows.
+    pub async fn run(&self) -> Result<usize> {
+        let trades = self
+            .copy(
+                &LEGACY_TRADE_TABLE,
+                &TRADE_TABLE,
+                &LEGACY_TRADE_ALL_COLUMNS,
+                LegacyTrade::into_query_values,
+            )
+            .await?;
+        info!("{0} trades copied into the default realm", trades);
+
+        let sales = self
+            .copy(
+                &LEGACY_SALE_HISTORY_TABLE,
+                &SALE_HISTORY_TABLE,
+                &LEGACY_SALE_HISTORY_ALL_COLUMNS,
+                LegacySaleHistoryEntry::into_query_values,
+            )
+            .await?;
+        info!("{0} sales copied into the default realm", sales);
+
+        let summaries = self
+            .copy(
+                &LEGACY_MARKET_SUMMARY_TABLE,
+                &MARKET_SUMMARY_TABLE,
+                &LEGACY_MARKET_SUMMARY_ALL_COLUMNS,
+                LegacyMarketSummary::into_query_values,
+            )
+            .await?;
+        info!(
+            "{0} market summaries copied into the default realm",
+            summaries
+        );
+
+        Ok(trades + sales + summaries)
+    }
+
+    async fn copy<T>(
+        &self,
+        legacy_table: &str,
+        realm_table: &str,
+        columns: &[&str],
+        into_query_values: fn(T, &str) -> QueryValues,
+    ) -> Result<usize>
+    where
+        T: Serialize + TryFromRow,
+    {
+        let select_query = QueryBuilder::new(legacy_table)
+            .query_type(QueryType::Select)
+            .columns(columns)
+            .build();
+        let realm_columns = [&["realm_id"], columns].concat();
+        let insert_query = QueryBuilder::new(realm_table)
+            .query_type(QueryType::Insert)
+            .columns(&realm_columns)
+            .if_not_exists()
+            .build();
+
+        let mut copied = 0;
+        for row in select_query.get_entries::<T>(&self.db).await? {
+            if insert_query
+                .update_if(&self.db, &into_query_values(row, DEFAULT_REALM))
+                .await?
+            {
+                copied += 1;
+            }
+        }
+
+        Ok(copied)
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::core::cache::TtlCache;
+use crate::core::error::Result;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::realm_visibility::{
+    RealmLink, REALM_VISIBILITY_ALL_COLUMNS, REALM_VISIBILITY_TABLE,
+};
+
+// How long the visible realms are kept in memory. Changes made via other
+// instances of the service are picked up after this delay.
+const REALM_VISIBILITY_TTL: Duration = Duration::from_secs(10);
+
+// Defines which realms see and accept bids on listings of other realms.
+// Cloned into the auction and admin services, so that they share the cache
+// and changes made via the admin API are applied immediately.
+#[derive(Clone)]
+pub struct RealmVisibility {
+    db: CassandraSession,
+    visible_realms: Arc<TtlCache<String, Vec<String>>>,
+}
+
+impl RealmVisibility {
+    pub fn new(db: CassandraSession) -> Self {
+        Self {
+            db,
+            visible_realms: Arc::new(TtlCache::new(REALM_VISIBILITY_TTL)),
+        }
+    }
+
+    // Returns the realms with listings visible to players of the realm,
+    // starting from the realm itself.
+    pub async fn get_visible_realms(&self, realm_id: &str) -> Result<Vec<String>> {
+        if let Some(realms) = self.visible_realms.get(&realm_id.to_string()) {
+            return Ok(realms);
+        }
+
+        let links = self.get_links(realm_id).await?;
+        let realms: Vec<String> = std::iter::once(realm_id.to_string())
+            .chain(links.iter().map(|link| link.visible_realm_id().to_string()))
+            .collect();
+        self.visible_realms
+            .insert(realm_id.to_string(), realms.clone());
+
+        Ok(realms)
+    }
+
+    // Replaces the realms visible to players of the realm. Returns the new
+    // visible realms, starting from the realm itself.
+    pub async fn set_visible_realms(
+        &self,
+        realm_id: &str,
+        visible_realms: &[String],
+    ) -> Result<Vec<String>> {
+        let links = self.get_links(realm_id).await?;
+
+        for link in links.iter().filter(|link| {
+            !visible_realms
+                .iter()
+                .any(|realm| realm == link.visible_realm_id())
+        }) {
+            let query = QueryBuilder::new(&REALM_VISIBILITY_TABLE)
+                .query_type(QueryType::Delete)
+                .filter_by(Filter::new(
+                    "visible_realm_id",
+                    Operator::Eq,
+                    Some(link.visible_realm_id().into()),
+                ))
+                .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+                .build();
+            query.delete(&self.db).await?;
+        }
+
+        let query = QueryBuilder::new(&REALM_VISIBILITY_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&REALM_VISIBILITY_ALL_COLUMNS)
+            .build();
+        for visible_realm_id in visible_realms.iter().filter(|realm| {
+            *realm != realm_id && !links.iter().any(|link| link.visible_realm_id() == *realm)
+        }) {
+            let query_values = RealmLink::new(realm_id, visible_realm_id).into_query_values();
+            query.insert(&self.db, &query_values).await;
+        }
+
+        self.visible_realms.remove(&realm_id.to_string());
+        self.get_visible_realms(realm_id).await
+    }
+
+    async fn get_links(&self, realm_id: &str) -> Result<Vec<RealmLink>> {
+        let query = QueryBuilder::new(&REALM_VISIBILITY_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&REALM_VISIBILITY_ALL_COLUMNS)
+            .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+            .build();
+
+        query.get_entries::<RealmLink>(&self.db).await
+    }
+}
@@ -0,0 +1,254 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::error;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::core::cache::TtlCache;
+use crate::core::error::{Error, Result};
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::notification::{Notification, NotificationKind};
+use crate::models::saved_search::{SavedSearch, SAVED_SEARCH_ALL_COLUMNS, SAVED_SEARCH_TABLE};
+use crate::models::trade::{Trade, TradeStatus, TRADE_ALL_COLUMNS, TRADE_TABLE};
+use crate::services::notifications::Notifications;
+use crate::services::outbox::sinks::OutboxSink;
+use crate::services::outbox::OutboxEvent;
+
+// The maximum amount of saved searches per player.
+const MAX_SAVED_SEARCHES: usize = 20;
+// How long all saved searches are kept in memory for matching new trades.
+// Changes made to saved searches are picked up after this delay.
+const SAVED_SEARCHES_TTL: Duration = Duration::from_secs(30);
+
+pub struct SavedSearches {
+    db: CassandraSession,
+    snapshot: TtlCache<(), Arc<Vec<SavedSearch>>>,
+}
+
+impl SavedSearches {
+    pub fn new(db: CassandraSession) -> Self {
+        Self {
+            db,
+            snapshot: TtlCache::new(SAVED_SEARCHES_TTL),
+        }
+    }
+
+    pub async fn create(&self, saved_search: SavedSearch) -> Result<()> {
+        let saved_searches = self.get_player_searches(saved_search.player_id()).await?;
+        if saved_searches.len() >= MAX_SAVED_SEARCHES {
+            return Err(Error::LimitExceeded {
+                subject: "saved_searches".to_string(),
+                message: format!(
+                    "The player can't have more than {0} saved searches.",
+                    MAX_SAVED_SEARCHES
+                ),
+                retry_after: None,
+            });
+        }
+
+        self.save(saved_search).await
+    }
+
+    pub async fn save(&self, saved_search: SavedSearch) -> Result<()> {
+        let query = QueryBuilder::new(&SAVED_SEARCH_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&SAVED_SEARCH_ALL_COLUMNS)
+            .build();
+        let query_values = saved_search.into_query_values();
+        query.try_insert(&self.db, &query_values).await
+    }
+
+    pub async fn delete(&self, player_id: Uuid, id: Uuid) -> Result<()> {
+        let query = QueryBuilder::new(&SAVED_SEARCH_TABLE)
+            .query_type(QueryType::Delete)
+            .filter_by(Filter::new("id", Operator::Eq, Some(id.into())))
+            .filter_by(Filter::new(
+                "player_id",
+                Operator::Eq,
+                Some(player_id.into()),
+            ))
+            .build();
+        query.delete(&self.db).await?;
+
+        Ok(())
+    }
+
+    pub async fn get(&self, player_id: Uuid, id: Uuid) -> Result<SavedSearch> {
+        let query = QueryBuilder::new(&SAVED_SEARCH_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&SAVED_SEARCH_ALL_COLUMNS)
+            .filter_by(Filter::new("id", Operator::Eq, Some(id.into())))
+            .filter_by(Filter::new(
+                "player_id",
+                Operator::Eq,
+                Some(player_id.into()),
+            ))
+            .limit(1)
+            .build();
+
+        query.get_instance::<SavedSearch>(&self.db).await
+    }
+
+    pub async fn get_player_searches(&self, player_id: Uuid) -> Result<Vec<SavedSearch>> {
+        let query = QueryBuilder::new(&SAVED_SEARCH_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&SAVED_SEARCH_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "player_id",
+                Operator::Eq,
+                Some(player_id.into()),
+            ))
+            .build();
+
+        query.get_entries::<SavedSearch>(&self.db).await
+    }
+
+    // Returns notifications for the owners of saved searches matching
+    // the new trade. The seller isn't notified about own trades and nobody
+    // is notified about trades reserved for another player.
+    pub async fn find_matches(&self, trade: &Trade) -> Result<Vec<Notification>> {
+        if trade.reserved_for().is_some() {
+            return Ok(vec![]);
+        }
+
+        let saved_searches = self.get_all_searches().await?;
+
+        Ok(saved_searches
+            .iter()
+            .filter(|saved_search| saved_search.player_id() != trade.created_by())
+            .filter(|saved_search| saved_search.matches(trade))
+            .map(|saved_search| {
+                Notification::new(
+                    saved_search.player_id(),
+                    NotificationKind::SavedSearchMatch,
+                    trade.id(),
+                    format!(
+                        "{0} matches your saved search \"{1}\".",
+                        trade.item_name(),
+                        saved_search.name()
+                    ),
+                )
+            })
+            .collect())
+    }
+
+    async fn get_all_searches(&self) -> Result<Arc<Vec<SavedSearch>>> {
+        if let Some(saved_searches) = self.snapshot.get(&()) {
+            return Ok(saved_searches);
+        }
+
+        let query = QueryBuilder::new(&SAVED_SEARCH_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&SAVED_SEARCH_ALL_COLUMNS)
+            .build();
+        let saved_searches = Arc::new(query.get_entries::<SavedSearch>(&self.db).await?);
+        self.snapshot.insert((), saved_searches.clone());
+
+        Ok(saved_searches)
+    }
+}
+
+// The part of the trade event, that identifies the listed trade.
+#[derive(Deserialize)]
+struct ListedTrade {
+    realm_id: String,
+    trade_id: Uuid,
+}
+
+// Notifies the owners of saved searches about the listed trades. Runs in
+// the outbox relay, so that listing a trade doesn't wait for the matching.
+// Trades listed by the scheduler are announced with the same event.
+pub struct SavedSearchAlerts {
+    db: CassandraSession,
+    saved_searches: SavedSearches,
+    notifications: Notifications,
+}
+
+impl SavedSearchAlerts {
+    pub fn new(db: CassandraSession) -> Self {
+        Self {
+            saved_searches: SavedSearches::new(db.clone()),
+            notifications: Notifications::new(db.clone()),
+            db,
+        }
+    }
+
+    // Returns the trade while it's still on sale.
+    async fn get_listed_trade(&self, listed_trade: &ListedTrade) -> Result<Option<Trade>> {
+        let query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::Eq,
+                Some(listed_trade.realm_id.as_str().into()),
+            ))
+            .filter_by(Filter::new(
+                "id",
+                Operator::Eq,
+                Some(listed_trade.trade_id.into()),
+            ))
+            .allow_filtering(true)
+            .build();
+
+        Ok(query
+            .get_entries::<Trade>(&self.db)
+            .await?
+            .into_iter()
+            .find(|trade| trade.current_status() == TradeStatus::Active))
+    }
+}
+
+#[tonic::async_trait]
+impl OutboxSink for SavedSearchAlerts {
+    async fn publish(&self, event: &OutboxEvent) -> Result<()> {
+        if event.kind != "trade.listed" {
+            return Ok(());
+        }
+
+        let listed_trade = match ListedTrade::deserialize(&event.payload) {
+            Ok(listed_trade) => listed_trade,
+            Err(err) => {
+                // Retrying can't fix the payload, so the event is skipped
+                error!(
+                    "Can't read the listed trade of the event {0}: {1}",
+                    event.id, err
+                );
+                return Ok(());
+            }
+        };
+        let Some(trade) = self.get_listed_trade(&listed_trade).await? else {
+            return Ok(());
+        };
+
+        for notification in self.saved_searches.find_matches(&trade).await? {
+            let notification = notification.with_origin(event.id, event.created_at);
+            self.notifications.send(notification).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::models::trade::Trade;
+    use crate::services::saved_searches::ListedTrade;
+    use crate::services::trade_events::{TradeEvent, TradeEventKind};
+
+    #[test]
+    fn test_listed_trade_is_read_from_event_payload() {
+        let trade = Trade::builder().build().with_realm("eu-1");
+        let event = TradeEvent::new(TradeEventKind::Listed, &trade);
+        let payload = serde_json::to_value(&event).unwrap();
+
+        let listed_trade = ListedTrade::deserialize(&payload).unwrap();
+        assert_eq!(listed_trade.realm_id, "eu-1");
+        assert_eq!(listed_trade.trade_id, trade.id());
+    }
+}
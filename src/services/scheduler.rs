@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use cdrs_tokio::query_values;
+use log::{error, info};
+
+use crate::core::error::Result;
+use crate::core::orm::batch::Batch;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::trade::{Trade, TradeStatus, TRADE_ALL_COLUMNS, TRADE_TABLE};
+use crate::services::lease::{Lease, LEASE_INTERVALS};
+use crate::services::market_summary::MarketSummaries;
+use crate::services::outbox::record_event;
+use crate::services::trade_events::{TradeEvent, TradeEventBus, TradeEventKind};
+use crate::services::trade_updates::get_trade_update;
+
+// Puts the scheduled trades on sale once their start time passes. Players
+// see the trades as active right after the start time, the scheduler only
+// stores the status and announces the trades. The scan reads every scheduled
+// trade, so only a single replica runs it at a time.
+pub struct ListingScheduler {
+    db: CassandraSession,
+    events: TradeEventBus,
+    market_summaries: MarketSummaries,
+}
+
+impl ListingScheduler {
+    pub fn new(db: CassandraSession, events: TradeEventBus) -> Self {
+        Self {
+            market_summaries: MarketSummaries::new(db.clone()),
+            db,
+            events,
+        }
+    }
+
+    pub async fn run(self, interval: Duration) {
+        let lease = Lease::new(self.db.clone(), "scheduler", interval * LEASE_INTERVALS);
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match lease.acquire().await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(err) => {
+                    error!("Can't acquire the scheduler lease: {0}", err);
+                    continue;
+                }
+            }
+
+            match self.scan().await {
+                Ok(0) => {}
+                Ok(started) => info!("Schedule scan finished, {0} trades started", started),
+                Err(err) => error!("Schedule scan failed: {0}", err),
+            }
+        }
+    }
+
+    pub async fn scan(&self) -> Result<usize> {
+        let query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "status",
+                Operator::Eq,
+                Some(TradeStatus::Scheduled.as_str().into()),
+            ))
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
+            .allow_filtering(true)
+            .build();
+        let trades = query
+            .get_entries::<Trade>(&self.db)
+            .await?
+            .into_iter()
+            .filter(|trade| !trade.is_pending())
+            .collect::<Vec<Trade>>();
+
+        let mut started = 0;
+        for trade in trades {
+            let event = TradeEvent::new(TradeEventKind::Listed, &trade);
+            match self.start(&trade, &event).await {
+                Ok(true) => {}
+                // The seller cancelled the trade in the meantime
+                Ok(false) => continue,
+                Err(err) => {
+                    error!("Can't start the scheduled trade {0}: {1}", trade.id(), err);
+                    continue;
+                }
+            }
+
+            self.events.publish(event);
+            self.market_summaries
+                .refresh(trade.realm_id(), trade.item_id())
+                .await;
+            started += 1;
+        }
+
+        Ok(started)
+    }
+
+    // Returns whether the trade was started by this call.
+    async fn start(&self, trade: &Trade, event: &TradeEvent) -> Result<bool> {
+        let update_query = get_trade_update(trade, &["status"]).build();
+        let update_query_values = query_values!(
+            "status" => TradeStatus::Active.as_str()
+        );
+        if !update_query
+            .update_if(&self.db, &update_query_values)
+            .await?
+        {
+            return Ok(false);
+        }
+
+        record_event(Batch::new(), event)?.execute(&self.db).await?;
+
+        Ok(true)
+    }
+}
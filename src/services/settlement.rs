@@ -0,0 +1,79 @@
+use uuid::Uuid;
+
+use crate::core::error::Result;
+use crate::core::money::Money;
+use crate::core::orm::batch::Batch;
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::ledger::{LedgerEntry, LedgerReason, LEDGER_ALL_COLUMNS, LEDGER_TABLE};
+use crate::models::mail::Mail;
+use crate::models::trade::Trade;
+
+// Adds the charge to the batch with the state change of the trade, so that
+// the player is charged only when the change is applied.
+pub fn record_charge(
+    batch: Batch,
+    player_id: Uuid,
+    trade: &Trade,
+    amount: Money,
+    reason: LedgerReason,
+) -> Result<Batch> {
+    let query = QueryBuilder::new(&LEDGER_TABLE)
+        .query_type(QueryType::Insert)
+        .columns(&LEDGER_ALL_COLUMNS)
+        .build();
+    let entry = get_charge_entry(player_id, trade, amount, reason)?;
+
+    batch.add(&query, &entry.into_query_values())
+}
+
+pub fn get_charge_entry(
+    player_id: Uuid,
+    trade: &Trade,
+    amount: Money,
+    reason: LedgerReason,
+) -> Result<LedgerEntry> {
+    let debit = Money::ZERO.checked_sub(amount)?;
+    Ok(LedgerEntry::new(
+        player_id,
+        trade.id(),
+        debit,
+        trade.currency(),
+        reason,
+    ))
+}
+
+// Records currency movements caused by trades, so that the game server
+// can apply them to player wallets.
+pub struct Settlement {
+    db: CassandraSession,
+}
+
+impl Settlement {
+    pub fn new(db: CassandraSession) -> Self {
+        Self { db }
+    }
+
+    // Credits the currency attached to the mail. The entry shares the id
+    // with the mail, so claiming the mail again doesn't credit it twice.
+    pub async fn claim(&self, mail: &Mail) -> Result<()> {
+        let entry = LedgerEntry::new(
+            mail.player_id(),
+            mail.trade_id(),
+            mail.currency(),
+            mail.currency_code(),
+            LedgerReason::MailClaim,
+        )
+        .with_id(mail.id());
+        self.record(entry).await
+    }
+
+    async fn record(&self, entry: LedgerEntry) -> Result<()> {
+        let query = QueryBuilder::new(&LEDGER_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&LEDGER_ALL_COLUMNS)
+            .build();
+        let query_values = entry.into_query_values();
+        query.try_insert(&self.db, &query_values).await
+    }
+}
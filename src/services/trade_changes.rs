@@ -0,0 +1,277 @@
+use cdrs_tokio::query_values;
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{error, warn};
+
+use crate::core::error::Result;
+use crate::core::orm::batch::Batch;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::inventory_change::{INVENTORY_CHANGE_ALL_COLUMNS, INVENTORY_CHANGE_TABLE};
+use crate::models::ledger::{LEDGER_ALL_COLUMNS, LEDGER_TABLE};
+use crate::models::outbox::{OUTBOX_ALL_COLUMNS, OUTBOX_TABLE};
+use crate::models::sale_history::get_bucket;
+use crate::models::trade::{Trade, TRADE_ALL_COLUMNS, TRADE_TABLE};
+use crate::models::trade_change::{
+    decode_trade_change, TradeChange, TradeChangeEntry, TRADE_CHANGE_ALL_COLUMNS,
+    TRADE_CHANGE_TABLE,
+};
+use crate::services::mailbox::record_mail;
+
+// The time given to the request to write the follow-up of its change,
+// before the relay writes it instead.
+const FOLLOW_UP_GRACE_PERIOD: TimeDelta = TimeDelta::minutes(1);
+
+// How far back the relay looks for changes with unwritten follow-ups.
+const RECOVERY_LOOKBACK: TimeDelta = TimeDelta::days(7);
+
+// Writes the events, the mail, the charges and the inventory changes that
+// follow the change of the trade. The follow-up is stored in the trade by
+// the conditional update that changes it, so it's never lost: when writing
+// it fails, it's written by the next change of the trade or by the relay.
+pub struct TradeChanges {
+    db: CassandraSession,
+}
+
+impl TradeChanges {
+    pub fn new(db: CassandraSession) -> Self {
+        Self { db }
+    }
+
+    // Stores the entry of the change before the trade is updated, and returns
+    // the change encoded for the pending_change column of the trade.
+    pub async fn prepare(&self, change: &TradeChange) -> Result<String> {
+        let query = QueryBuilder::new(&TRADE_CHANGE_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&TRADE_CHANGE_ALL_COLUMNS)
+            .build();
+        query
+            .try_insert(&self.db, &change.entry().clone().into_query_values())
+            .await?;
+
+        Ok(change.encode())
+    }
+
+    // Removes the entry of the change rejected by the trade update. The entry
+    // that can't be removed is removed later by the relay.
+    pub async fn discard(&self, change: &TradeChange) {
+        if let Err(err) = self.remove(change.entry()).await {
+            warn!(
+                "Can't remove the rejected trade change {0}: {1}",
+                change.id(),
+                err
+            );
+        }
+    }
+
+    // The trade is already changed, so the failure isn't returned to the
+    // player. The follow-up stays in the trade and is written later.
+    pub async fn finish(&self, change: &TradeChange, encoded: &str) {
+        if let Err(err) = self.write(change, encoded).await {
+            error!(
+                "Can't write the follow-up of the trade change {0}, it will be retried: {1}",
+                change.id(),
+                err
+            );
+        }
+    }
+
+    // Writes the follow-up left in the trade by its previous change. Every
+    // trade update expects no pending change, so it must be called before
+    // the trade is changed again.
+    pub async fn finish_pending(&self, trade: &mut Trade) -> Result<()> {
+        if let Some(encoded) = trade.pending_change().map(str::to_string) {
+            let change = decode_trade_change(&encoded)?;
+            self.write(&change, &encoded).await?;
+            trade.clear_pending_change();
+        }
+
+        Ok(())
+    }
+
+    // Writes the follow-ups of the changes, that weren't finished within
+    // the grace period. Returns the amount of written follow-ups.
+    pub async fn recover(&self) -> Result<usize> {
+        let now = Utc::now();
+        let mut bucket = get_bucket(now - RECOVERY_LOOKBACK);
+        let mut recovered = 0;
+
+        while bucket <= get_bucket(now) {
+            for entry in self
+                .get_entries(bucket, now - FOLLOW_UP_GRACE_PERIOD)
+                .await?
+            {
+                match self.recover_entry(&entry).await {
+                    Ok(true) => recovered += 1,
+                    Ok(false) => {}
+                    Err(err) => error!(
+                        "Can't recover the follow-up of the trade change {0}: {1}",
+                        entry.id(),
+                        err
+                    ),
+                }
+            }
+            bucket += TimeDelta::days(1);
+        }
+
+        Ok(recovered)
+    }
+
+    // Returns whether the follow-up was written by this call. The entry
+    // without the change in the trade belongs to the rejected update or to
+    // the follow-up that was already written.
+    async fn recover_entry(&self, entry: &TradeChangeEntry) -> Result<bool> {
+        let pending = self
+            .get_trade(entry)
+            .await?
+            .and_then(|trade| trade.pending_change().map(str::to_string));
+
+        if let Some(encoded) = pending {
+            let change = decode_trade_change(&encoded)?;
+            if change.id() == entry.id() {
+                self.write(&change, &encoded).await?;
+                return Ok(true);
+            }
+        }
+
+        self.remove(entry).await?;
+        Ok(false)
+    }
+
+    // The rows keep the ids they got with the change, so writing them again
+    // doesn't duplicate them. The pending change is cleared only when it's
+    // still the same change, so that the change made after it isn't lost.
+    async fn write(&self, change: &TradeChange, encoded: &str) -> Result<()> {
+        let outbox_query = QueryBuilder::new(&OUTBOX_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&OUTBOX_ALL_COLUMNS)
+            .build();
+        let ledger_query = QueryBuilder::new(&LEDGER_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&LEDGER_ALL_COLUMNS)
+            .build();
+        let inventory_query = QueryBuilder::new(&INVENTORY_CHANGE_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&INVENTORY_CHANGE_ALL_COLUMNS)
+            .build();
+
+        let mut batch = record_mail(Batch::new(), change.mail().to_vec())?;
+        for entry in change.outbox() {
+            batch = batch.add(&outbox_query, &entry.clone().into_query_values())?;
+        }
+        for entry in change.ledger() {
+            batch = batch.add(&ledger_query, &entry.clone().into_query_values())?;
+        }
+        for entry in change.inventory() {
+            batch = batch.add(&inventory_query, &entry.clone().into_query_values())?;
+        }
+        batch.execute(&self.db).await?;
+
+        let entry = change.entry();
+        let clear_query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Update)
+            .columns(&["pending_change"])
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::Eq,
+                Some(entry.realm_id().into()),
+            ))
+            .filter_by(Filter::new(
+                "item_id",
+                Operator::Eq,
+                Some(entry.item_id().into()),
+            ))
+            .filter_by(Filter::new(
+                "id",
+                Operator::Eq,
+                Some(entry.trade_id().into()),
+            ))
+            .filter_by(Filter::new(
+                "created_by",
+                Operator::Eq,
+                Some(entry.created_by().into()),
+            ))
+            .only_if(Filter::new(
+                "pending_change",
+                Operator::Eq,
+                Some(encoded.into()),
+            ))
+            .build();
+        clear_query
+            .update_if(
+                &self.db,
+                &query_values!("pending_change" => Option::<String>::None),
+            )
+            .await?;
+
+        self.remove(entry).await
+    }
+
+    async fn get_entries(
+        &self,
+        bucket: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<TradeChangeEntry>> {
+        let query = QueryBuilder::new(&TRADE_CHANGE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_CHANGE_ALL_COLUMNS)
+            .filter_by(Filter::new("bucket", Operator::Eq, Some(bucket.into())))
+            .filter_by(Filter::new("created_at", Operator::Lte, Some(until.into())))
+            .build();
+
+        query.get_entries::<TradeChangeEntry>(&self.db).await
+    }
+
+    async fn get_trade(&self, entry: &TradeChangeEntry) -> Result<Option<Trade>> {
+        let query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::Eq,
+                Some(entry.realm_id().into()),
+            ))
+            .filter_by(Filter::new(
+                "item_id",
+                Operator::Eq,
+                Some(entry.item_id().into()),
+            ))
+            .filter_by(Filter::new(
+                "id",
+                Operator::Eq,
+                Some(entry.trade_id().into()),
+            ))
+            .filter_by(Filter::new(
+                "created_by",
+                Operator::Eq,
+                Some(entry.created_by().into()),
+            ))
+            .build();
+
+        Ok(query
+            .get_entries::<Trade>(&self.db)
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    async fn remove(&self, entry: &TradeChangeEntry) -> Result<()> {
+        let query = QueryBuilder::new(&TRADE_CHANGE_TABLE)
+            .query_type(QueryType::Delete)
+            .filter_by(Filter::new(
+                "bucket",
+                Operator::Eq,
+                Some(entry.bucket().into()),
+            ))
+            .filter_by(Filter::new(
+                "created_at",
+                Operator::Eq,
+                Some(entry.created_at().into()),
+            ))
+            .filter_by(Filter::new("id", Operator::Eq, Some(entry.id().into())))
+            .build();
+        query.delete(&self.db).await?;
+
+        Ok(())
+    }
+}
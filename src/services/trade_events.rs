@@ -0,0 +1,488 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+
+use crate::core::error::{Error, Result};
+use crate::core::money::Money;
+use crate::models::trade::{Trade, TradeStatus, EMPTY_UUID};
+use crate::proto::TradeEvent as TradeEventDetail;
+
+// The amount of events buffered for a single subscriber before it's
+// considered as a slow one.
+const SUBSCRIBER_BUFFER_SIZE: usize = 32;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeEventKind {
+    // The trade waits for its start time. Followed by the listed event,
+    // once the trade goes on sale.
+    Scheduled,
+    Listed,
+    Bid,
+    // Sent to the previous bidder, when someone placed a higher bid.
+    Outbid,
+    BoughtOut,
+    Cancelled,
+    Expired,
+}
+
+impl TradeEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradeEventKind::Scheduled => "scheduled",
+            TradeEventKind::Listed => "listed",
+            TradeEventKind::Bid => "bid",
+            TradeEventKind::Outbid => "outbid",
+            TradeEventKind::BoughtOut => "bought_out",
+            TradeEventKind::Cancelled => "cancelled",
+            TradeEventKind::Expired => "expired",
+        }
+    }
+
+    // Returns true when no more events are expected for the trade.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TradeEventKind::BoughtOut | TradeEventKind::Cancelled | TradeEventKind::Expired
+        )
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TradeEvent {
+    kind: TradeEventKind,
+    realm_id: String,
+    trade_id: Uuid,
+    item_id: Uuid,
+    item_name: String,
+    item_category: String,
+    quantity: i32,
+    seller: Uuid,
+    bidder: Option<Uuid>,
+    previous_bidder: Option<Uuid>,
+    price: Money,
+    buyout_price: Money,
+    currency: String,
+    // The only player allowed to buy the private trade.
+    reserved_for: Option<Uuid>,
+    created_at: DateTime<Utc>,
+}
+
+impl TradeEvent {
+    // Creates the event from the trade state. The bidder is taken from
+    // the trade, if anyone placed a bid.
+    pub fn new(kind: TradeEventKind, trade: &Trade) -> Self {
+        let bidder = Some(trade.bought_by()).filter(|bidder| *bidder != *EMPTY_UUID);
+
+        Self {
+            kind,
+            realm_id: trade.realm_id().to_string(),
+            trade_id: trade.id(),
+            item_id: trade.item_id(),
+            item_name: trade.item_name().to_string(),
+            item_category: trade.item_category().to_string(),
+            quantity: trade.quantity(),
+            seller: trade.created_by(),
+            bidder,
+            previous_bidder: None,
+            price: trade.bid_price(),
+            buyout_price: trade.buyout_price(),
+            currency: trade.currency().to_string(),
+            reserved_for: trade.reserved_for(),
+            created_at: Utc::now(),
+        }
+    }
+
+    // Replaces the bidder with the new one. The replaced bidder is kept as
+    // the previous bidder.
+    pub fn with_bid(mut self, bidder: Uuid, price: Money) -> Self {
+        self.previous_bidder = self.bidder.filter(|previous| *previous != bidder);
+        self.bidder = Some(bidder);
+        self.price = price;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: TradeEventKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn kind(&self) -> TradeEventKind {
+        self.kind
+    }
+
+    pub fn realm_id(&self) -> &str {
+        &self.realm_id
+    }
+
+    pub fn trade_id(&self) -> Uuid {
+        self.trade_id
+    }
+
+    pub fn previous_bidder(&self) -> Option<Uuid> {
+        self.previous_bidder
+    }
+
+    // Checks whether the player takes part in the trade in the way
+    // the event matters for.
+    pub fn is_addressed_to(&self, player_id: Uuid) -> bool {
+        match self.kind {
+            TradeEventKind::Outbid => self.previous_bidder == Some(player_id),
+            _ => {
+                self.seller == player_id
+                    || self.bidder == Some(player_id)
+                    || self.reserved_for == Some(player_id)
+            }
+        }
+    }
+}
+
+// Returns the event that closed the trade, or None while the trade is open.
+// Used for subscribers that came after the trade was already closed.
+pub fn get_closing_event(trade: &Trade) -> Option<TradeEvent> {
+    let kind = match trade.current_status() {
+        TradeStatus::Sold => TradeEventKind::BoughtOut,
+        TradeStatus::Cancelled => TradeEventKind::Cancelled,
+        TradeStatus::Expired => TradeEventKind::Expired,
+        TradeStatus::Scheduled | TradeStatus::Active => return None,
+    };
+
+    Some(TradeEvent::new(kind, trade))
+}
+
+impl From<&TradeEvent> for TradeEventDetail {
+    fn from(instance: &TradeEvent) -> Self {
+        Self {
+            kind: instance.kind.as_str().to_string(),
+            trade_id: instance.trade_id.to_string(),
+            item_id: instance.item_id.to_string(),
+            seller: instance.seller.to_string(),
+            bidder: instance.bidder.map(|bidder| bidder.to_string()),
+            previous_bidder: instance.previous_bidder.map(|bidder| bidder.to_string()),
+            price: instance.price.into(),
+            created_at: instance.created_at.timestamp(),
+            currency: instance.currency.to_owned(),
+            realm_id: instance.realm_id.to_owned(),
+        }
+    }
+}
+
+// The public representation of the trade event for the market feed.
+// Doesn't expose the players taking part in the trade.
+#[derive(Serialize, Debug)]
+pub struct MarketEvent {
+    kind: &'static str,
+    realm_id: String,
+    trade_id: Uuid,
+    item_id: Uuid,
+    item_name: String,
+    item_category: String,
+    quantity: i32,
+    price: Money,
+    buyout_price: Money,
+    currency: String,
+    created_at: i64,
+}
+
+impl MarketEvent {
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+}
+
+impl From<&TradeEvent> for MarketEvent {
+    fn from(instance: &TradeEvent) -> Self {
+        Self {
+            kind: instance.kind.as_str(),
+            realm_id: instance.realm_id.to_owned(),
+            trade_id: instance.trade_id,
+            item_id: instance.item_id,
+            item_name: instance.item_name.to_owned(),
+            item_category: instance.item_category.to_owned(),
+            quantity: instance.quantity,
+            price: instance.price,
+            buyout_price: instance.buyout_price,
+            currency: instance.currency.to_owned(),
+            created_at: instance.created_at.timestamp(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Subscription {
+    // All events of the trade, except the outbid ones that duplicate bids.
+    Trade(Uuid),
+    // All events addressed to the player.
+    Player(Uuid),
+    // The public events of the realm market, optionally narrowed down to
+    // the item or the item category.
+    Market {
+        realm_id: String,
+        item_id: Option<Uuid>,
+        category: Option<String>,
+    },
+}
+
+impl Subscription {
+    pub fn accepts(&self, event: &TradeEvent) -> bool {
+        match self {
+            Subscription::Trade(trade_id) => {
+                event.trade_id == *trade_id && event.kind != TradeEventKind::Outbid
+            }
+            Subscription::Player(player_id) => event.is_addressed_to(*player_id),
+            Subscription::Market {
+                realm_id,
+                item_id,
+                category,
+            } => {
+                // Scheduled trades stay hidden from the market until they start,
+                // reserved trades are never shown there
+                !matches!(
+                    event.kind,
+                    TradeEventKind::Outbid | TradeEventKind::Scheduled
+                ) && event.reserved_for.is_none()
+                    && event.realm_id == *realm_id
+                    && item_id.is_none_or(|item_id| event.item_id == item_id)
+                    && category
+                        .as_ref()
+                        .is_none_or(|category| event.item_category.eq_ignore_ascii_case(category))
+            }
+        }
+    }
+
+    // Returns true when the subscription won't receive any more events.
+    pub fn is_finished_by(&self, event: &TradeEvent) -> bool {
+        matches!(self, Subscription::Trade(_)) && event.kind.is_final()
+    }
+}
+
+// The in-process bus for trade events. Publishing never blocks: each
+// subscriber has a bounded buffer and gets disconnected when it falls behind.
+#[derive(Clone)]
+pub struct TradeEventBus {
+    sender: broadcast::Sender<TradeEvent>,
+}
+
+impl TradeEventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: TradeEvent) {
+        // Fails only when nobody listens to the events
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self, subscription: Subscription) -> ReceiverStream<Result<TradeEvent>> {
+        let (tx, rx) = mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        let mut events = self.sender.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                let received = tokio::select! {
+                    received = events.recv() => received,
+                    _ = tx.closed() => break,
+                };
+
+                let event = match received {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        let _ = tx.send(Err(Error::SubscriberLagged { skipped })).await;
+                        break;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                if !subscription.accepts(&event) {
+                    continue;
+                }
+
+                let is_finished = subscription.is_finished_by(&event);
+                if tx.send(Ok(event)).await.is_err() || is_finished {
+                    break;
+                }
+            }
+        });
+
+        ReceiverStream::new(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_stream::StreamExt;
+    use uuid::Uuid;
+
+    use crate::core::error::Error;
+    use crate::core::money::Money;
+    use crate::core::realm::DEFAULT_REALM;
+    use crate::models::trade::{Trade, TradeStatus};
+    use crate::services::trade_events::{
+        get_closing_event, Subscription, TradeEvent, TradeEventBus, TradeEventKind,
+    };
+
+    #[test]
+    fn test_outbid_is_addressed_to_previous_bidder_only() {
+        let (seller, first, second) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let trade = Trade::builder()
+            .created_by(seller)
+            .build()
+            .with_bid(first, "first", 150);
+        let event = TradeEvent::new(TradeEventKind::Bid, &trade)
+            .with_bid(second, Money::new(200))
+            .with_kind(TradeEventKind::Outbid);
+
+        assert_eq!(event.previous_bidder(), Some(first));
+        assert!(event.is_addressed_to(first));
+        assert!(!event.is_addressed_to(second));
+        assert!(!event.is_addressed_to(seller));
+    }
+
+    #[test]
+    fn test_trade_subscription_skips_outbid_events() {
+        let trade = Trade::builder()
+            .build()
+            .with_bid(Uuid::new_v4(), "first", 150);
+        let subscription = Subscription::Trade(trade.id());
+        let bid =
+            TradeEvent::new(TradeEventKind::Bid, &trade).with_bid(Uuid::new_v4(), Money::new(200));
+        let outbid = bid.clone().with_kind(TradeEventKind::Outbid);
+
+        assert!(subscription.accepts(&bid));
+        assert!(!subscription.accepts(&outbid));
+        assert!(!Subscription::Trade(Uuid::new_v4()).accepts(&bid));
+    }
+
+    #[test]
+    fn test_market_subscription_filters_by_category() {
+        let trade = Trade::builder().item_category("Weapon").build();
+        let listed = TradeEvent::new(TradeEventKind::Listed, &trade);
+        let outbid = TradeEvent::new(TradeEventKind::Bid, &trade)
+            .with_bid(Uuid::new_v4(), Money::new(200))
+            .with_kind(TradeEventKind::Outbid);
+        let weapons = Subscription::Market {
+            realm_id: DEFAULT_REALM.to_string(),
+            item_id: None,
+            category: Some("weapon".to_string()),
+        };
+        let armor = Subscription::Market {
+            realm_id: DEFAULT_REALM.to_string(),
+            item_id: None,
+            category: Some("armor".to_string()),
+        };
+
+        let scheduled = TradeEvent::new(TradeEventKind::Scheduled, &trade);
+
+        assert!(weapons.accepts(&listed));
+        assert!(!weapons.accepts(&outbid));
+        assert!(!weapons.accepts(&scheduled));
+        assert!(!armor.accepts(&listed));
+    }
+
+    #[test]
+    fn test_reserved_trade_is_announced_to_buyer_only() {
+        let (seller, buyer) = (Uuid::new_v4(), Uuid::new_v4());
+        let trade = Trade::builder()
+            .created_by(seller)
+            .reserved_for(Some(buyer))
+            .build();
+        let listed = TradeEvent::new(TradeEventKind::Listed, &trade);
+        let market = Subscription::Market {
+            realm_id: DEFAULT_REALM.to_string(),
+            item_id: None,
+            category: None,
+        };
+
+        assert!(!market.accepts(&listed));
+        assert!(Subscription::Player(buyer).accepts(&listed));
+        assert!(!Subscription::Player(Uuid::new_v4()).accepts(&listed));
+    }
+
+    #[test]
+    fn test_market_subscription_filters_by_realm() {
+        let trade = Trade::builder().build().with_realm("eu-1");
+        let listed = TradeEvent::new(TradeEventKind::Listed, &trade);
+        let subscription = |realm_id: &str| Subscription::Market {
+            realm_id: realm_id.to_string(),
+            item_id: None,
+            category: None,
+        };
+
+        assert!(subscription("eu-1").accepts(&listed));
+        assert!(!subscription("us-1").accepts(&listed));
+        assert!(!subscription(DEFAULT_REALM).accepts(&listed));
+    }
+
+    #[tokio::test]
+    async fn test_trade_stream_ends_after_final_event() {
+        let bus = TradeEventBus::new(16);
+        let trade = Trade::builder().build();
+        let mut stream = bus.subscribe(Subscription::Trade(trade.id()));
+
+        bus.publish(
+            TradeEvent::new(TradeEventKind::Bid, &trade).with_bid(Uuid::new_v4(), Money::new(200)),
+        );
+        bus.publish(TradeEvent::new(TradeEventKind::Cancelled, &trade));
+
+        assert_eq!(
+            stream.next().await.unwrap().unwrap().kind,
+            TradeEventKind::Bid
+        );
+        assert_eq!(
+            stream.next().await.unwrap().unwrap().kind,
+            TradeEventKind::Cancelled
+        );
+        assert!(stream.next().await.is_none());
+    }
+
+    #[test]
+    fn test_closed_trade_has_closing_event() {
+        let buyer = Uuid::new_v4();
+        let trade = Trade::builder().build();
+        assert!(get_closing_event(&trade).is_none());
+
+        let sold = Trade::builder()
+            .build()
+            .with_bid(buyer, "buyer", 1000)
+            .with_status(TradeStatus::Sold);
+        let event = get_closing_event(&sold).unwrap();
+        assert_eq!(event.kind(), TradeEventKind::BoughtOut);
+        assert!(event.is_addressed_to(buyer));
+        assert!(Subscription::Trade(sold.id()).is_finished_by(&event));
+
+        let cancelled = Trade::builder().build().with_status(TradeStatus::Cancelled);
+        assert_eq!(
+            get_closing_event(&cancelled).map(|event| event.kind()),
+            Some(TradeEventKind::Cancelled)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_gets_disconnected() {
+        let bus = TradeEventBus::new(4);
+        let seller = Uuid::new_v4();
+        let trade = Trade::builder().created_by(seller).build();
+        let mut stream = bus.subscribe(Subscription::Player(seller));
+
+        for price in 0..100 {
+            bus.publish(
+                TradeEvent::new(TradeEventKind::Bid, &trade)
+                    .with_bid(Uuid::new_v4(), Money::new(price)),
+            );
+            tokio::task::yield_now().await;
+        }
+
+        let mut received = vec![];
+        while let Some(item) = stream.next().await {
+            received.push(item);
+        }
+
+        let err = received.last().unwrap().as_ref().unwrap_err();
+        assert!(matches!(err, Error::SubscriberLagged { .. }));
+        assert!(received.len() < 100);
+    }
+}
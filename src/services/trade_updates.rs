@@ -0,0 +1,163 @@
+use chrono::Utc;
+
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::models::trade::{Trade, TRADE_TABLE};
+
+// Returns the update of the trade, that is applied only while the trade keeps
+// the status it was read with. Concurrent changes of the trade read the same
+// status, so only one of them is applied. The others must not write their
+// mail, charges or events. The update is rejected while the follow-up of
+// the previous change is pending, so that it isn't replaced before it's
+// written.
+pub fn get_trade_update<'a>(trade: &'a Trade, columns: &'a [&'a str]) -> QueryBuilder<'a> {
+    let query = QueryBuilder::new(&TRADE_TABLE)
+        .query_type(QueryType::Update)
+        .columns(columns)
+        .filter_by(Filter::new(
+            "realm_id",
+            Operator::Eq,
+            Some(trade.realm_id().into()),
+        ))
+        .filter_by(Filter::new("id", Operator::Eq, Some(trade.id().into())))
+        .filter_by(Filter::new(
+            "item_id",
+            Operator::Eq,
+            Some(trade.item_id().into()),
+        ))
+        .filter_by(Filter::new(
+            "created_by",
+            Operator::Eq,
+            Some(trade.created_by().into()),
+        ));
+
+    let query = match trade.stored_status() {
+        Some(status) => query.only_if(Filter::new("status", Operator::Eq, Some(status.into()))),
+        // Trades created before statuses were introduced
+        None => query.only_if_null("status"),
+    };
+
+    query.only_if_null("pending_change")
+}
+
+// Returns the update of the trade, that is applied only while the trade keeps
+// the status it was read with and isn't expired yet. The trade may expire
+// between the read and the update, before the expiry job closes it.
+pub fn get_open_trade_update<'a>(trade: &'a Trade, columns: &'a [&'a str]) -> QueryBuilder<'a> {
+    let query = get_trade_update(trade, columns);
+    match trade.expires() {
+        true => query.only_if(Filter::new(
+            "expired_at",
+            Operator::Gt,
+            Some(Utc::now().into()),
+        )),
+        false => query,
+    }
+}
+
+// Returns the bid on the trade, that is applied only when nobody else bid
+// on it since it was read.
+pub fn get_bid_update(trade: &Trade) -> QueryBuilder<'_> {
+    get_open_trade_update(
+        trade,
+        &[
+            "bid_price",
+            "bought_by",
+            "bought_by_username",
+            "pending_change",
+        ],
+    )
+    .only_if(Filter::new(
+        "bid_price",
+        Operator::Eq,
+        Some(trade.bid_price().into()),
+    ))
+    .only_if(Filter::new(
+        "bought_by",
+        Operator::Eq,
+        Some(trade.bought_by().into()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use cdrs_tokio::query::QueryValues;
+    use cdrs_tokio::query_values;
+    use cdrs_tokio::types::value::Value;
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use crate::core::money::Money;
+    use crate::models::trade::{Trade, TradeStatus};
+    use crate::services::trade_updates::{get_bid_update, get_trade_update};
+
+    #[test]
+    fn test_trade_update_expects_read_status() {
+        let trade = Trade::builder().build();
+        let query = get_trade_update(&trade, &["status", "pending_change"]).build();
+
+        assert!(query
+            .get_raw_cql()
+            .ends_with("IF status = ? AND pending_change = null"));
+        match query
+            .get_positional_values(&query_values!(
+                "status" => TradeStatus::Sold.as_str(),
+                "pending_change" => "{}"
+            ))
+            .unwrap()
+        {
+            QueryValues::SimpleValues(values) => assert_eq!(
+                values.last(),
+                Some(&Value::from(TradeStatus::Active.as_str()))
+            ),
+            QueryValues::NamedValues(_) => panic!("expected positional values"),
+        }
+    }
+
+    #[test]
+    fn test_losing_bid_expects_outdated_bid() {
+        // Both bidders read the trade before any of them placed the bid, so
+        // the second bid expects the price already replaced by the first one
+        let trade = Trade::builder().build();
+        let query = get_bid_update(&trade).build();
+        let bid = query_values!(
+            "bid_price" => Money::new(150),
+            "bought_by" => Uuid::new_v4(),
+            "bought_by_username" => "bidder",
+            "pending_change" => "{}"
+        );
+
+        assert!(query.get_raw_cql().ends_with(
+            "IF status = ? AND pending_change = null AND bid_price = ? AND bought_by = ?"
+        ));
+        match query.get_positional_values(&bid).unwrap() {
+            QueryValues::SimpleValues(values) => assert_eq!(
+                values[values.len() - 2..].to_vec(),
+                vec![
+                    Value::from(trade.bid_price()),
+                    Value::from(trade.bought_by())
+                ]
+            ),
+            QueryValues::NamedValues(_) => panic!("expected positional values"),
+        }
+    }
+
+    #[test]
+    fn test_bid_expects_trade_not_expired() {
+        let trade = Trade::builder().build();
+        let expiring = Trade::builder()
+            .build()
+            .with_lifetime(Utc::now(), Utc::now() + Duration::hours(1));
+
+        assert!(!get_bid_update(&trade)
+            .build()
+            .get_raw_cql()
+            .contains("expired_at"));
+        assert!(get_bid_update(&expiring)
+            .build()
+            .get_raw_cql()
+            .ends_with(
+                "IF status = ? AND pending_change = null AND expired_at > ? AND bid_price = ? AND bought_by = ?"
+            ));
+    }
+}
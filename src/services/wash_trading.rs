@@ -0,0 +1,475 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{error, info};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::cli::CliOptions;
+use crate::core::error::Result;
+use crate::core::money::Money;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::sale::{SaleEntry, SALE_ALL_COLUMNS, SALE_TABLE};
+use crate::models::sale_history::get_bucket;
+use crate::models::trade::format_price;
+use crate::models::trade_flag::{FlagKind, TradeFlag, TRADE_FLAG_ALL_COLUMNS, TRADE_FLAG_TABLE};
+use crate::services::outbox::sinks::OutboxSink;
+use crate::services::outbox::OutboxEvent;
+use crate::services::price_history::median;
+
+#[derive(Debug, Clone)]
+pub struct Sale {
+    trade_id: Uuid,
+    item_id: Uuid,
+    seller: Uuid,
+    buyer: Uuid,
+    price: Money,
+    currency: String,
+    quantity: i32,
+}
+
+impl Sale {
+    fn unit_price(&self) -> Money {
+        // Sold trades hold at least one item, so the division can't fail
+        self.price
+            .checked_div(i64::from(self.quantity.max(1)))
+            .unwrap_or_default()
+    }
+}
+
+impl From<&SaleEntry> for Sale {
+    fn from(entry: &SaleEntry) -> Self {
+        Self {
+            trade_id: entry.trade_id(),
+            item_id: entry.item_id(),
+            seller: entry.seller(),
+            buyer: entry.buyer(),
+            price: entry.price(),
+            currency: entry.currency().to_string(),
+            quantity: entry.quantity(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DetectionSettings {
+    // The amount of sales between the same seller and buyer to flag them.
+    pub pair_threshold: usize,
+    // How many times the price must exceed the median price to flag the sale.
+    pub price_multiplier: i64,
+    // The minimal amount of sales of the item to calculate its median price.
+    pub min_samples: usize,
+}
+
+pub fn detect(sales: &[Sale], settings: &DetectionSettings) -> Vec<TradeFlag> {
+    let mut flags = vec![];
+    flags.extend(detect_repeated_pairs(sales, settings));
+    flags.extend(detect_price_outliers(sales, settings));
+    flags.extend(detect_round_trips(sales));
+    flags
+}
+
+fn detect_repeated_pairs(sales: &[Sale], settings: &DetectionSettings) -> Vec<TradeFlag> {
+    let mut pairs: HashMap<(Uuid, Uuid), Vec<&Sale>> = HashMap::new();
+    for sale in sales {
+        pairs
+            .entry((sale.seller, sale.buyer))
+            .or_default()
+            .push(sale);
+    }
+
+    pairs
+        .values()
+        .filter(|pair_sales| pair_sales.len() >= settings.pair_threshold)
+        .flat_map(|pair_sales| {
+            pair_sales.iter().map(|sale| {
+                let details = format!(
+                    "{0} sales between the same seller and buyer.",
+                    pair_sales.len()
+                );
+                create_flag(sale, FlagKind::RepeatedPair, details)
+            })
+        })
+        .collect()
+}
+
+fn detect_price_outliers(sales: &[Sale], settings: &DetectionSettings) -> Vec<TradeFlag> {
+    // Prices in different currencies can't be compared. Barter trades are
+    // sold for items instead of money, so their zero prices are left out.
+    let mut items: HashMap<(Uuid, &str), Vec<&Sale>> = HashMap::new();
+    for sale in sales.iter().filter(|sale| sale.price.is_positive()) {
+        items
+            .entry((sale.item_id, sale.currency.as_str()))
+            .or_default()
+            .push(sale);
+    }
+
+    items
+        .values()
+        .filter(|item_sales| item_sales.len() >= settings.min_samples)
+        .flat_map(|item_sales| {
+            let unit_prices = item_sales.iter().map(|sale| sale.unit_price()).collect();
+            let median_price = median(unit_prices).unwrap_or_default();
+            // No price can exceed the limit that doesn't fit into the amount
+            let max_price = median_price.checked_mul(settings.price_multiplier).ok();
+
+            item_sales
+                .iter()
+                .filter(move |sale| {
+                    max_price.is_some_and(|max_price| sale.unit_price() > max_price)
+                })
+                .map(move |sale| {
+                    let details = format!(
+                        "The unit price is more than {0} times above the median price {1}.",
+                        settings.price_multiplier,
+                        format_price(median_price, &sale.currency)
+                    );
+                    create_flag(sale, FlagKind::PriceOutlier, details)
+                })
+        })
+        .collect()
+}
+
+fn detect_round_trips(sales: &[Sale]) -> Vec<TradeFlag> {
+    let transfers: HashSet<(Uuid, Uuid, Uuid)> = sales
+        .iter()
+        .map(|sale| (sale.seller, sale.buyer, sale.item_id))
+        .collect();
+
+    sales
+        .iter()
+        .filter(|sale| transfers.contains(&(sale.buyer, sale.seller, sale.item_id)))
+        .map(|sale| {
+            let details = "The item was sold back between the same players.".to_string();
+            create_flag(sale, FlagKind::RoundTrip, details)
+        })
+        .collect()
+}
+
+fn create_flag(sale: &Sale, kind: FlagKind, details: String) -> TradeFlag {
+    TradeFlag::new(
+        sale.trade_id,
+        kind,
+        sale.item_id,
+        sale.seller,
+        sale.buyer,
+        sale.price,
+        details,
+    )
+}
+
+// Periodically scans the sales stored by the sale log within the lookback
+// and stores the suspicious ones in the trade_flag table.
+pub struct WashTradingDetector {
+    db: CassandraSession,
+    settings: DetectionSettings,
+    lookback: Duration,
+}
+
+impl WashTradingDetector {
+    pub fn new(db: CassandraSession, settings: DetectionSettings, lookback: Duration) -> Self {
+        Self {
+            db,
+            settings,
+            lookback,
+        }
+    }
+
+    pub async fn run(self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match self.scan().await {
+                Ok(flagged) => info!("Wash trading scan finished, {0} flags stored", flagged),
+                Err(err) => error!("Wash trading scan failed: {0}", err),
+            }
+        }
+    }
+
+    // Returns the amount of new flags. The sales flagged by previous scans
+    // keep their flags as they were first stored.
+    pub async fn scan(&self) -> Result<usize> {
+        let since: DateTime<Utc> = Utc::now() - self.lookback;
+        let sales = self
+            .get_sales(since)
+            .await?
+            .iter()
+            .map(Sale::from)
+            .collect::<Vec<Sale>>();
+
+        let insert_query = QueryBuilder::new(&TRADE_FLAG_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&TRADE_FLAG_ALL_COLUMNS)
+            .if_not_exists()
+            .build();
+        let mut flagged = 0;
+        for flag in detect(&sales, &self.settings) {
+            let (trade_id, kind) = (flag.trade_id(), flag.kind().to_string());
+            if insert_query
+                .update_if(&self.db, &flag.into_query_values())
+                .await?
+            {
+                info!("Flagged the trade {0} as {1}", trade_id, kind);
+                flagged += 1;
+            }
+        }
+
+        Ok(flagged)
+    }
+
+    // Reads the sales from the day buckets, that overlap the lookback.
+    async fn get_sales(&self, since: DateTime<Utc>) -> Result<Vec<SaleEntry>> {
+        let mut sales = vec![];
+        let mut bucket = get_bucket(since);
+
+        while bucket <= Utc::now() {
+            let query = QueryBuilder::new(&SALE_TABLE)
+                .query_type(QueryType::Select)
+                .columns(&SALE_ALL_COLUMNS)
+                .filter_by(Filter::new("bucket", Operator::Eq, Some(bucket.into())))
+                .filter_by(Filter::new("sold_at", Operator::Gte, Some(since.into())))
+                .build();
+            sales.extend(query.get_entries::<SaleEntry>(&self.db).await?);
+            bucket += TimeDelta::days(1);
+        }
+
+        Ok(sales)
+    }
+}
+
+// The part of the trade event, that describes the sale.
+#[derive(Deserialize)]
+struct SoldTrade {
+    trade_id: Uuid,
+    item_id: Uuid,
+    quantity: i32,
+    seller: Uuid,
+    bidder: Option<Uuid>,
+    price: i64,
+    currency: String,
+}
+
+// Returns the sale recorded by the event, if the event completed one.
+// Expired trades are sold to their top bidder.
+fn get_sale(event: &OutboxEvent) -> Option<SaleEntry> {
+    if event.kind != "trade.bought_out" && event.kind != "trade.expired" {
+        return None;
+    }
+
+    let sold_trade = match SoldTrade::deserialize(&event.payload) {
+        Ok(sold_trade) => sold_trade,
+        Err(err) => {
+            // Retrying can't fix the payload, so the event is skipped
+            error!("Can't read the sale of the event {0}: {1}", event.id, err);
+            return None;
+        }
+    };
+    let buyer = sold_trade.bidder?;
+
+    let entry = SaleEntry::new(
+        sold_trade.trade_id,
+        sold_trade.item_id,
+        sold_trade.seller,
+        buyer,
+        Money::new(sold_trade.price),
+        sold_trade.quantity,
+        event.created_at,
+    );
+    Some(entry.with_currency(&sold_trade.currency))
+}
+
+// Stores the sales announced in the outbox for the wash trading scans.
+// The redelivered event overwrites the same sale.
+pub struct SaleLog {
+    db: CassandraSession,
+}
+
+impl SaleLog {
+    pub fn new(db: CassandraSession) -> Self {
+        Self { db }
+    }
+}
+
+#[tonic::async_trait]
+impl OutboxSink for SaleLog {
+    async fn publish(&self, event: &OutboxEvent) -> Result<()> {
+        let Some(entry) = get_sale(event) else {
+            return Ok(());
+        };
+
+        let query = QueryBuilder::new(&SALE_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&SALE_ALL_COLUMNS)
+            .build();
+        query.try_insert(&self.db, &entry.into_query_values()).await
+    }
+}
+
+pub fn create_wash_trading_detector(
+    opts: &CliOptions,
+    db: CassandraSession,
+) -> WashTradingDetector {
+    let settings = DetectionSettings {
+        pair_threshold: opts.wash_trading_pair_threshold,
+        price_multiplier: opts.wash_trading_price_multiplier,
+        min_samples: opts.wash_trading_min_samples,
+    };
+    let lookback = Duration::from_secs(opts.wash_trading_lookback_hours * 60 * 60);
+
+    WashTradingDetector::new(db, settings, lookback)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::core::money::Money;
+    use crate::models::outbox::OutboxEntry;
+    use crate::models::trade::{Trade, DEFAULT_CURRENCY};
+    use crate::models::trade_flag::FlagKind;
+    use crate::services::outbox::OutboxEvent;
+    use crate::services::trade_events::{TradeEvent, TradeEventKind};
+    use crate::services::wash_trading::{detect, get_sale, DetectionSettings, Sale};
+
+    fn create_sale(item_id: Uuid, seller: Uuid, buyer: Uuid, price: i64) -> Sale {
+        Sale {
+            trade_id: Uuid::new_v4(),
+            item_id,
+            seller,
+            buyer,
+            price: Money::new(price),
+            currency: DEFAULT_CURRENCY.to_string(),
+            quantity: 1,
+        }
+    }
+
+    fn settings() -> DetectionSettings {
+        DetectionSettings {
+            pair_threshold: 3,
+            price_multiplier: 5,
+            min_samples: 3,
+        }
+    }
+
+    fn count_flags(sales: &[Sale], kind: FlagKind) -> usize {
+        detect(sales, &settings())
+            .iter()
+            .filter(|flag| flag.kind() == kind.as_str())
+            .count()
+    }
+
+    #[test]
+    fn test_repeated_pair_is_flagged() {
+        let (seller, buyer) = (Uuid::new_v4(), Uuid::new_v4());
+        let sales = (0..3)
+            .map(|_| create_sale(Uuid::new_v4(), seller, buyer, 100))
+            .collect::<Vec<Sale>>();
+
+        assert_eq!(count_flags(&sales, FlagKind::RepeatedPair), 3);
+    }
+
+    #[test]
+    fn test_occasional_pair_is_not_flagged() {
+        let (seller, buyer) = (Uuid::new_v4(), Uuid::new_v4());
+        let sales = (0..2)
+            .map(|_| create_sale(Uuid::new_v4(), seller, buyer, 100))
+            .collect::<Vec<Sale>>();
+
+        assert_eq!(count_flags(&sales, FlagKind::RepeatedPair), 0);
+    }
+
+    #[test]
+    fn test_price_far_above_median_is_flagged() {
+        let item_id = Uuid::new_v4();
+        let sales = [100, 110, 90, 120, 5000]
+            .iter()
+            .map(|price| create_sale(item_id, Uuid::new_v4(), Uuid::new_v4(), *price))
+            .collect::<Vec<Sale>>();
+
+        let flags = detect(&sales, &settings());
+        let outliers = flags
+            .iter()
+            .filter(|flag| flag.kind() == FlagKind::PriceOutlier.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0].trade_id(), sales[4].trade_id);
+    }
+
+    #[test]
+    fn test_barter_sales_dont_lower_median_price() {
+        let item_id = Uuid::new_v4();
+        let sales = [0, 0, 0, 0, 100, 110, 90]
+            .iter()
+            .map(|price| create_sale(item_id, Uuid::new_v4(), Uuid::new_v4(), *price))
+            .collect::<Vec<Sale>>();
+
+        assert_eq!(count_flags(&sales, FlagKind::PriceOutlier), 0);
+    }
+
+    #[test]
+    fn test_price_outliers_need_enough_samples() {
+        let item_id = Uuid::new_v4();
+        let sales = [100, 5000]
+            .iter()
+            .map(|price| create_sale(item_id, Uuid::new_v4(), Uuid::new_v4(), *price))
+            .collect::<Vec<Sale>>();
+
+        assert_eq!(count_flags(&sales, FlagKind::PriceOutlier), 0);
+    }
+
+    #[test]
+    fn test_round_trip_is_flagged() {
+        let (first, second, item_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let sales = vec![
+            create_sale(item_id, first, second, 100),
+            create_sale(item_id, second, first, 100),
+            create_sale(item_id, first, Uuid::new_v4(), 100),
+        ];
+
+        assert_eq!(count_flags(&sales, FlagKind::RoundTrip), 2);
+    }
+
+    fn create_event(event: TradeEvent) -> OutboxEvent {
+        let payload = serde_json::to_string(&event).unwrap();
+        let entry = OutboxEntry::new(
+            &format!("trade.{0}", event.kind().as_str()),
+            event.trade_id(),
+            payload,
+        );
+        OutboxEvent::new(1, &entry)
+    }
+
+    #[test]
+    fn test_sale_is_read_from_closing_events() {
+        let trade = Trade::builder().currency("gems").build();
+        let buyer = Uuid::new_v4();
+        let bought_out =
+            TradeEvent::new(TradeEventKind::BoughtOut, &trade).with_bid(buyer, Money::new(1000));
+
+        let sale = Sale::from(&get_sale(&create_event(bought_out)).unwrap());
+        assert_eq!(sale.trade_id, trade.id());
+        assert_eq!(sale.seller, trade.created_by());
+        assert_eq!(sale.buyer, buyer);
+        assert_eq!(sale.price, Money::new(1000));
+        assert_eq!(sale.currency, "gems");
+
+        let won = TradeEvent::new(TradeEventKind::Expired, &trade).with_bid(buyer, Money::new(150));
+        assert!(get_sale(&create_event(won)).is_some());
+    }
+
+    #[test]
+    fn test_unsold_trades_are_not_sales() {
+        let trade = Trade::builder().currency("gems").build();
+
+        let expired = TradeEvent::new(TradeEventKind::Expired, &trade);
+        assert!(get_sale(&create_event(expired)).is_none());
+        let listed = TradeEvent::new(TradeEventKind::Listed, &trade);
+        assert!(get_sale(&create_event(listed)).is_none());
+    }
+}
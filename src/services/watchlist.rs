@@ -0,0 +1,271 @@
+use futures::future::try_join_all;
+use uuid::Uuid;
+
+use crate::core::error::{Error, Result};
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::trade::{Trade, TradeStatus, TRADE_ALL_COLUMNS, TRADE_TABLE};
+use crate::models::watchlist::{
+    WatchedItem, WatchlistEntry, WATCHED_ITEM_ALL_COLUMNS, WATCHED_ITEM_TABLE,
+    WATCHLIST_ALL_COLUMNS, WATCHLIST_TABLE,
+};
+
+// The maximum amount of trades the player can watch at the same time.
+const MAX_WATCHED_TRADES: usize = 100;
+// The maximum amount of items the player can watch in a realm. Every
+// watched item reads all its trades when the watchlist is listed.
+const MAX_WATCHED_ITEMS: usize = 20;
+
+// Keeps track of the trades and items followed by players.
+pub struct Watchlist {
+    db: CassandraSession,
+}
+
+impl Watchlist {
+    pub fn new(db: CassandraSession) -> Self {
+        Self { db }
+    }
+
+    // Adds the trade to the player's watchlist. Watching the same trade
+    // twice has no effect.
+    pub async fn watch(&self, player_id: Uuid, trade: &Trade) -> Result<()> {
+        let entries = self.get_entries(player_id).await?;
+        if entries.iter().any(|entry| entry.trade_id() == trade.id()) {
+            return Ok(());
+        }
+
+        if entries.len() >= MAX_WATCHED_TRADES {
+            return Err(Error::LimitExceeded {
+                subject: "watched_trades".to_string(),
+                message: format!(
+                    "The player can't watch more than {0} trades.",
+                    MAX_WATCHED_TRADES
+                ),
+                retry_after: None,
+            });
+        }
+
+        let query = QueryBuilder::new(&WATCHLIST_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&WATCHLIST_ALL_COLUMNS)
+            .build();
+        let query_values = WatchlistEntry::new(player_id, trade).into_query_values();
+        query.insert(&self.db, &query_values).await;
+
+        Ok(())
+    }
+
+    pub async fn unwatch(&self, player_id: Uuid, trade_id: Uuid) -> Result<()> {
+        let query = QueryBuilder::new(&WATCHLIST_TABLE)
+            .query_type(QueryType::Delete)
+            .filter_by(Filter::new("trade_id", Operator::Eq, Some(trade_id.into())))
+            .filter_by(Filter::new(
+                "player_id",
+                Operator::Eq,
+                Some(player_id.into()),
+            ))
+            .build();
+        query.delete(&self.db).await?;
+
+        Ok(())
+    }
+
+    // Returns the watched trades of the realm along with their current state,
+    // starting from the most recently watched. Trades that no longer exist
+    // are omitted.
+    pub async fn get_watched_trades(
+        &self,
+        realm_id: &str,
+        player_id: Uuid,
+    ) -> Result<Vec<(WatchlistEntry, Trade)>> {
+        let mut entries = self.get_entries(player_id).await?;
+        entries.retain(|entry| entry.realm_id() == realm_id);
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.created_at()));
+
+        let trades = try_join_all(entries.iter().map(|entry| self.get_trade(entry))).await?;
+
+        Ok(entries
+            .into_iter()
+            .zip(trades)
+            .filter_map(|(entry, trade)| trade.map(|trade| (entry, trade)))
+            .collect())
+    }
+
+    // Adds the item to the player's watchlist in the realm. Watching the same
+    // item twice has no effect.
+    pub async fn watch_item(&self, realm_id: &str, player_id: Uuid, item_id: Uuid) -> Result<()> {
+        let items = self.get_items(realm_id, player_id).await?;
+        if items.iter().any(|item| item.item_id() == item_id) {
+            return Ok(());
+        }
+
+        if items.len() >= MAX_WATCHED_ITEMS {
+            return Err(Error::LimitExceeded {
+                subject: "watched_items".to_string(),
+                message: format!(
+                    "The player can't watch more than {0} items.",
+                    MAX_WATCHED_ITEMS
+                ),
+                retry_after: None,
+            });
+        }
+
+        let query = QueryBuilder::new(&WATCHED_ITEM_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&WATCHED_ITEM_ALL_COLUMNS)
+            .build();
+        let query_values = WatchedItem::new(player_id, realm_id, item_id).into_query_values();
+        query.try_insert(&self.db, &query_values).await
+    }
+
+    pub async fn unwatch_item(&self, realm_id: &str, player_id: Uuid, item_id: Uuid) -> Result<()> {
+        let query = QueryBuilder::new(&WATCHED_ITEM_TABLE)
+            .query_type(QueryType::Delete)
+            .filter_by(Filter::new(
+                "player_id",
+                Operator::Eq,
+                Some(player_id.into()),
+            ))
+            .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+            .filter_by(Filter::new("item_id", Operator::Eq, Some(item_id.into())))
+            .build();
+        query.delete(&self.db).await?;
+
+        Ok(())
+    }
+
+    // Returns the watched items of the realm along with their active trades,
+    // that the player can buy, starting from the most recently watched.
+    pub async fn get_watched_items(
+        &self,
+        realm_id: &str,
+        player_id: Uuid,
+    ) -> Result<Vec<(WatchedItem, Vec<Trade>)>> {
+        let mut items = self.get_items(realm_id, player_id).await?;
+        items.sort_by_key(|item| std::cmp::Reverse(item.created_at()));
+
+        let trades = try_join_all(
+            items
+                .iter()
+                .map(|item| self.get_item_trades(realm_id, item.item_id(), player_id)),
+        )
+        .await?;
+
+        Ok(items.into_iter().zip(trades).collect())
+    }
+
+    async fn get_entries(&self, player_id: Uuid) -> Result<Vec<WatchlistEntry>> {
+        let query = QueryBuilder::new(&WATCHLIST_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&WATCHLIST_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "player_id",
+                Operator::Eq,
+                Some(player_id.into()),
+            ))
+            .build();
+
+        query.get_entries::<WatchlistEntry>(&self.db).await
+    }
+
+    async fn get_trade(&self, entry: &WatchlistEntry) -> Result<Option<Trade>> {
+        let query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "realm_id",
+                Operator::Eq,
+                Some(entry.realm_id().into()),
+            ))
+            .filter_by(Filter::new(
+                "id",
+                Operator::Eq,
+                Some(entry.trade_id().into()),
+            ))
+            .filter_by(Filter::new(
+                "item_id",
+                Operator::Eq,
+                Some(entry.item_id().into()),
+            ))
+            .limit(1)
+            .build();
+        let trades = query.get_entries::<Trade>(&self.db).await?;
+
+        Ok(trades.into_iter().next())
+    }
+
+    async fn get_items(&self, realm_id: &str, player_id: Uuid) -> Result<Vec<WatchedItem>> {
+        let query = QueryBuilder::new(&WATCHED_ITEM_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&WATCHED_ITEM_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "player_id",
+                Operator::Eq,
+                Some(player_id.into()),
+            ))
+            .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+            .build();
+
+        query.get_entries::<WatchedItem>(&self.db).await
+    }
+
+    async fn get_item_trades(
+        &self,
+        realm_id: &str,
+        item_id: Uuid,
+        player_id: Uuid,
+    ) -> Result<Vec<Trade>> {
+        let query = QueryBuilder::new(&TRADE_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&TRADE_ALL_COLUMNS)
+            .filter_by(Filter::new("realm_id", Operator::Eq, Some(realm_id.into())))
+            .filter_by(Filter::new("item_id", Operator::Eq, Some(item_id.into())))
+            .filter_by(Filter::new("is_deleted", Operator::Eq, Some(false.into())))
+            .allow_filtering(true)
+            .build();
+        let trades = query.get_entries::<Trade>(&self.db).await?;
+
+        Ok(trades
+            .into_iter()
+            .filter(|trade| is_watched_item_trade(trade, player_id))
+            .collect())
+    }
+}
+
+// Watched items show only the trades, that the player can buy right away.
+fn is_watched_item_trade(trade: &Trade, player_id: Uuid) -> bool {
+    trade.current_status() == TradeStatus::Active
+        && trade.created_by() != player_id
+        && trade.reserved_for().is_none_or(|buyer| buyer == player_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::models::trade::Trade;
+    use crate::services::watchlist::is_watched_item_trade;
+
+    #[test]
+    fn test_watched_item_shows_trades_the_player_can_buy() {
+        let player_id = Uuid::new_v4();
+        let seller = Uuid::new_v4();
+
+        let listed = Trade::builder().created_by(seller).build();
+        let reserved = Trade::builder()
+            .created_by(seller)
+            .reserved_for(Some(player_id))
+            .build();
+        let reserved_for_other = Trade::builder()
+            .created_by(seller)
+            .reserved_for(Some(Uuid::new_v4()))
+            .build();
+        let own = Trade::builder().created_by(player_id).build();
+
+        assert!(is_watched_item_trade(&listed, player_id));
+        assert!(is_watched_item_trade(&reserved, player_id));
+        assert!(!is_watched_item_trade(&reserved_for_other, player_id));
+        assert!(!is_watched_item_trade(&own, player_id));
+    }
+}
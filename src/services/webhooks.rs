@@ -0,0 +1,557 @@
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use futures::future::join_all;
+use hmac::{Hmac, Mac};
+use hyper::client::HttpConnector;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Method, Request, StatusCode, Uri};
+use log::{error, info, warn};
+use serde::Serialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::cli::CliOptions;
+use crate::core::error::Result;
+use crate::core::orm::filter::{Filter, Operator};
+use crate::core::orm::query_builder::{QueryBuilder, QueryType};
+use crate::core::orm::session::CassandraSession;
+use crate::models::webhook_dead_letter::{
+    WEBHOOK_DEAD_LETTER_ALL_COLUMNS, WEBHOOK_DEAD_LETTER_TABLE,
+};
+use crate::models::webhook_retry::{WebhookRetry, WEBHOOK_RETRY_ALL_COLUMNS, WEBHOOK_RETRY_TABLE};
+use crate::services::lease::{Lease, LEASE_INTERVALS};
+use crate::services::outbox::sinks::OutboxSink;
+use crate::services::outbox::OutboxEvent;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// The hex-encoded HMAC-SHA256 of the `{timestamp}.{body}` string, prefixed with `sha256=`.
+pub const SIGNATURE_HEADER: &str = "x-trading-post-signature";
+// The unix timestamp of the attempt, so that receivers can reject replayed requests.
+pub const TIMESTAMP_HEADER: &str = "x-trading-post-timestamp";
+// The same for all attempts of the payload, so that receivers can skip duplicates.
+pub const DELIVERY_HEADER: &str = "x-trading-post-delivery";
+
+// The time given to the endpoint to answer a single attempt.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// The upper bound for the delay between attempts.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+// Returns the name of the webhook event, when game servers need to know
+// about the outbox event.
+pub fn get_webhook_event(kind: &str) -> Option<&'static str> {
+    match kind {
+        "trade.bought_out" => Some("sale"),
+        "trade.outbid" => Some("outbid"),
+        "trade.expired" => Some("expiry"),
+        _ => None,
+    }
+}
+
+pub fn sign(secret: &[u8], timestamp: i64, body: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("create hmac with any key size");
+    mac.update(format!("{0}.{1}", timestamp, body).as_bytes());
+    format!("sha256={0}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[derive(Serialize, Debug)]
+pub struct WebhookPayload<'a> {
+    // The id of the outbox event, the same for its redeliveries.
+    pub id: Uuid,
+    pub event: &'static str,
+    pub trade: &'a serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, initial_delay: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_delay,
+        }
+    }
+
+    pub fn can_retry(&self, attempts: u32) -> bool {
+        attempts < self.max_attempts
+    }
+
+    // The delay after the failed attempt doubles with every attempt.
+    pub fn get_delay(&self, attempt: u32) -> Duration {
+        let multiplier = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(multiplier)
+            .min(MAX_RETRY_DELAY)
+    }
+}
+
+#[derive(Debug)]
+pub enum DeliveryFailure {
+    // The endpoint might accept the payload later.
+    Retryable(String),
+    // The endpoint refused the payload, so repeating it makes no sense.
+    Permanent(String),
+}
+
+// Sends the signed payloads to a single endpoint.
+pub struct WebhookClient {
+    client: Client<HttpConnector>,
+    secret: Vec<u8>,
+}
+
+impl WebhookClient {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            client: Client::new(),
+            secret: secret.as_bytes().to_vec(),
+        }
+    }
+
+    // Makes a single attempt, the failed ones are retried by the caller.
+    pub async fn deliver(
+        &self,
+        endpoint: &Uri,
+        id: Uuid,
+        body: &str,
+    ) -> std::result::Result<(), DeliveryFailure> {
+        let timestamp = Utc::now().timestamp();
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(endpoint.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .header(DELIVERY_HEADER, id.to_string())
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign(&self.secret, timestamp, body))
+            .body(Body::from(body.to_string()))
+            .expect("build webhook request");
+
+        let response = tokio::time::timeout(REQUEST_TIMEOUT, self.client.request(request))
+            .await
+            .map_err(|_| DeliveryFailure::Retryable("The endpoint timed out.".to_string()))?
+            .map_err(|err| {
+                DeliveryFailure::Retryable(format!("The endpoint is unavailable: {0}", err))
+            })?;
+
+        let status = response.status();
+        match status {
+            _ if status.is_success() => Ok(()),
+            StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS => Err(
+                DeliveryFailure::Retryable(format!("The endpoint responded with {0}.", status)),
+            ),
+            _ if status.is_client_error() => Err(DeliveryFailure::Permanent(format!(
+                "The endpoint responded with {0}.",
+                status
+            ))),
+            _ => Err(DeliveryFailure::Retryable(format!(
+                "The endpoint responded with {0}.",
+                status
+            ))),
+        }
+    }
+}
+
+// Notifies game servers about sales, outbids and expired trades, so that
+// they can tell the players in-game. Driven by the outbox relay, so the
+// events are delivered once per cluster and survive restarts. The relay
+// makes a single attempt, so that a slow endpoint doesn't hold it up for
+// longer than its lease. The failed payloads are stored with the time of
+// the next attempt and sent again by the retry worker, until they are
+// moved to the dead-letter table.
+pub struct GameServerWebhooks {
+    db: CassandraSession,
+    client: WebhookClient,
+    retry_policy: RetryPolicy,
+    endpoints: Vec<Uri>,
+}
+
+impl GameServerWebhooks {
+    pub fn new(
+        db: CassandraSession,
+        client: WebhookClient,
+        retry_policy: RetryPolicy,
+        endpoints: Vec<Uri>,
+    ) -> Self {
+        Self {
+            db,
+            client,
+            retry_policy,
+            endpoints,
+        }
+    }
+
+    pub async fn run(self, interval: Duration) {
+        let lease = Lease::new(
+            self.db.clone(),
+            "game_server_webhooks",
+            interval * LEASE_INTERVALS,
+        );
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match lease.acquire().await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(err) => {
+                    error!("Can't acquire the game server webhooks lease: {0}", err);
+                    continue;
+                }
+            }
+
+            match self.retry(&lease).await {
+                Ok(0) => {}
+                Ok(delivered) => info!("{0} webhooks delivered on retry", delivered),
+                Err(err) => error!("Can't retry the webhooks: {0}", err),
+            }
+        }
+    }
+
+    // Sends the payloads due for the next attempt. Stops once the lease is
+    // lost, so that the replica that took it over doesn't send them again.
+    pub async fn retry(&self, lease: &Lease) -> Result<usize> {
+        let mut delivered = 0;
+
+        for endpoint in &self.endpoints {
+            for retry in self.get_retries(endpoint).await? {
+                if !retry.is_due(Utc::now()) {
+                    continue;
+                }
+                if !lease.keep().await? {
+                    warn!(
+                        "Webhook retries lost the lease after {0} webhooks",
+                        delivered
+                    );
+                    return Ok(delivered);
+                }
+
+                match self
+                    .client
+                    .deliver(endpoint, retry.id(), retry.payload())
+                    .await
+                {
+                    Ok(()) => {
+                        self.remove(endpoint, retry.id()).await?;
+                        delivered += 1;
+                    }
+                    Err(failure) => self.fail(endpoint, retry, failure).await?,
+                }
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    // Fails only when the failed payload can't be stored, so that the relay
+    // publishes the event again.
+    async fn deliver(
+        &self,
+        endpoint: &Uri,
+        event: &OutboxEvent,
+        name: &str,
+        body: &str,
+    ) -> Result<()> {
+        let failure = match self.client.deliver(endpoint, event.id, body).await {
+            Ok(()) => return Ok(()),
+            Err(failure) => failure,
+        };
+
+        let retry = WebhookRetry::new(
+            &endpoint.to_string(),
+            event.id,
+            name,
+            event.aggregate_id,
+            body.to_string(),
+        );
+        self.fail(endpoint, retry, failure).await
+    }
+
+    // Schedules the next attempt, or moves the payload to the dead-letter
+    // table when it was refused or there are no attempts left.
+    async fn fail(
+        &self,
+        endpoint: &Uri,
+        mut retry: WebhookRetry,
+        failure: DeliveryFailure,
+    ) -> Result<()> {
+        let (reason, retryable) = match failure {
+            DeliveryFailure::Retryable(reason) => (reason, true),
+            DeliveryFailure::Permanent(reason) => (reason, false),
+        };
+        let delay = self.retry_policy.get_delay(retry.attempts() + 1);
+        retry.fail(
+            reason,
+            Utc::now() + TimeDelta::from_std(delay).unwrap_or(TimeDelta::MAX),
+        );
+
+        if retryable && self.retry_policy.can_retry(retry.attempts()) {
+            let query = QueryBuilder::new(&WEBHOOK_RETRY_TABLE)
+                .query_type(QueryType::Insert)
+                .columns(&WEBHOOK_RETRY_ALL_COLUMNS)
+                .build();
+            return query.try_insert(&self.db, &retry.into_query_values()).await;
+        }
+
+        error!(
+            "Webhook {0} to {1} failed after {2} attempts: {3}",
+            retry.id(),
+            endpoint,
+            retry.attempts(),
+            retry.last_error()
+        );
+        let id = retry.id();
+        let dead_letter = retry.into_dead_letter();
+        let query = QueryBuilder::new(&WEBHOOK_DEAD_LETTER_TABLE)
+            .query_type(QueryType::Insert)
+            .columns(&WEBHOOK_DEAD_LETTER_ALL_COLUMNS)
+            .build();
+        query
+            .try_insert(&self.db, &dead_letter.into_query_values())
+            .await?;
+        self.remove(endpoint, id).await
+    }
+
+    async fn get_retries(&self, endpoint: &Uri) -> Result<Vec<WebhookRetry>> {
+        let query = QueryBuilder::new(&WEBHOOK_RETRY_TABLE)
+            .query_type(QueryType::Select)
+            .columns(&WEBHOOK_RETRY_ALL_COLUMNS)
+            .filter_by(Filter::new(
+                "endpoint",
+                Operator::Eq,
+                Some(endpoint.to_string().into()),
+            ))
+            .build();
+
+        query.get_entries::<WebhookRetry>(&self.db).await
+    }
+
+    async fn remove(&self, endpoint: &Uri, id: Uuid) -> Result<()> {
+        let query = QueryBuilder::new(&WEBHOOK_RETRY_TABLE)
+            .query_type(QueryType::Delete)
+            .filter_by(Filter::new(
+                "endpoint",
+                Operator::Eq,
+                Some(endpoint.to_string().into()),
+            ))
+            .filter_by(Filter::new("id", Operator::Eq, Some(id.into())))
+            .build();
+        query.delete(&self.db).await?;
+
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl OutboxSink for GameServerWebhooks {
+    // The endpoints are notified at the same time, and the relay waits for
+    // all of them before the next event. So the amount of pending requests
+    // never exceeds the amount of endpoints.
+    async fn publish(&self, event: &OutboxEvent) -> Result<()> {
+        let Some(name) = get_webhook_event(&event.kind) else {
+            return Ok(());
+        };
+        let payload = WebhookPayload {
+            id: event.id,
+            event: name,
+            trade: &event.payload,
+        };
+        let body = serde_json::to_string(&payload).expect("serialize webhook payload");
+
+        join_all(
+            self.endpoints
+                .iter()
+                .map(|endpoint| self.deliver(endpoint, event, name, &body)),
+        )
+        .await
+        .into_iter()
+        .collect()
+    }
+}
+
+pub fn create_game_server_webhooks(
+    opts: &CliOptions,
+    db: CassandraSession,
+) -> Option<GameServerWebhooks> {
+    let endpoints = opts
+        .game_server_webhook_urls
+        .split(',')
+        .map(|url| url.trim())
+        .filter(|url| !url.is_empty())
+        .map(|url| url.parse::<Uri>().expect("parse webhook url"))
+        .collect::<Vec<Uri>>();
+
+    if endpoints.is_empty() {
+        return None;
+    }
+
+    let secret = opts
+        .game_server_webhook_secret
+        .as_ref()
+        .expect("--game-server-webhook-secret is required for webhooks");
+    let retry_policy = RetryPolicy::new(
+        opts.webhook_max_attempts,
+        Duration::from_millis(opts.webhook_retry_delay),
+    );
+
+    Some(GameServerWebhooks::new(
+        db,
+        WebhookClient::new(secret),
+        retry_policy,
+        endpoints,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::convert::Infallible;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, HeaderMap, Request, Response, Server, StatusCode, Uri};
+    use uuid::Uuid;
+
+    use crate::services::webhooks::{
+        get_webhook_event, sign, DeliveryFailure, RetryPolicy, WebhookClient, DELIVERY_HEADER,
+        SIGNATURE_HEADER, TIMESTAMP_HEADER,
+    };
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+    // Starts the game server stand-in, which answers with the given statuses
+    // in order and with 200 OK afterwards.
+    fn start_game_server(statuses: Vec<StatusCode>) -> (Uri, Received) {
+        let statuses = Arc::new(Mutex::new(VecDeque::from(statuses)));
+        let received: Received = Arc::new(Mutex::new(vec![]));
+        let state = (statuses, received.clone());
+
+        let make_service = make_service_fn(move |_| {
+            let (statuses, received) = state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let (statuses, received) = (statuses.clone(), received.clone());
+                    async move {
+                        let (parts, body) = request.into_parts();
+                        let body = hyper::body::to_bytes(body).await.unwrap();
+                        received
+                            .lock()
+                            .unwrap()
+                            .push((parts.headers, String::from_utf8(body.to_vec()).unwrap()));
+                        let status = statuses
+                            .lock()
+                            .unwrap()
+                            .pop_front()
+                            .unwrap_or(StatusCode::OK);
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(status)
+                                .body(Body::empty())
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let endpoint = format!("http://{0}/webhooks", server.local_addr())
+            .parse()
+            .unwrap();
+        tokio::spawn(server);
+        (endpoint, received)
+    }
+
+    #[test]
+    fn test_only_game_server_events_are_sent() {
+        assert_eq!(get_webhook_event("trade.bought_out"), Some("sale"));
+        assert_eq!(get_webhook_event("trade.outbid"), Some("outbid"));
+        assert_eq!(get_webhook_event("trade.expired"), Some("expiry"));
+        assert_eq!(get_webhook_event("trade.bid"), None);
+        assert_eq!(get_webhook_event("trade.listed"), None);
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_limit() {
+        let policy = RetryPolicy::new(5, Duration::from_secs(1));
+
+        assert_eq!(policy.get_delay(1), Duration::from_secs(1));
+        assert_eq!(policy.get_delay(2), Duration::from_secs(2));
+        assert_eq!(policy.get_delay(4), Duration::from_secs(8));
+        assert_eq!(policy.get_delay(20), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_retries_stop_after_all_attempts() {
+        let policy = RetryPolicy::new(3, Duration::from_secs(1));
+
+        assert!(policy.can_retry(1));
+        assert!(policy.can_retry(2));
+        assert!(!policy.can_retry(3));
+        assert!(!RetryPolicy::new(0, Duration::from_secs(1)).can_retry(1));
+    }
+
+    #[tokio::test]
+    async fn test_payload_is_signed() {
+        let (endpoint, received) = start_game_server(vec![]);
+        let id = Uuid::new_v4();
+
+        WebhookClient::new("secret")
+            .deliver(&endpoint, id, r#"{"event":"sale"}"#)
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        let (headers, body) = &received[0];
+        let timestamp = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(headers[DELIVERY_HEADER].to_str().unwrap(), id.to_string());
+        assert_eq!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            sign(b"secret", timestamp, body)
+        );
+        assert_ne!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            sign(b"other", timestamp, body)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_endpoint_is_retried_later() {
+        let (endpoint, received) = start_game_server(vec![
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::TOO_MANY_REQUESTS,
+        ]);
+        let client = WebhookClient::new("secret");
+        let id = Uuid::new_v4();
+
+        for _ in 0..2 {
+            let failure = client.deliver(&endpoint, id, "{}").await.unwrap_err();
+            assert!(matches!(failure, DeliveryFailure::Retryable(_)));
+        }
+        client.deliver(&endpoint, id, "{}").await.unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+        assert!(received
+            .iter()
+            .all(|(headers, _)| headers[DELIVERY_HEADER].to_str().unwrap() == id.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_payload_is_not_retried() {
+        let (endpoint, received) = start_game_server(vec![StatusCode::BAD_REQUEST]);
+
+        let failure = WebhookClient::new("secret")
+            .deliver(&endpoint, Uuid::new_v4(), "{}")
+            .await
+            .unwrap_err();
+
+        assert!(matches!(failure, DeliveryFailure::Permanent(_)));
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+}